@@ -0,0 +1,598 @@
+//! A module that containers the core of the arena allocator
+#![allow(clippy::new_without_default)]
+#![allow(unused)]
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+use core::num::NonZeroUsize;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::token::Token;
+
+/// How the arena grows once it runs out of free cells. See
+/// [`Arena::set_growth`].
+///
+/// [`Arena::set_growth`]: struct.Arena.html#method.set_growth
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GrowthStrategy {
+    /// Grow by the current capacity, doubling it. This is the default.
+    #[default]
+    Double,
+    /// Grow by the same number of cells every time.
+    Fixed(usize),
+    /// Grow by the given number of cells more than the previous growth, i.e.
+    /// by `n`, `2n`, `3n` and so on.
+    Additive(usize)
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Allocator<T> {
+    data: Vec<Cell<T>>,
+    head: Option<NonZeroUsize>,
+    // the last cell of the free list, so that new cells can be linked in
+    // without walking the list
+    tail: Option<NonZeroUsize>,
+    len: usize,
+    // the generation of each cell, bumped whenever its occupant is removed so
+    // that tokens to removed nodes do not pick up whatever moves in next
+    generations: Vec<u32>,
+    growth: GrowthStrategy,
+    last_growth: usize
+}
+
+/// An iterator that moves the data out of the occupied cells in storage
+/// order.
+pub struct IntoIter<T> {
+    iter: vec::IntoIter<Cell<T>>,
+    remaining: usize  // the number of occupied cells left
+}
+
+/// An iterator of references to the data in the occupied cells in storage
+/// order.
+pub struct Iter<'a, T> {
+    iter: core::slice::Iter<'a, Cell<T>>,
+    remaining: usize
+}
+
+/// An iterator of mutable references to the data in the occupied cells in
+/// storage order.
+pub struct IterMut<'a, T> {
+    iter: core::slice::IterMut<'a, Cell<T>>,
+    remaining: usize
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Cell<T> {
+    Just(T),
+    Nothing(Option<NonZeroUsize>)
+}
+
+impl<T> Default for Allocator<T> {
+    fn default() -> Self {
+        Allocator {
+            data: vec![Cell::Nothing(None)],
+            head: Some(NonZeroUsize::new(1).unwrap()),
+            tail: Some(NonZeroUsize::new(1).unwrap()),
+            len: 0,
+            generations: vec![0],
+            growth: GrowthStrategy::Double,
+            last_growth: 0
+        }
+    }
+}
+
+impl<T> Allocator<T> {
+    pub fn new() -> Self {
+        Allocator {
+            data: vec![Cell::Nothing(None)],
+            head: Some(NonZeroUsize::new(1).unwrap()),
+            tail: Some(NonZeroUsize::new(1).unwrap()),
+            len: 0,
+            generations: vec![0],
+            growth: GrowthStrategy::Double,
+            last_growth: 0
+        }
+    }
+
+    pub fn head(&mut self) -> Token {
+        match self.head {
+            Some(head) => self.token(head),
+            None => {
+                self.grow();
+                self.head()
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize { self.len }
+
+    /// Makes a token for the cell at the given (one-based) index with its
+    /// current generation.
+    fn token(&self, index: NonZeroUsize) -> Token {
+        Token::new(index, self.generations[index.get() - 1])
+    }
+
+    /// The zero-based index of the cell the token points to if the token is
+    /// of the current generation of the cell.
+    fn position(&self, token: Token) -> Option<usize> {
+        let i = token.slot();
+        match self.generations.get(i) == Some(&token.generation) {
+            true => Some(i),
+            false => None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    pub fn capacity(&self) -> usize { self.data.len() }
+
+    /// The number of bytes the backing storage takes for each cell.
+    pub fn cell_size() -> usize { mem::size_of::<Cell<T>>() }
+
+    pub fn is_valid_token(&self, token: Token) -> bool {
+        self.get(token).is_some()
+    }
+
+    pub fn growth(&self) -> GrowthStrategy { self.growth }
+
+    pub fn set_growth(&mut self, growth: GrowthStrategy) {
+        self.growth = growth;
+        self.last_growth = 0;
+    }
+
+    /// Adds free cells according to the growth strategy. Always adds at least
+    /// one cell.
+    fn grow(&mut self) {
+        let additional = match self.growth {
+            GrowthStrategy::Double => self.capacity(),
+            GrowthStrategy::Fixed(n) => n,
+            GrowthStrategy::Additive(n) => self.last_growth + n
+        }.max(1);
+        self.last_growth = additional;
+        self.reserve(additional);
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        if additional == 0 { return }
+        self.data.reserve_exact(additional);
+        let head_indx = NonZeroUsize::new(self.data.len() + 1).unwrap();
+        match self.tail {
+            None => self.head = Some(head_indx),
+            Some(n) => self.data[n.get() - 1] = Cell::Nothing(Some(head_indx)),
+        };
+        let new_cells = (head_indx.get()..)  // already bigger by 1
+            .take(additional - 1)
+            .map(|i| Cell::Nothing(Some(NonZeroUsize::new(i + 1).unwrap())))
+            .chain(core::iter::once(Cell::Nothing(None)));
+        self.data.extend(new_cells);
+        if self.generations.len() < self.data.len() {
+            self.generations.resize(self.data.len(), 0);
+        }
+        self.tail = NonZeroUsize::new(self.data.len());
+    }
+
+    /// Moves the generations of every cell, including the ones past the
+    /// current capacity of `previous`, beyond all generations of `previous`
+    /// such that none of its tokens resolve in this allocator. Returns the new
+    /// generation of the cells.
+    pub fn succeed(&mut self, previous: &Self) -> u32 {
+        let generation = previous.generations.iter()
+            .max()
+            .map_or(0, |g| g.wrapping_add(1));
+        let len = self.generations.len().max(previous.generations.len());
+        self.generations = vec![generation; len];
+        generation
+    }
+
+    /// Releases the free cells past the last occupied cell and rebuilds the
+    /// free list over the remaining free cells in ascending order. The
+    /// generations of the released cells are kept so that tokens to nodes
+    /// removed from them stay invalid once the cells are added back.
+    pub fn shrink_to_fit(&mut self) {
+        let used = self.data.iter()
+            .rposition(|cell| match cell {
+                Cell::Just(_) => true,
+                Cell::Nothing(_) => false
+            })
+            .map_or(0, |i| i + 1);
+        self.data.truncate(used);
+        self.data.shrink_to_fit();
+        self.head = None;
+        self.tail = None;
+        for i in (0..used).rev() {
+            if let Cell::Nothing(next_head) = &mut self.data[i] {
+                *next_head = self.head;
+                self.head = NonZeroUsize::new(i + 1);  // one-based
+                if self.tail.is_none() { self.tail = self.head }
+            }
+        }
+    }
+
+    pub fn insert(&mut self, data: T) -> Token {
+        match self.head {
+            None => {
+                self.grow();
+                self.insert(data)
+            },
+            Some(index) => {
+                let i = index.get() - 1;  // zero-based index
+                let next_head = match self.data.get(i) {
+                    Some(Cell::Just(_)) | None => panic!("corrupt arena"),
+                    Some(Cell::Nothing(next_head)) => next_head
+                };
+                self.head = *next_head;
+                if self.head.is_none() { self.tail = None }
+                self.len += 1;
+                self.data[i] = Cell::Just(data);
+                self.token(index)
+            }
+        }
+    }
+
+    pub fn set(&mut self, token: Token, data: T) -> Option<T> {
+        let out = self.remove(token);
+        self.insert(data);
+        out
+    }
+
+    /// Replaces the data in an occupied cell with the result of the closure
+    /// without freeing the cell, so the token stays valid. Returns false if
+    /// the token is invalid. Should the closure panic, the cell is freed as if
+    /// the data had been removed.
+    pub fn update<F>(&mut self, token: Token, f: F) -> bool
+        where F: FnOnce(T) -> T {
+        let i = match self.position(token) {
+            Some(i) => i,
+            None => return false
+        };
+        let data = match mem::replace(&mut self.data[i], Cell::Nothing(None)) {
+            Cell::Just(data) => data,
+            nothing => { self.data[i] = nothing; return false }
+        };
+        let guard = ReleaseOnUnwind { allocator: self, index: i };
+        let data = f(data);
+        mem::forget(guard);
+        self.data[i] = Cell::Just(data);
+        true
+    }
+
+    pub fn remove(&mut self, token: Token) -> Option<T> {
+        let i = self.position(token)?;
+        match self.data.get_mut(i) {
+            Some(Cell::Nothing(_)) | None => None,
+            Some(cell) => match mem::replace(cell, Cell::Nothing(None)) {
+                Cell::Just(data) => {
+                    self.release(i);
+                    Some(data)
+                },
+                _ => panic!("something is wrong with the code")
+            }
+        }
+    }
+
+    /// Puts the cell at the given (zero-based) index, whose data has already
+    /// been taken out, at the head of the free list.
+    fn release(&mut self, i: usize) {
+        self.generations[i] = self.generations[i].wrapping_add(1);
+        self.data[i] = Cell::Nothing(self.head);
+        self.head = NonZeroUsize::new(i + 1);  // one-based
+        if self.tail.is_none() { self.tail = self.head }
+        self.len -= 1;
+    }
+
+    /// Checks that the free list covers exactly the unoccupied cells and that
+    /// `len` agrees with the number of occupied cells.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.generations.len() < self.capacity() {
+            return Err(format!("{} generations for {} cells",
+                               self.generations.len(), self.capacity()))
+        }
+        let occupied = self.iter().count();
+        if occupied != self.len {
+            return Err(format!("len is {} but {} cells are occupied",
+                               self.len, occupied))
+        }
+        let free = self.capacity() - occupied;
+        let mut steps = 0;
+        let mut last = None;
+        let mut next_head = self.head;
+        while let Some(indx) = next_head {
+            steps += 1;
+            last = Some(indx);
+            if steps > free {
+                return Err(String::from("free list is longer than the number \
+                                         of free cells or contains a cycle"))
+            }
+            next_head = match self.data.get(indx.get() - 1) {
+                None => return Err(format!("free list points to cell {} which \
+                                            is out of bounds", indx)),
+                Some(Cell::Just(_)) => return Err(format!("free list points \
+                                                           to occupied cell {}",
+                                                           indx)),
+                Some(Cell::Nothing(n)) => *n
+            };
+        }
+        if last != self.tail {
+            return Err(format!("free list ends at {:?} but its tail is {:?}",
+                               last, self.tail))
+        }
+        match steps == free {
+            true => Ok(()),
+            false => Err(format!("free list covers {} of {} free cells",
+                                 steps, free))
+        }
+    }
+
+    /// Iterates over the occupied cells along with their tokens.
+    pub fn iter(&self) -> impl Iterator<Item=(Token, &T)> {
+        self.data.iter().enumerate().filter_map(move |(i, cell)| match cell {
+            Cell::Nothing(_) => None,
+            Cell::Just(data) => {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                Some((self.token(index), data))
+            }
+        })
+    }
+
+    /// Iterates over the data in occupied cells.
+    pub fn values(&self) -> Iter<'_, T> {
+        Iter { iter: self.data.iter(), remaining: self.len }
+    }
+
+    /// Iterates over the data in occupied cells mutably.
+    pub fn values_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { iter: self.data.iter_mut(), remaining: self.len }
+    }
+
+    /// Maps the data in occupied cells while keeping the memory layout.
+    pub fn map<U, F>(&self, mut f: F) -> Allocator<U>
+        where F: FnMut(Token, &T) -> U {
+        let data = self.data.iter().enumerate().map(|(i, cell)| match cell {
+            Cell::Nothing(next_head) => Cell::Nothing(*next_head),
+            Cell::Just(data) => {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                Cell::Just(f(self.token(index), data))
+            }
+        }).collect();
+        Allocator {
+            data,
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+            generations: self.generations.clone(),
+            growth: self.growth,
+            last_growth: self.last_growth
+        }
+    }
+
+    /// Maps the data in occupied cells while keeping the memory layout,
+    /// consuming the allocator.
+    pub fn into_map<U, F>(self, mut f: F) -> Allocator<U>
+        where F: FnMut(Token, T) -> U {
+        let generations = self.generations;
+        let data = self.data.into_iter().enumerate().map(|(i, cell)| match cell {
+            Cell::Nothing(next_head) => Cell::Nothing(next_head),
+            Cell::Just(data) => {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                Cell::Just(f(Token::new(index, generations[i]), data))
+            }
+        }).collect();
+        Allocator {
+            data,
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+            generations,
+            growth: self.growth,
+            last_growth: self.last_growth
+        }
+    }
+
+    /// Like `into_map` but stops at the first error, which is returned along
+    /// with the token of the offending cell.
+    pub fn try_into_map<U, E, F>(self, mut f: F) -> Result<Allocator<U>, (Token, E)>
+        where F: FnMut(Token, T) -> Result<U, E> {
+        let generations = self.generations;
+        let data = self.data.into_iter().enumerate().map(|(i, cell)| match cell {
+            Cell::Nothing(next_head) => Ok(Cell::Nothing(next_head)),
+            Cell::Just(data) => {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                let token = Token::new(index, generations[i]);
+                match f(token, data) {
+                    Ok(data) => Ok(Cell::Just(data)),
+                    Err(e) => Err((token, e))
+                }
+            }
+        }).collect::<Result<_, _>>()?;
+        Ok(Allocator {
+            data,
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+            generations,
+            growth: self.growth,
+            last_growth: self.last_growth
+        })
+    }
+
+    /// Iterates over the occupied cells along with their tokens in parallel.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter(&self) -> impl ParallelIterator<Item=(Token, &T)>
+        where T: Sync {
+        self.data.par_iter().enumerate().filter_map(move |(i, cell)| match cell {
+            Cell::Nothing(_) => None,
+            Cell::Just(data) => {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                Some((self.token(index), data))
+            }
+        })
+    }
+
+    /// Iterates over the occupied cells along with their tokens in parallel,
+    /// mutably.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item=(Token, &mut T)>
+        where T: Send {
+        let generations = &self.generations;
+        self.data.par_iter_mut().enumerate().filter_map(move |(i, cell)| match cell {
+            Cell::Nothing(_) => None,
+            Cell::Just(data) => {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                Some((Token::new(index, generations[i]), data))
+            }
+        })
+    }
+
+    #[cfg(test)]
+    pub fn set_len(&mut self, len: usize) { self.len = len }
+
+    pub fn get(&self, token: Token) -> Option<&T> {
+        match self.data.get(self.position(token)?) {
+            Some(Cell::Nothing(_)) | None => None,
+            Some(Cell::Just(data)) => Some(data)
+        }
+    }
+
+    pub fn get_mut(&mut self, token: Token) -> Option<&mut T> {
+        let i = self.position(token)?;
+        match self.data.get_mut(i) {
+            Some(Cell::Nothing(_)) | None => None,
+            Some(Cell::Just(data)) => Some(data)
+        }
+    }
+
+    pub fn get_disjoint_mut<const N: usize>(&mut self, tokens: [Token; N])
+        -> Option<[&mut T; N]> {
+        for (i, token) in tokens.iter().enumerate() {
+            if !self.is_valid_token(*token) { return None }
+            if tokens[..i].contains(token) { return None }
+        }
+        let ptr = self.data.as_mut_ptr();
+        // SAFETY: the tokens are all valid and pairwise distinct, so the
+        // references point to distinct occupied cells in the vector, which
+        // stays borrowed mutably for as long as the references live.
+        Some(tokens.map(|token| match unsafe { &mut *ptr.add(token.slot()) } {
+            Cell::Just(data) => data,
+            Cell::Nothing(_) => unreachable!()
+        }))
+    }
+
+    /// Mutable references to the data at the tokens of each of the groups.
+    /// Returns `None` if any of the tokens is invalid or appears more than
+    /// once across the groups.
+    pub fn get_disjoint_groups_mut(&mut self, groups: &[Vec<Token>])
+        -> Option<Vec<Vec<&mut T>>> {
+        let mut taken = vec![false; self.data.len()];
+        for &token in groups.iter().flatten() {
+            match self.position(token) {
+                Some(i) if self.is_valid_token(token) && !taken[i] => taken[i] = true,
+                _ => return None
+            }
+        }
+        let ptr = self.data.as_mut_ptr();
+        // SAFETY: the tokens are all valid and pairwise distinct, so the
+        // references point to distinct occupied cells in the vector, which
+        // stays borrowed mutably for as long as the references live.
+        let groups = groups.iter()
+            .map(|group| group.iter()
+                .map(|token| match unsafe { &mut *ptr.add(token.slot()) } {
+                    Cell::Just(data) => data,
+                    Cell::Nothing(_) => unreachable!()
+                })
+                .collect())
+            .collect();
+        Some(groups)
+    }
+}
+
+/// Frees the cell of an update in progress unless it is forgotten, so that
+/// the allocator stays consistent when the update panics.
+struct ReleaseOnUnwind<'a, T> {
+    allocator: &'a mut Allocator<T>,
+    index: usize
+}
+
+impl<T> Drop for ReleaseOnUnwind<'_, T> {
+    fn drop(&mut self) { self.allocator.release(self.index) }
+}
+
+impl<T> IntoIterator for Allocator<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { iter: self.data.into_iter(), remaining: self.len }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            match self.iter.next() {
+                None => break None,
+                Some(Cell::Nothing(_)) => (),  // skip over the free cells
+                Some(Cell::Just(data)) => {
+                    self.remaining -= 1;
+                    break Some(data)
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            match self.iter.next() {
+                None => break None,
+                Some(Cell::Nothing(_)) => (),  // skip over the free cells
+                Some(Cell::Just(data)) => {
+                    self.remaining -= 1;
+                    break Some(data)
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<&'a mut T> {
+        loop {
+            match self.iter.next() {
+                None => break None,
+                Some(Cell::Nothing(_)) => (),  // skip over the free cells
+                Some(Cell::Just(data)) => {
+                    self.remaining -= 1;
+                    break Some(data)
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
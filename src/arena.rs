@@ -1,24 +1,218 @@
 #![allow(clippy::match_bool)]
-use std::collections::HashMap;
-use std::num::NonZeroUsize;
-use std::ops::{Index, IndexMut};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+use core::num::NonZeroUsize;
+use core::ops::{Index, IndexMut};
 
-use crate::alloc::Allocator;
-use crate::iter::{Branch, ChildrenTokens};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::Error;
+use crate::allocator::{Allocator, GrowthStrategy};
+use crate::cursor::{Cursor, CursorMut};
+use crate::iter::{IntoIter, Nodes, NodesMut, SubtreeMut, TraversalOrder};
 use crate::node::Node;
 use crate::token::Token;
 
 /// A struct that provides the arena allocator.
-#[derive(Debug, Default, Clone)]
+#[derive(Default, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedArena<T>"))]
 pub struct Arena<T> {
     pub (crate) allocator: Allocator<Node<T>>
 }
 
+/// The arena as it comes out of the deserializer, before it is checked for
+/// consistency.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedArena<T> {
+    allocator: Allocator<Node<T>>
+}
+
+#[cfg(feature = "serde")]
+impl<T> core::convert::TryFrom<UncheckedArena<T>> for Arena<T> {
+    type Error = String;
+    fn try_from(unchecked: UncheckedArena<T>) -> Result<Self, String> {
+        let arena = Arena { allocator: unchecked.allocator };
+        arena.validate()?;
+        Ok(arena)
+    }
+}
+
+/// A flat, relational representation of a node as produced by
+/// [`Arena::to_records`]. The `id` of a record is its position in the list
+/// and `parent` is the `id` of the record of its parent node.
+///
+/// [`Arena::to_records`]: struct.Arena.html#method.to_records
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Record<T> {
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub data: T
+}
+
+/// An owned, nested representation of a tree where each node holds its
+/// children directly, as produced by [`Token::to_nested`] and consumed by
+/// [`Arena::from_nested`]. With the `serde` feature this serializes to the
+/// usual recursive `{"data": ..., "children": [...]}` form.
+///
+/// [`Token::to_nested`]: struct.Token.html#method.to_nested
+/// [`Arena::from_nested`]: struct.Arena.html#method.from_nested
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RecursiveTree<T> {
+    pub data: T,
+    pub children: Vec<RecursiveTree<T>>
+}
+
+/// The depths of all the nodes in an arena, as computed by
+/// [`Arena::precompute_depths`], for answering repeated queries such as
+/// [`lca`] without walking all the way up to the root each time.
+///
+/// The index is a snapshot. It is not updated when the arena changes, so it
+/// has to be computed again after any node is added, removed or moved.
+///
+/// [`Arena::precompute_depths`]: struct.Arena.html#method.precompute_depths
+/// [`lca`]: struct.DepthIndex.html#method.lca
+#[derive(Debug, Clone)]
+pub struct DepthIndex {
+    // indexed by the zero-based index of the token, along with the token
+    // itself so that stale tokens to reused cells are told apart
+    depths: Vec<Option<(Token, usize)>>
+}
+
+impl DepthIndex {
+    /// Returns the depth of the given node, i.e. the number of ancestors it
+    /// has, or `None` if the node was not in the arena when the index was
+    /// computed.
+    pub fn depth(&self, token: Token) -> Option<usize> {
+        match self.depths.get(token.slot()) {
+            Some(&Some((t, depth))) if t == token => Some(depth),
+            _ => None
+        }
+    }
+
+    /// Finds the lowest common ancestor of the given nodes. A node counts as
+    /// an ancestor of itself. Returns `None` if the nodes belong to different
+    /// trees or if either of them was not in the arena when the index was
+    /// computed.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let scots = germanic.append(&mut arena, "Scots");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let other_root = arena.new_node("Sino-Tibetan");
+    ///
+    /// let index = arena.precompute_depths();
+    /// assert_eq!(index.lca(&arena, english, scots), Some(germanic));
+    /// assert_eq!(index.lca(&arena, english, romance), Some(root_token));
+    /// assert_eq!(index.lca(&arena, english, germanic), Some(germanic));
+    /// assert_eq!(index.lca(&arena, english, other_root), None);
+    /// ```
+    pub fn lca<T>(&self, arena: &Arena<T>, a: Token, b: Token) -> Option<Token> {
+        let parent = |token| arena[token].parent;
+        let (mut a, mut b) = (a, b);
+        let (mut depth_a, mut depth_b) = (self.depth(a)?, self.depth(b)?);
+        // bring both nodes up to the same depth before walking up in lockstep
+        while depth_a > depth_b {
+            a = parent(a)?;
+            depth_a -= 1;
+        }
+        while depth_b > depth_a {
+            b = parent(b)?;
+            depth_b -= 1;
+        }
+        while a != b {
+            a = parent(a)?;
+            b = parent(b)?;
+        }
+        Some(a)
+    }
+}
+
+/// Unlinks a node whose update is in progress from its neighbors unless it is
+/// forgotten. By the time it is dropped in a panicking update, the cell of the
+/// node has been freed, so the links of the node are kept here.
+struct UnlinkOnUnwind<'a, T> {
+    arena: &'a mut Arena<T>,
+    parent: Option<Token>,
+    previous_sibling: Option<Token>,
+    next_sibling: Option<Token>,
+    first_child: Option<Token>
+}
+
+impl<T> Drop for UnlinkOnUnwind<'_, T> {
+    fn drop(&mut self) {
+        let allocator = &mut self.arena.allocator;
+        match (self.previous_sibling, self.parent) {
+            (Some(t), _) => if let Some(node) = allocator.get_mut(t) {
+                node.next_sibling = self.next_sibling
+            },
+            (None, Some(t)) => if let Some(node) = allocator.get_mut(t) {
+                node.first_child = self.next_sibling
+            },
+            (None, None) => ()
+        }
+        match (self.next_sibling, self.parent) {
+            (Some(t), _) => if let Some(node) = allocator.get_mut(t) {
+                node.previous_sibling = self.previous_sibling
+            },
+            (None, Some(t)) => if let Some(node) = allocator.get_mut(t) {
+                node.last_child = self.previous_sibling
+            },
+            (None, None) => ()
+        }
+        // the children remain siblings as with `Arena::remove`
+        let mut child = self.first_child;
+        while let Some(node) = child.and_then(|t| allocator.get_mut(t)) {
+            node.parent = None;
+            child = node.next_sibling;
+        }
+    }
+}
+
 impl<T> Arena<T> {
     /// Initializes a new `Arena<T>`.
     pub fn new() -> Self { Arena { allocator: Allocator::new() } }
 
+    /// Initializes a new `Arena<T>` that can hold at least `capacity` nodes
+    /// without reallocating.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let mut arena = Arena::with_capacity(10);
+    /// let capacity = arena.capacity();
+    /// assert!(capacity >= 10);
+    ///
+    /// for i in 0..10usize { arena.new_node(i); }
+    /// assert_eq!(arena.capacity(), capacity);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut arena = Arena::new();
+        arena.reserve(capacity);
+        arena
+    }
+
     /// Returns true if the arena is empty.
     ///
     /// # Examples:
@@ -54,377 +248,3438 @@ impl<T> Arena<T> {
     /// ```
     pub fn node_count(&self) -> usize { self.allocator.len() }
 
-    /// Returns the number of nodes the tree can hold without reallocating.
-    pub fn capacity(&self) -> usize { self.allocator.capacity() }
-
-
-    /// Initializes arena and initializes a new tree with the given data at the
-    /// root node.
+    /// Sets how the arena grows once it runs out of free cells. Arenas double
+    /// their capacity by default. Growing by a fixed amount avoids large
+    /// spikes in memory use at the cost of reallocating more often. Explicit
+    /// calls to [`reserve`] are not affected.
     ///
     /// # Examples:
     ///
     /// ```
-    /// use atree::Arena;
+    /// use atree::{Arena, GrowthStrategy};
     ///
-    /// let root_data = 1usize;
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
-    /// assert_eq!(arena[root_token].data, 1);
+    /// let (mut arena, root_token) = Arena::with_data(0usize);
+    /// arena.set_growth(GrowthStrategy::Fixed(4));
+    /// root_token.append(&mut arena, 1usize);
+    /// assert_eq!(arena.capacity(), 5);
     /// ```
-    pub fn with_data(data: T) -> (Self, Token) {
-        let root_node = Node {
-            data,
-            parent: None,
-            previous_sibling: None,
-            token: Token { index: NonZeroUsize::new(1).unwrap() },
-            next_sibling: None,
-            first_child: None
-        };
-        let mut allocator = Allocator::new();
-        let root_token = allocator.insert(root_node);
-        (Arena { allocator }, root_token)
+    ///
+    /// [`reserve`]: struct.Arena.html#method.reserve
+    pub fn set_growth(&mut self, strategy: GrowthStrategy) {
+        self.allocator.set_growth(strategy)
     }
 
-    /// Creates a new free node in the given arena.
+    /// Returns the current growth strategy of the arena. See [`set_growth`].
+    ///
+    /// [`set_growth`]: struct.Arena.html#method.set_growth
+    pub fn growth(&self) -> GrowthStrategy { self.allocator.growth() }
+
+    /// Computes the depths of all the nodes in the arena for repeated lowest
+    /// common ancestor queries. See [`DepthIndex`] for more.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
     ///
-    /// let mut arena = Arena::default();
-    /// assert!(arena.is_empty());
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
     ///
-    /// let root_data = 1usize;
-    /// arena.new_node(root_data);
-    /// assert!(!arena.is_empty());
+    /// let index = arena.precompute_depths();
+    /// assert_eq!(index.depth(root_token), Some(0));
+    /// assert_eq!(index.depth(english), Some(2));
     /// ```
-    pub fn new_node(&mut self, data: T) -> Token {
-        let token = self.allocator.head();
-        let node = Node {
-            data,
-            parent: None,
-            previous_sibling: None,
-            token,
-            next_sibling: None,
-            first_child: None
-        };
-        self.allocator.set(token, node);
-        token
+    ///
+    /// [`DepthIndex`]: struct.DepthIndex.html
+    pub fn precompute_depths(&self) -> DepthIndex {
+        let mut depths = vec![None; self.capacity()];
+        let roots = self.allocator.iter()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(token, _)| token);
+        for root in roots {
+            for token in root.subtree_tokens(self, TraversalOrder::Pre) {
+                let depth = match self[token].parent {
+                    None => 0,
+                    // parents are visited before their children in pre-order
+                    Some(parent) => match depths[parent.slot()] {
+                        Some((_, depth)) => depth + 1,
+                        None => panic!("Corrupt arena")
+                    }
+                };
+                depths[token.slot()] = Some((token, depth));
+            }
+        }
+        DepthIndex { depths }
     }
 
-    /// Gets a reference to a node in the arena.
+    /// Returns an iterator of references to all the nodes in the arena,
+    /// regardless of the tree they belong to, in the order they are stored.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
     ///
-    /// let root_data = 1usize;
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
-    /// let next_node_token = root_token.append(&mut arena, 2usize);
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// root_token.append(&mut arena, 2usize);
+    /// arena.new_node(3usize);
     ///
-    /// // get the node we just inserted
-    /// let next_node = arena.get(next_node_token).unwrap();
-    /// assert_eq!(next_node.data, 2);
+    /// let sum: usize = arena.iter().map(|node| node.data).sum();
+    /// assert_eq!(sum, 6);
     /// ```
-    pub fn get(&self, indx: Token) -> Option<&Node<T>> {
-        self.allocator.get(indx)
+    pub fn iter(&self) -> Nodes<'_, T> {
+        Nodes { iter: self.allocator.values() }
     }
 
-    /// Gets a mutable reference to a node in the arena.
+    /// Returns an iterator of mutable references to all the nodes in the
+    /// arena, regardless of the tree they belong to, in the order they are
+    /// stored. Every node is visited exactly once, except for frozen nodes,
+    /// which are read-only and so are skipped over like in [`subtree_mut`].
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
     ///
-    /// let root_data = 1usize;
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
-    /// let next_node_token = root_token.append(&mut arena, 2usize);
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// root_token.append(&mut arena, 2usize);
+    /// arena.new_node(3usize);
     ///
-    /// // get the node we just inserted
-    /// let next_node = arena.get_mut(next_node_token).unwrap();
-    /// // mutate the data as you wish
-    /// next_node.data = 10;
+    /// for node in arena.iter_mut() { node.data *= 10 }
+    /// let sum: usize = arena.iter().map(|node| node.data).sum();
+    /// assert_eq!(sum, 60);
     /// ```
-    pub fn get_mut(&mut self, indx: Token) -> Option<&mut Node<T>> {
-        self.allocator.get_mut(indx)
+    ///
+    /// [`subtree_mut`]: struct.Token.html#method.subtree_mut
+    pub fn iter_mut(&mut self) -> NodesMut<'_, T> {
+        NodesMut { iter: self.allocator.values_mut() }
     }
 
-    /// Sets data to node.
-    pub (crate) fn set(&mut self, indx: Token, node: Node<T>) {
-        if let Some(mut n) = self.allocator.set(indx, node) {
-            n.remove_descendants(self)
-        }
-    }
+    /// Returns the number of nodes the tree can hold without reallocating.
+    pub fn capacity(&self) -> usize { self.allocator.capacity() }
 
-    /// Removes the given node from the arena and returns the tokens of its
-    /// children. Use [`uproot`] instead if you no longer need the descendants
-    /// of the node such that the freed memory could be reused.
-    /// # Panics:
-    ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Returns an estimate of the memory taken by the arena in bytes: the
+    /// arena itself plus its backing storage, including the free cells. Any
+    /// heap memory owned by the node data itself, such as the contents of a
+    /// `String`, is not included. Comparing this against [`node_count`] can
+    /// help decide when compacting the arena is worthwhile.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// // root node that we will attach subtrees to
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root) = Arena::with_data(root_data);
-    ///
-    /// // the Germanic branch
-    /// let germanic = root.append(&mut arena, "Germanic");
-    /// let west = germanic.append(&mut arena, "West");
-    /// let scots = west.append(&mut arena, "Scots");
-    /// let english = west.append(&mut arena, "English");
+    /// let (mut arena, root_token) = Arena::with_data(1u64);
+    /// let before = arena.total_size_bytes();
+    /// arena.reserve(10);
+    /// root_token.append(&mut arena, 2u64);
+    /// assert!(arena.total_size_bytes() > before);
+    /// ```
     ///
-    /// // detach the west branch from the main tree
-    /// let west_children = arena.remove(west);
+    /// [`node_count`]: struct.Arena.html#method.node_count
+    pub fn total_size_bytes(&self) -> usize {
+        core::mem::size_of::<Self>()
+            + self.capacity() * Allocator::<Node<T>>::cell_size()
+    }
+
+    /// Reserves capacity for at least `additional` more nodes to be inserted
+    /// without reallocating.
     ///
-    /// // the west branch is gone from the original tree
-    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("Indo-European"));
-    /// assert_eq!(iter.next(), Some("Germanic"));
-    /// assert!(iter.next().is_none());
+    /// # Examples:
     ///
-    /// // its children are still areound
-    /// let mut iter = west_children.iter().map(|&t| arena[t].data);
-    /// assert_eq!(iter.next(), Some("Scots"));
-    /// assert_eq!(iter.next(), Some("English"));
-    /// assert!(iter.next().is_none());
     /// ```
+    /// use atree::Arena;
     ///
-    /// [`uproot`]: struct.Arena.html#method.uproot
-    // cannot return an iterator since we need to drop the mutable borrow
-    pub fn remove(&mut self, token: Token) -> Vec<Token> {
-        token.detach(self);
-        // The chidlren will remain siblings. Change in the future if this leads
-        // to problems.
-        for child in token.children_mut(self) {
-            child.parent = None;
+    /// let (mut arena, _) = Arena::with_data(1usize);
+    /// arena.reserve(10);
+    /// assert!(arena.capacity() >= 11);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        let available = self.capacity() - self.node_count();
+        if additional > available {
+            self.allocator.reserve(additional - available)
         }
-        // should not fail because children_mut checks the validity of token
-        let first_child = self[token].first_child;
-        self.allocator.remove(token);
-        let iter = ChildrenTokens { arena: self, node_token: first_child };
-        iter.collect()
     }
 
-    /// Removes the given node along with all its descendants. If you only
-    /// wanted to remove the node while keeping its children, use [`remove`]
-    /// instead.
-    ///
-    /// # Panics:
-    ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Shrinks the capacity of the arena as much as possible without moving
+    /// any nodes, so unlike [`compact`] all tokens stay valid. Free cells that
+    /// sit between nodes cannot be released this way.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = 1usize;
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let (mut arena, root_token) = Arena::with_data(0usize);
+    /// arena.reserve(100);
+    /// let child_token = root_token.append(&mut arena, 1usize);
+    /// assert!(arena.capacity() > 100);
     ///
-    /// let next_node = root_token.append(&mut arena, 2usize);
-    /// let nnext_node1 = next_node.append(&mut arena, 3usize);
-    /// let nnext_node2 = next_node.append(&mut arena, 4usize);
-    /// 
-    /// arena.uproot(next_node);
-    /// let mut iter = root_token.subtree_tokens(&arena, TraversalOrder::Pre);
-    /// assert_eq!(iter.next(), Some(root_token));
-    /// assert!(iter.next().is_none());
-    /// assert_eq!(arena.node_count(), 1);  // only the root node is left
+    /// arena.shrink_to_fit();
+    /// assert_eq!(arena.capacity(), 2);
+    /// assert_eq!(arena[child_token].parent(), Some(root_token));
     /// ```
     ///
-    /// [`remove`]: struct.Arena.html#method.remove
-    pub fn uproot(&mut self, token: Token) {
-        token.remove_descendants(self);
-        match self.allocator.remove(token) {
-            None => panic!("Invalid token"),
-            Some(node) => match (node.parent, node.previous_sibling,
-                                 node.next_sibling) {
-                (Some(_), Some(otkn), Some(ytkn)) => {
-                    match self.get_mut(otkn) {
-                        Some(o) => o.next_sibling = Some(ytkn),
-                        None => panic!("Corrupt arena")
-                    }
-                    match self.get_mut(ytkn) {
-                        Some(y) => y.previous_sibling = Some(otkn),
-                        None => panic!("Corrupt arena")
-                    }
-                },
-                (Some(_), Some(otkn), None) => match self.get_mut(otkn) {
-                    Some(o) => o.next_sibling = None,
-                    None => panic!("Corrupt arena")
-                },
-                (Some(ptkn), None, Some(ytkn)) => match self.get_mut(ptkn) {
-                    Some(p) => p.first_child = Some(ytkn),
-                    None => panic!("Corrupt arena")
-                },
-                (Some(ptkn), None, None) => match self.get_mut(ptkn) {
-                    Some(p) => p.first_child = None,
-                    None => panic!("Corrupt arena")
-                },
-                (None, None, None) => (),  // empty tree
-                (None, None, Some(_))
-                    | (None, Some(_), None)
-                    | (None, Some(_), Some(_)) => panic!("Corrupt arena")
-            }
-        }
+    /// [`compact`]: struct.Arena.html#method.compact
+    pub fn shrink_to_fit(&mut self) {
+        self.allocator.shrink_to_fit()
     }
-}
 
-impl<T> Arena<T> where T: Clone {
-    /// Moves subtree with the root at the given node into its own arena. To
-    /// detach a given subtree root node from a tree into its own while
-    /// remaining in the same arena, use [`detach`] instead.
+    /// Moves the nodes of the arena so that the nodes in the subtree of the
+    /// given node are stored in pre-order, which makes traversing the subtree
+    /// walk through memory sequentially. Nodes that are not part of the
+    /// subtree are placed after it in their original relative order, and any
+    /// spare capacity is released.
+    ///
+    /// All tokens pointing into the arena are invalidated. The returned map
+    /// takes the old token of every node to its new token.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
     /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = "a0";
-    /// let (mut arena1, root1) = Arena::with_data(root_data);
-    ///
-    /// let node1 = root1.append(&mut arena1, "a1");
-    /// let node2 = root1.append(&mut arena1, "b1");
-    /// let grandchild1 = node1.append(&mut arena1, "a2");
-    /// let grandchild2 = node2.append(&mut arena1, "b2");
-    ///
-    /// // split tree
-    /// let (arena2, root2) = arena1.split_at(node2);
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// romance.append(&mut arena, "Italian");
+    /// germanic.append(&mut arena, "English");
     ///
-    /// let arena1_elt: Vec<_> = root1.subtree(&arena1, TraversalOrder::Pre)
-    ///     .map(|x| x.data).collect();
-    /// let arena2_elt: Vec<_> = root2.subtree(&arena2, TraversalOrder::Pre)
-    ///     .map(|x| x.data).collect();
+    /// let remap = arena.compact_preserving_order(root_token);
+    /// let root_token = remap[&root_token];
     ///
-    /// assert_eq!(&["a0", "a1", "a2"], &arena1_elt[..]);
-    /// assert_eq!(&["b1", "b2"], &arena2_elt[..]);
+    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(subtree.next(), Some("Indo-European"));
+    /// assert_eq!(subtree.next(), Some("Romance"));
+    /// assert_eq!(subtree.next(), Some("Italian"));
+    /// assert_eq!(subtree.next(), Some("Germanic"));
+    /// assert_eq!(subtree.next(), Some("English"));
+    /// assert!(subtree.next().is_none());
+    /// assert_eq!(arena[remap[&germanic]].data, "Germanic");
     /// ```
-    ///
-    /// [`detach`]: struct.Token.html#method.detach
-    // TODO: could probably be optimized
-    pub fn split_at(&mut self, token: Token) -> (Self, Token) where T: Clone {
-        let root_data = match self.get(token) {
-            Some(node) => node.data.clone(),
-            None => panic!("Invalid token")
-        };
-        let (mut arena, root) = Arena::with_data(root_data);
-        for child_token in token.children_tokens(&self) {
-            arena.copy_and_append_subtree(root, self, child_token);
-        }
-        self.uproot(token);
-        (arena, root)
+    pub fn compact_preserving_order(&mut self, root: Token)
+        -> BTreeMap<Token, Token> {
+        if self.get(root).is_none() { panic!("Invalid token") }
+        let mut order: Vec<Token> = root.subtree_tokens(self, TraversalOrder::Pre)
+            .collect();
+        let in_subtree: BTreeSet<Token> = order.iter().copied().collect();
+        order.extend(self.allocator.iter()
+            .map(|(token, _)| token)
+            .filter(|token| !in_subtree.contains(token)));
+        self.relocate(order)
     }
 
-    /// Copies a sub-tree from one arena and append to the given node of another.
-    /// It does so by walking the tree and copying node by node to the target
-    /// arena.  Potentially expensive operation.
+    /// Moves the nodes of the arena next to each other, keeping their
+    /// relative order in memory, and releases the freed cells. This reclaims
+    /// the memory left behind by removed nodes in long-lived arenas. Use
+    /// [`compact_preserving_order`] instead to also lay out a tree in
+    /// traversal order.
     ///
-    /// # Panics:
-    ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// All tokens pointing into the arena are invalidated. The returned map
+    /// takes the old token of every node to its new token.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = "John";
-    /// let (mut arena1, root_token) = Arena::with_data(root_data);
+    /// let (mut arena, root_token) = Arena::with_data(0);
+    /// let children = root_token.append_children(&mut arena, 1..100);
+    /// for &child in &children[1..] {
+    ///     arena.uproot(child);
+    /// }
+    /// assert_eq!(arena.node_count(), 2);
+    /// assert!(arena.capacity() >= 100);
     ///
-    /// let node1 = root_token.append(&mut arena1, "Juan");
-    /// let node2 = root_token.append(&mut arena1, "Giovanni");
-    /// let grandchild1 = node1.append(&mut arena1, "Ivan");
-    /// let grandchild2 = node2.append(&mut arena1, "Johann");
+    /// let remap = arena.compact();
+    /// assert_eq!(arena.capacity(), 2);
+    /// let root_token = remap[&root_token];
+    /// assert_eq!(arena[root_token].first_child(), Some(remap[&children[0]]));
+    /// ```
     ///
-    /// // new arena
-    /// let mut arena2 = arena1.clone();
+    /// [`compact_preserving_order`]: struct.Arena.html#method.compact_preserving_order
+    pub fn compact(&mut self) -> BTreeMap<Token, Token> {
+        let order = self.allocator.iter().map(|(token, _)| token).collect();
+        self.relocate(order)
+    }
+
+    /// Moves every node of the arena into a freshly allocated arena in the
+    /// given order, which must list each node exactly once.
+    fn relocate(&mut self, order: Vec<Token>) -> BTreeMap<Token, Token> {
+        // a fresh allocator hands out its cells in ascending order. It starts
+        // out with one cell so it only needs to grow by one less. Its cells
+        // are of a later generation than any of the old ones such that stale
+        // tokens do not resolve to relocated nodes.
+        let mut allocator = Allocator::new();
+        allocator.set_growth(self.allocator.growth());
+        allocator.reserve(order.len().saturating_sub(1));
+        let generation = allocator.succeed(&self.allocator);
+        let remap: BTreeMap<Token, Token> = order.iter()
+            .enumerate()
+            .map(|(i, &token)| {
+                let index = NonZeroUsize::new(i + 1).unwrap();  // one-based
+                (token, Token::new(index, generation))
+            })
+            .collect();
+
+        let relink = |token: Option<Token>| token.map(|t| remap[&t]);
+        for old in order {
+            let node = match self.allocator.remove(old) {
+                Some(node) => node,
+                None => panic!("Corrupt arena")
+            };
+            let node = Node {
+                token: remap[&old],
+                parent: relink(node.parent),
+                previous_sibling: relink(node.previous_sibling),
+                next_sibling: relink(node.next_sibling),
+                first_child: relink(node.first_child),
+                last_child: relink(node.last_child),
+                ..node
+            };
+            allocator.insert(node);
+        }
+        self.allocator = allocator;
+        remap
+    }
+
+    /// Initializes arena and initializes a new tree with the given data at the
+    /// root node.
     ///
-    /// // append "node1" from tree2 under "node2" in tree1
-    /// arena1.copy_and_append_subtree(node2, &arena2, node1);
-    /// let mut subtree = node2.subtree(&arena1, TraversalOrder::Pre);
+    /// # Examples:
     ///
-    /// assert_eq!(subtree.next().unwrap().data, "Giovanni");
-    /// assert_eq!(subtree.next().unwrap().data, "Johann");
-    /// assert_eq!(subtree.next().unwrap().data, "Juan");
-    /// assert_eq!(subtree.next().unwrap().data, "Ivan");
-    /// assert!(subtree.next().is_none());
     /// ```
-    pub fn copy_and_append_subtree(&mut self, self_token: Token,
-                                   other_tree: &Arena<T>, other_token: Token) {
-        match other_tree.get(other_token) {
-            None => panic!("Invalid token"),
-            Some(node) => {
-                let new_subtree_root = self_token.append(self, node.data.clone());
-                let mut index_map: HashMap<Token, Token> = HashMap::new();
-                index_map.insert(other_token, new_subtree_root);
-
-                let mut stack = vec![other_token];
-                let mut branch = Branch::Child;
-
-                loop {
-                    let &token = stack.last().unwrap(); // never fails
-                    let node = &other_tree[token];  // already checked
-                    match branch {
-                        Branch::None => (),  // unreachable
-                        Branch::Child => match node.first_child {
-                            None => branch = Branch::Sibling,
-                            Some(child) => {
-                                let child_data = match other_tree.get(child) {
-                                    Some(node) => node.data.clone(),
-                                    None => panic!("Corrupt arena")
-                                };
-                                let new_parent = index_map[&token];
-                                let new_child_token =
-                                    new_parent.append(self, child_data);
-                                index_map.insert(child, new_child_token);
-                                stack.push(child);
-                            }
-                        },
-                        Branch::Sibling => match Some(other_token) == stack.pop() {
-                            true => break,
-                            false => match node.next_sibling {
-                                None => (),
-                                Some(sibling) => {
-                                    stack.push(sibling);
-                                    branch = Branch::Child;
-                                }
-                            }
-                        }
-                    }
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// assert_eq!(arena[root_token].data, 1);
+    /// ```
+    pub fn with_data(data: T) -> (Self, Token) {
+        let root_node = Node {
+            data,
+            parent: None,
+            previous_sibling: None,
+            token: Token::new(NonZeroUsize::new(1).unwrap(), 0),
+            next_sibling: None,
+            first_child: None,
+            last_child: None,
+            frozen: false
+        };
+        let mut allocator = Allocator::new();
+        let root_token = allocator.insert(root_node);
+        (Arena { allocator }, root_token)
+    }
+
+    /// Initializes an arena that can hold at least `capacity` nodes without
+    /// reallocating and initializes a new tree with the given data at the root
+    /// node.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data_and_capacity(1usize, 10);
+    /// assert_eq!(arena[root_token].data, 1);
+    /// assert_eq!(arena.node_count(), 1);
+    /// assert!(arena.capacity() >= 10);
+    /// ```
+    pub fn with_data_and_capacity(data: T, capacity: usize) -> (Self, Token) {
+        let mut arena = Arena::with_capacity(capacity);
+        let root_token = arena.new_node(data);
+        (arena, root_token)
+    }
+
+    /// Builds a new arena holding the tree described by the given nested
+    /// representation, such as one produced by [`Token::to_nested`], and
+    /// returns it along with the token of the root node. Capacity for all the
+    /// nodes is reserved up front. This is handy for writing out tree literals.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::{Arena, RecursiveTree};
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let leaf = |data| RecursiveTree { data, children: vec![] };
+    /// let (arena, root_token) = Arena::from_nested(RecursiveTree {
+    ///     data: "Indo-European",
+    ///     children: vec![
+    ///         RecursiveTree {
+    ///             data: "Germanic",
+    ///             children: vec![leaf("English"), leaf("Scots")]
+    ///         },
+    ///         leaf("Romance")
+    ///     ]
+    /// });
+    ///
+    /// let mut iter = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Scots"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    ///
+    /// [`Token::to_nested`]: struct.Token.html#method.to_nested
+    #[doc(alias = "from_spec")]
+    pub fn from_nested(tree: RecursiveTree<T>) -> (Self, Token) {
+        // explicit stacks instead of recursion so deep trees cannot overflow
+        let mut count = 0;
+        let mut stack = vec![&tree];
+        while let Some(subtree) = stack.pop() {
+            count += 1;
+            stack.extend(subtree.children.iter());
+        }
+
+        let RecursiveTree { data, children } = tree;
+        let (mut arena, root_token) = Arena::with_data_and_capacity(data, count);
+        // the path from the root to the current node, along with the children
+        // that are yet to be added under each node
+        let mut path = vec![(root_token, children.into_iter())];
+        while let Some((token, children)) = path.last_mut() {
+            let token = *token;
+            match children.next() {
+                None => { path.pop(); },
+                Some(child) => {
+                    let new_token = token.append(&mut arena, child.data);
+                    path.push((new_token, child.children.into_iter()));
                 }
             }
         }
+        (arena, root_token)
     }
-}
 
-impl<T> Index<Token> for Arena<T> {
-    type Output = Node<T>;
-    fn index(&self, index: Token) -> &Self::Output {
-        match self.get(index) {
-            Some(node) => node,
-            None => panic!("Invalid token")
+    /// Builds trees from a flat list of data tagged with their depths, much like
+    /// an indented outline. An item one level deeper than the item before it
+    /// becomes its child, an item at the same depth becomes its sibling and a
+    /// shallower item goes back up to the last item at the same depth. Items at
+    /// depth zero become the roots of separate trees. Returns the tokens of the
+    /// items in the order they were given, or error if the list starts at a
+    /// depth other than zero or goes more than one level deeper at once.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let items = vec![
+    ///     (0, "main"),
+    ///     (1, "parse"),
+    ///     (2, "read_line"),
+    ///     (2, "tokenize"),
+    ///     (1, "eval")
+    /// ];
+    /// let (arena, tokens) = Arena::from_depth_list(items).unwrap();
+    ///
+    /// let mut children = tokens[1].children(&arena).map(|x| x.data);
+    /// assert_eq!(children.next(), Some("read_line"));
+    /// assert_eq!(children.next(), Some("tokenize"));
+    /// assert!(children.next().is_none());
+    /// assert_eq!(arena[tokens[4]].parent(), Some(tokens[0]));
+    ///
+    /// assert!(Arena::from_depth_list(vec![(0, "main"), (2, "read_line")]).is_err());
+    /// ```
+    pub fn from_depth_list(items: Vec<(usize, T)>)
+        -> Result<(Self, Vec<Token>), Error> {
+        let mut arena = Arena::new();
+        let mut tokens = Vec::with_capacity(items.len());
+        let mut stack: Vec<Token> = Vec::new();  // the path to the last item
+        for (depth, data) in items {
+            if depth > stack.len() { return Err(Error::InvalidDepth) }
+            stack.truncate(depth);
+            let token = match stack.last() {
+                None => arena.new_node(data),
+                Some(&parent) => parent.append(&mut arena, data)
+            };
+            stack.push(token);
+            tokens.push(token);
         }
+        Ok((arena, tokens))
     }
-}
 
-impl<T> IndexMut<Token> for Arena<T> {
-    fn index_mut(&mut self, index: Token) -> &mut Self::Output {
-        match self.get_mut(index) {
+    /// Flattens the subtree rooted at the given node into a list of records in
+    /// pre-order. Each record holds a copy of the data of a node, its position
+    /// in the list as its id and the id of its parent, so the parent of a
+    /// record always comes before it. The record of the given node has no
+    /// parent.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::{Arena, Record};
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root.append(&mut arena, "Romance");
+    ///
+    /// let records = arena.to_records(root);
+    /// assert_eq!(records[0], Record { id: 0, parent: None, data: "Indo-European" });
+    /// assert_eq!(records[1], Record { id: 1, parent: Some(0), data: "Germanic" });
+    /// assert_eq!(records[2], Record { id: 2, parent: Some(1), data: "English" });
+    /// assert_eq!(records[3], Record { id: 3, parent: Some(0), data: "Romance" });
+    /// ```
+    pub fn to_records(&self, root: Token) -> Vec<Record<T>> where T: Clone {
+        let mut records = Vec::with_capacity(root.subtree_size_hint(self));
+        // the path from the root to the current node, along with the ids
+        let mut path: Vec<(Token, usize)> = Vec::new();
+        for (id, node) in root.subtree(self, TraversalOrder::Pre).enumerate() {
+            while let Some(&(token, _)) = path.last() {
+                match Some(token) == node.parent {
+                    true => break,
+                    false => { path.pop(); }
+                }
+            }
+            let parent = path.last().map(|&(_, parent_id)| parent_id);
+            path.push((node.token, id));
+            records.push(Record { id, parent, data: node.data.clone() });
+        }
+        records
+    }
+
+    /// Builds trees from a list of records such as those produced by
+    /// [`to_records`]. Records without a parent become the roots of separate
+    /// trees. Returns the tokens of the records in the order they were given,
+    /// or error if a record refers to a parent that does not come before it or
+    /// if an id is repeated.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::{Arena, Record};
+    ///
+    /// let records = vec![
+    ///     Record { id: 0, parent: None, data: "Indo-European" },
+    ///     Record { id: 1, parent: Some(0), data: "Germanic" },
+    ///     Record { id: 2, parent: Some(1), data: "English" },
+    /// ];
+    /// let (arena, tokens) = Arena::from_records(records).unwrap();
+    /// assert_eq!(arena[tokens[2]].parent(), Some(tokens[1]));
+    ///
+    /// let orphan = vec![Record { id: 0, parent: Some(1), data: "English" }];
+    /// assert!(Arena::from_records(orphan).is_err());
+    /// ```
+    ///
+    /// [`to_records`]: struct.Arena.html#method.to_records
+    pub fn from_records(records: Vec<Record<T>>)
+        -> Result<(Self, Vec<Token>), Error> {
+        let mut arena = Arena::new();
+        let mut ids: BTreeMap<usize, Token> = BTreeMap::new();
+        let mut tokens = Vec::with_capacity(records.len());
+        for Record { id, parent, data } in records {
+            if ids.contains_key(&id) { return Err(Error::InvalidRecord) }
+            let token = match parent {
+                None => arena.new_node(data),
+                Some(p) => match ids.get(&p) {
+                    None => return Err(Error::InvalidRecord),
+                    Some(&parent) => parent.append(&mut arena, data)
+                }
+            };
+            ids.insert(id, token);
+            tokens.push(token);
+        }
+        Ok((arena, tokens))
+    }
+
+    /// Creates a new free node in the given arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let mut arena = Arena::default();
+    /// assert!(arena.is_empty());
+    ///
+    /// let root_data = 1usize;
+    /// arena.new_node(root_data);
+    /// assert!(!arena.is_empty());
+    /// ```
+    pub fn new_node(&mut self, data: T) -> Token {
+        let token = self.allocator.head();
+        let node = Node {
+            data,
+            parent: None,
+            previous_sibling: None,
+            token,
+            next_sibling: None,
+            first_child: None,
+            last_child: None,
+            frozen: false
+        };
+        self.allocator.set(token, node);
+        token
+    }
+
+    /// Checks whether the token refers to a node that is currently in the
+    /// arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// let child = root_token.append(&mut arena, 2usize);
+    /// assert!(arena.contains(child));
+    ///
+    /// arena.remove(child);
+    /// assert!(!arena.contains(child));
+    /// ```
+    pub fn contains(&self, token: Token) -> bool {
+        self.allocator.is_valid_token(token)
+    }
+
+    /// Returns a cursor pointing at the given node. This is the same as
+    /// [`Token::cursor`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    ///
+    /// let mut cursor = arena.cursor(germanic);
+    /// assert_eq!(cursor.parent(), Some(&"Indo-European"));
+    /// assert_eq!(cursor.token(), root_token);
+    /// ```
+    ///
+    /// [`Token::cursor`]: struct.Token.html#method.cursor
+    pub fn cursor(&self, token: Token) -> Cursor<'_, T> {
+        Cursor::new(self, token)
+    }
+
+    /// Returns a cursor pointing at the given node that can also edit the
+    /// tree. This is the same as [`Token::cursor_mut`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// [`Token::cursor_mut`]: struct.Token.html#method.cursor_mut
+    pub fn cursor_mut(&mut self, token: Token) -> CursorMut<'_, T> {
+        CursorMut::new(self, token)
+    }
+
+    /// Gets a reference to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let next_node_token = root_token.append(&mut arena, 2usize);
+    ///
+    /// // get the node we just inserted
+    /// let next_node = arena.get(next_node_token).unwrap();
+    /// assert_eq!(next_node.data, 2);
+    /// ```
+    pub fn get(&self, indx: Token) -> Option<&Node<T>> {
+        self.allocator.get(indx)
+    }
+
+    /// Gets a mutable reference to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let next_node_token = root_token.append(&mut arena, 2usize);
+    ///
+    /// // get the node we just inserted
+    /// let next_node = arena.get_mut(next_node_token).unwrap();
+    /// // mutate the data as you wish
+    /// next_node.data = 10;
+    ///
+    /// // frozen nodes cannot be mutated
+    /// arena.freeze_subtree(next_node_token);
+    /// assert!(arena.get_mut(next_node_token).is_none());
+    /// ```
+    pub fn get_mut(&mut self, indx: Token) -> Option<&mut Node<T>> {
+        match self.allocator.get_mut(indx) {
+            Some(node) if !node.frozen => Some(node),
+            _ => None
+        }
+    }
+
+    /// Gets a mutable reference to a node whether or not it is frozen. For
+    /// internal bookkeeping of links only.
+    pub (crate) fn node_mut(&mut self, indx: Token) -> &mut Node<T> {
+        match self.allocator.get_mut(indx) {
             Some(node) => node,
-            None => panic!("Invalid token")
+            None => panic!("Invalid token: {}", indx.index)
+        }
+    }
+
+    /// Gets mutable references to several nodes in the arena at once. Returns
+    /// `None` if any of the tokens does not correspond to a node in the arena,
+    /// if any of the nodes is frozen or if any two of the tokens are the same.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use std::mem;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let next_node_token = root_token.append(&mut arena, 2usize);
+    ///
+    /// let [root, next_node] = arena.get_disjoint_mut([root_token, next_node_token]).unwrap();
+    /// mem::swap(&mut root.data, &mut next_node.data);
+    /// assert_eq!(arena[root_token].data, 2);
+    /// assert_eq!(arena[next_node_token].data, 1);
+    ///
+    /// assert!(arena.get_disjoint_mut([root_token, root_token]).is_none());
+    /// ```
+    pub fn get_disjoint_mut<const N: usize>(&mut self, tokens: [Token; N])
+        -> Option<[&mut Node<T>; N]> {
+        match self.allocator.get_disjoint_mut(tokens) {
+            Some(nodes) if nodes.iter().all(|node| !node.frozen) => Some(nodes),
+            _ => None
+        }
+    }
+
+    /// Splits the arena into mutable iterators over the subtrees of the given
+    /// nodes (in pre-order) so that separate branches can be processed
+    /// independently, e.g. on different threads. Frozen nodes are skipped
+    /// over as in [`subtree_mut`]. Returns `None` if any of the tokens does not
+    /// correspond to a node in the arena or if any two of the subtrees
+    /// overlap, i.e. if one of the nodes is the same as or a descendant of
+    /// another.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(0usize);
+    /// let left = root_token.append(&mut arena, 1);
+    /// let right = root_token.append(&mut arena, 2);
+    /// left.append(&mut arena, 3);
+    /// right.append(&mut arena, 4);
+    ///
+    /// let subtrees = arena.split_subtrees_mut(&[left, right]).unwrap();
+    /// for (i, subtree) in subtrees.into_iter().enumerate() {
+    ///     subtree.for_each(|node| node.data += 10 * (i + 1));
+    /// }
+    /// let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data)
+    ///     .collect();
+    /// assert_eq!(data, vec![0, 11, 13, 22, 24]);
+    ///
+    /// assert!(arena.split_subtrees_mut(&[root_token, right]).is_none());
+    /// ```
+    ///
+    /// [`subtree_mut`]: struct.Token.html#method.subtree_mut
+    pub fn split_subtrees_mut(&mut self, tokens: &[Token])
+        -> Option<Vec<SubtreeMut<'_, T>>> {
+        if tokens.iter().any(|&token| self.get(token).is_none()) { return None }
+        // overlapping subtrees share nodes, which the split refuses
+        let groups: Vec<Vec<Token>> = tokens.iter()
+            .map(|&token| token.subtree_tokens(self, TraversalOrder::Pre).collect())
+            .collect();
+        SubtreeMut::split(self, &groups)
+    }
+
+    /// Replaces the data of a node with the result of calling `f` on the old
+    /// data. Unlike mutating the data through [`get_mut`], `f` takes ownership
+    /// of the old value. Returns error if the token does not correspond to a
+    /// node in the arena. Should `f` panic, the node is removed from the arena
+    /// as by [`remove`] and its children are left without a parent.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = vec!["Indo-European"];
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let child_token = root_token.append(&mut arena, vec!["Germanic"]);
+    ///
+    /// arena.update(child_token, |mut data| { data.push("English"); data }).unwrap();
+    /// assert_eq!(arena[child_token].data, vec!["Germanic", "English"]);
+    /// assert_eq!(arena[child_token].parent(), Some(root_token));
+    /// ```
+    ///
+    /// [`get_mut`]: struct.Arena.html#method.get_mut
+    /// [`remove`]: struct.Arena.html#method.remove
+    pub fn update<F>(&mut self, token: Token, f: F) -> Result<(), Error>
+        where F: FnOnce(T) -> T {
+        let node = match self.get(token) {
+            None => return Err(Error::InvalidToken),
+            Some(node) => match node.frozen {
+                true => return Err(Error::FrozenNode),
+                false => node
+            }
+        };
+        let guard = UnlinkOnUnwind {
+            parent: node.parent,
+            previous_sibling: node.previous_sibling,
+            next_sibling: node.next_sibling,
+            first_child: node.first_child,
+            arena: self
+        };
+        let f = |node: Node<T>| Node { data: f(node.data), ..node };
+        let updated = guard.arena.allocator.update(token, f);
+        mem::forget(guard);
+        match updated {
+            true => Ok(()),
+            false => Err(Error::InvalidToken)
         }
     }
+
+    /// Removes the given node from the arena and returns the tokens of its
+    /// children. Use [`uproot`] instead if you no longer need the descendants
+    /// of the node such that the freed memory could be reused.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node or its parent is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// // root node that we will attach subtrees to
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root) = Arena::with_data(root_data);
+    ///
+    /// // the Germanic branch
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West");
+    /// let scots = west.append(&mut arena, "Scots");
+    /// let english = west.append(&mut arena, "English");
+    ///
+    /// // detach the west branch from the main tree
+    /// let west_children = arena.remove(west);
+    ///
+    /// // the west branch is gone from the original tree
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert!(iter.next().is_none());
+    ///
+    /// // its children are still areound
+    /// let mut iter = west_children.iter().map(|&t| arena[t].data);
+    /// assert_eq!(iter.next(), Some("Scots"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    ///
+    /// [`uproot`]: struct.Arena.html#method.uproot
+    // cannot return an iterator since we need to drop the mutable borrow
+    pub fn remove(&mut self, token: Token) -> Vec<Token> {
+        self.remove_take(token).1
+    }
+
+    /// Removes the given node from the arena like [`remove`] and returns its
+    /// data along with the tokens of its children.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node or its parent is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root) = Arena::with_data(String::from("Indo-European"));
+    /// let germanic = root.append(&mut arena, String::from("Germanic"));
+    /// let english = germanic.append(&mut arena, String::from("English"));
+    ///
+    /// let (data, children) = arena.remove_take(germanic);
+    /// assert_eq!(data, "Germanic");
+    /// assert_eq!(children, vec![english]);
+    /// assert!(arena[english].is_root());
+    /// ```
+    ///
+    /// [`remove`]: struct.Arena.html#method.remove
+    pub fn remove_take(&mut self, token: Token) -> (T, Vec<Token>) {
+        match self.get(token) {
+            None => panic!("Invalid token"),
+            Some(node) => if node.frozen { panic!("Frozen node") }
+        }
+        token.detach(self);
+        // The chidlren will remain siblings. Change in the future if this leads
+        // to problems.
+        let children: Vec<_> = token.children_tokens(self).collect();
+        for &child in children.iter() {
+            self.node_mut(child).parent = None;
+        }
+        match self.allocator.remove(token) {
+            Some(node) => (node.data, children),
+            None => panic!("Corrupt arena")
+        }
+    }
+
+    /// Removes every node in the arena whose data fails the predicate, along
+    /// with the descendants of the removed node. Nodes within a removed subtree
+    /// are not passed to the predicate. This is [`prune`] applied to every
+    /// tree in the arena, with the roots themselves up for removal as well.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if a node to be removed, any of its descendants or its parent is
+    /// frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(2);
+    /// root_token.append(&mut arena, 3);
+    /// root_token.append(&mut arena, 4);
+    /// let other_root = arena.new_node(5);
+    /// other_root.append(&mut arena, 6);
+    ///
+    /// arena.retain(|&x| x % 2 == 0);
+    /// assert_eq!(arena.node_count(), 2);
+    /// assert!(!arena.contains(other_root));
+    /// ```
+    ///
+    /// [`prune`]: struct.Token.html#method.prune
+    pub fn retain<F>(&mut self, mut pred: F) where F: FnMut(&T) -> bool {
+        let roots: Vec<_> = self.allocator.iter()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(token, _)| token)
+            .collect();
+        for root in roots {
+            match pred(&self[root].data) {
+                true => root.prune(self, &mut pred),
+                false => self.uproot(root)
+            }
+        }
+    }
+
+    /// Removes the nodes in the subtree of the given node whose data satisfy
+    /// the predicate and returns their data in pre-order. Unlike [`remove`],
+    /// the children of a removed node take its place among the children of
+    /// its parent, so the rest of the tree stays connected. If the given node
+    /// itself is removed and has no parent, its children become the roots of
+    /// their own trees.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if a
+    /// node to be removed or its parent is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West Germanic");
+    /// west.append(&mut arena, "English");
+    /// west.append(&mut arena, "Dutch");
+    /// germanic.append(&mut arena, "Swedish");
+    ///
+    /// let removed = arena.extract_if(root_token, |x| x.ends_with("Germanic"));
+    /// assert_eq!(removed, vec!["Germanic", "West Germanic"]);
+    ///
+    /// let mut children = root_token.children(&arena).map(|x| x.data);
+    /// assert_eq!(children.next(), Some("English"));
+    /// assert_eq!(children.next(), Some("Dutch"));
+    /// assert_eq!(children.next(), Some("Swedish"));
+    /// assert!(children.next().is_none());
+    /// ```
+    ///
+    /// [`remove`]: struct.Arena.html#method.remove
+    pub fn extract_if<F>(&mut self, root: Token, mut pred: F) -> Vec<T>
+        where F: FnMut(&T) -> bool {
+        // Removing a node only moves its children up into its place, which
+        // leaves the pre-order of the remaining nodes intact. The order can
+        // therefore be settled up front.
+        let tokens: Vec<_> = root.subtree_tokens(self, TraversalOrder::Pre)
+            .collect();
+        let mut removed = Vec::new();
+        for token in tokens {
+            if pred(&self[token].data) { removed.push(self.splice_out(token)) }
+        }
+        removed
+    }
+
+    /// Removes the given node and moves its children into its place. Without a
+    /// parent to move into, the children become separate roots.
+    pub (crate) fn splice_out(&mut self, token: Token) -> T {
+        let (parent, previous_sibling, next_sibling) = match self.get(token) {
+            None => panic!("Invalid token"),
+            Some(node) => match node.frozen {
+                true => panic!("Frozen node"),
+                false => (node.parent, node.previous_sibling, node.next_sibling)
+            }
+        };
+        let children: Vec<_> = token.children_tokens(self).collect();
+        match (parent, children.first(), children.last()) {
+            (Some(p), Some(&first), Some(&last)) => {
+                if self[p].frozen { panic!("Frozen node") }
+                for &child in children.iter() {
+                    self.node_mut(child).parent = Some(p);
+                }
+                self.node_mut(first).previous_sibling = previous_sibling;
+                self.node_mut(last).next_sibling = next_sibling;
+                match previous_sibling {
+                    Some(s) => self.node_mut(s).next_sibling = Some(first),
+                    None => self.node_mut(p).first_child = Some(first)
+                }
+                match next_sibling {
+                    Some(s) => self.node_mut(s).previous_sibling = Some(last),
+                    None => self.node_mut(p).last_child = Some(last)
+                }
+            },
+            _ => {
+                // either there is nothing to splice or no parent to splice the
+                // children into, in which case they become roots
+                token.detach(self);
+                for &child in children.iter() {
+                    let node = self.node_mut(child);
+                    node.parent = None;
+                    node.previous_sibling = None;
+                    node.next_sibling = None;
+                }
+            }
+        }
+        match self.allocator.remove(token) {
+            Some(node) => node.data,
+            None => panic!("Corrupt arena")
+        }
+    }
+
+    /// Removes the given node along with all its descendants. If you only
+    /// wanted to remove the node while keeping its children, use [`remove`]
+    /// instead.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if
+    /// any of the nodes in the subtree or the parent of the node is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let next_node = root_token.append(&mut arena, 2usize);
+    /// let nnext_node1 = next_node.append(&mut arena, 3usize);
+    /// let nnext_node2 = next_node.append(&mut arena, 4usize);
+    /// 
+    /// arena.uproot(next_node);
+    /// let mut iter = root_token.subtree_tokens(&arena, TraversalOrder::Pre);
+    /// assert_eq!(iter.next(), Some(root_token));
+    /// assert!(iter.next().is_none());
+    /// assert_eq!(arena.node_count(), 1);  // only the root node is left
+    /// ```
+    ///
+    /// [`remove`]: struct.Arena.html#method.remove
+    pub fn uproot(&mut self, token: Token) {
+        if token.subtree(self, TraversalOrder::Pre).any(|node| node.frozen) {
+            panic!("Frozen node")
+        }
+        token.remove_descendants(self);
+        token.detach(self);
+        self.allocator.remove(token);  // detach will have checked the token
+    }
+
+    /// Removes the subtree rooted at the given node from the arena like
+    /// [`uproot`] but hands back the data of the removed nodes in pre-order
+    /// instead of dropping it. The nodes are removed right away, whether or
+    /// not the iterator is consumed.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if
+    /// any of the nodes in the subtree or the parent of the node is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// germanic.append(&mut arena, "Dutch");
+    ///
+    /// let archived: Vec<_> = arena.drain_subtree(germanic).collect();
+    /// assert_eq!(archived, vec!["Germanic", "English", "Dutch"]);
+    /// assert_eq!(arena.node_count(), 1);
+    /// assert!(!arena.contains(germanic));
+    /// ```
+    ///
+    /// [`uproot`]: struct.Arena.html#method.uproot
+    pub fn drain_subtree(&mut self, token: Token) -> vec::IntoIter<T> {
+        if token.subtree(self, TraversalOrder::Pre).any(|node| node.frozen) {
+            panic!("Frozen node")
+        }
+        token.detach(self);
+        let tokens: Vec<_> = token.subtree_tokens(self, TraversalOrder::Pre)
+            .collect();
+        let removed: Vec<_> = tokens.into_iter()
+            .map(|token| match self.allocator.remove(token) {
+                Some(node) => node.data,
+                None => panic!("Corrupt arena")
+            })
+            .collect();
+        removed.into_iter()
+    }
+
+    /// Exchanges the positions of two nodes, along with their descendants, in
+    /// the tree(s) of the arena. Both nodes keep their tokens. Returns error if
+    /// one node is within the subtree of the other or if the parent of either
+    /// node is frozen.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let romance = root.append(&mut arena, "Romance");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// arena.swap_subtrees(germanic, romance).unwrap();
+    /// arena.swap_subtrees(english, french).unwrap();
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("French"));
+    /// assert!(iter.next().is_none());
+    ///
+    /// // cannot swap a node with its own descendant
+    /// assert!(arena.swap_subtrees(root, french).is_err());
+    /// ```
+    pub fn swap_subtrees(&mut self, a: Token, b: Token) -> Result<(), Error> {
+        let position = |arena: &Self, token| match arena.get(token) {
+            None => panic!("Invalid token"),
+            Some(node) => (node.parent, node.previous_sibling, node.next_sibling)
+        };
+        let (a_parent, a_previous, a_next) = position(self, a);
+        let (b_parent, b_previous, b_next) = position(self, b);
+        if a == b { return Ok(()) }
+        let frozen = |parent: Option<Token>| match parent {
+            Some(p) => self[p].frozen,
+            None => false
+        };
+        if frozen(a_parent) || frozen(b_parent) { return Err(Error::FrozenNode) }
+        if a.ancestors_tokens(self).any(|t| t == b)
+            || b.ancestors_tokens(self).any(|t| t == a) {
+            return Err(Error::CyclicGraph)
+        }
+
+        // adjacent siblings are each other's neighbors, whether they have a
+        // parent or not, so the pair is simply laid out in reverse
+        match (a_next == Some(b), b_next == Some(a)) {
+            (true, _) => {
+                self.relink_slot(a_parent, a_previous, Some(a), b);
+                self.relink_slot(a_parent, Some(b), b_next, a);
+            },
+            (_, true) => {
+                self.relink_slot(b_parent, b_previous, Some(b), a);
+                self.relink_slot(b_parent, Some(a), a_next, b);
+            },
+            // otherwise the neighbors of the two nodes are all distinct
+            _ => {
+                self.relink_slot(a_parent, a_previous, a_next, b);
+                self.relink_slot(b_parent, b_previous, b_next, a);
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts the given node in the slot described by the given parent and
+    /// siblings, updating the links of the neighbors to match.
+    fn relink_slot(&mut self, parent: Option<Token>, previous: Option<Token>,
+                   next: Option<Token>, token: Token) {
+        match (previous, parent) {
+            (Some(sibling), _) => self.node_mut(sibling).next_sibling = Some(token),
+            (None, Some(parent)) => self.node_mut(parent).first_child = Some(token),
+            (None, None) => ()
+        }
+        match (next, parent) {
+            (Some(sibling), _) =>
+                self.node_mut(sibling).previous_sibling = Some(token),
+            (None, Some(parent)) => self.node_mut(parent).last_child = Some(token),
+            (None, None) => ()
+        }
+        let node = self.node_mut(token);
+        node.parent = parent;
+        node.previous_sibling = previous;
+        node.next_sibling = next;
+    }
+
+    /// Moves the subtree rooted at the given node into its own arena. Unlike
+    /// [`split_at`], the data is moved rather than cloned. The cells of the
+    /// subtree are freed in the original arena. Returns the new arena along
+    /// with the token of the root in the new arena.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if
+    /// any of the nodes in the subtree or the parent of the node is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "a0";
+    /// let (mut arena1, root1) = Arena::with_data(root_data);
+    ///
+    /// let node1 = root1.append(&mut arena1, "a1");
+    /// let node2 = root1.append(&mut arena1, "b1");
+    /// node1.append(&mut arena1, "a2");
+    /// node2.append(&mut arena1, "b2");
+    ///
+    /// // split tree
+    /// let (arena2, root2) = arena1.split_off(node2);
+    /// assert_eq!(arena1.node_count(), 3);
+    /// assert_eq!(arena2.node_count(), 2);
+    ///
+    /// let arena1_elt: Vec<_> = root1.subtree(&arena1, TraversalOrder::Pre)
+    ///     .map(|x| x.data).collect();
+    /// let arena2_elt: Vec<_> = root2.subtree(&arena2, TraversalOrder::Pre)
+    ///     .map(|x| x.data).collect();
+    ///
+    /// assert_eq!(&["a0", "a1", "a2"], &arena1_elt[..]);
+    /// assert_eq!(&["b1", "b2"], &arena2_elt[..]);
+    /// ```
+    ///
+    /// [`split_at`]: struct.Arena.html#method.split_at
+    pub fn split_off(&mut self, token: Token) -> (Self, Token) {
+        if token.subtree(self, TraversalOrder::Pre).any(|node| node.frozen) {
+            panic!("Frozen node")
+        }
+        token.detach(self);
+        let mut arena = Arena::new();
+        let root = self.move_subtree_into(token, &mut arena, None);
+        (arena, root)
+    }
+
+    /// Moves the subtree rooted at the given node from another arena into this
+    /// one and attaches it as the last child of `dest_parent`. Unlike
+    /// [`copy_and_append_subtree`], the data is moved rather than cloned. The
+    /// cells of the subtree are freed in the other arena. Returns the token of
+    /// the root of the subtree in this arena.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in its arena, if
+    /// `dest_parent` is frozen or if any of the nodes in the subtree or the
+    /// parent of `src_root` is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    ///
+    /// // built elsewhere
+    /// let (mut scratch, scratch_root) = Arena::with_data("West");
+    /// scratch_root.append(&mut scratch, "English");
+    /// scratch_root.append(&mut scratch, "Dutch");
+    ///
+    /// let west = arena.transplant(&mut scratch, scratch_root, germanic);
+    /// assert!(scratch.is_empty());
+    /// assert_eq!(arena[west].parent(), Some(germanic));
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("West"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Dutch"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    ///
+    /// [`copy_and_append_subtree`]: struct.Arena.html#method.copy_and_append_subtree
+    pub fn transplant(&mut self, src: &mut Arena<T>, src_root: Token,
+                      dest_parent: Token) -> Token {
+        match self.get(dest_parent) {
+            None => panic!("Invalid token"),
+            Some(node) => if node.frozen { panic!("Frozen node") }
+        }
+        if src_root.subtree(src, TraversalOrder::Pre).any(|node| node.frozen) {
+            panic!("Frozen node")
+        }
+        src_root.detach(src);
+        src.move_subtree_into(src_root, self, Some(dest_parent))
+    }
+
+    /// Moves the free-standing subtree rooted at the given node into another
+    /// arena, under the given parent or as a tree of its own. Returns the token
+    /// of the root in the other arena.
+    fn move_subtree_into(&mut self, token: Token, dest: &mut Arena<T>,
+                         dest_parent: Option<Token>) -> Token {
+        let tokens: Vec<(Token, Option<Token>)> =
+            token.subtree(self, TraversalOrder::Pre)
+                .map(|node| (node.token, node.parent))
+                .collect();
+
+        dest.reserve(tokens.len());
+        // the path from the root of the subtree to the current node, as pairs
+        // of the old and the new tokens
+        let mut path: Vec<(Token, Token)> = Vec::new();
+        for (old_token, parent) in tokens {
+            while let Some(&(old, _)) = path.last() {
+                match Some(old) == parent {
+                    true => break,
+                    false => { path.pop(); }
+                }
+            }
+            let data = match self.allocator.remove(old_token) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.data
+            };
+            let new_token = match (path.last(), dest_parent) {
+                (Some(&(_, new)), _) | (None, Some(new)) => new.append(dest, data),
+                (None, None) => dest.new_node(data)
+            };
+            path.push((old_token, new_token));
+        }
+        path[0].1  // the root is never popped off
+    }
+
+    /// Freezes the subtree rooted at the given node, marking its nodes as
+    /// read-only. The data of a frozen node cannot be mutated and a frozen node
+    /// cannot gain or lose children or be removed from the arena. Operations
+    /// that would do so return [`Error::FrozenNode`] where they return a
+    /// `Result`, and panic otherwise. [`get_mut`] returns `None` for frozen
+    /// nodes and the mutable iterators skip over them.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    ///
+    /// arena.freeze_subtree(germanic);
+    /// assert!(arena[germanic].is_frozen());
+    /// assert!(germanic.try_append(&mut arena, "Swedish").is_err());
+    ///
+    /// // the rest of the tree is not affected
+    /// assert!(!arena[root_token].is_frozen());
+    /// root_token.append(&mut arena, "Romance");
+    /// ```
+    ///
+    /// [`Error::FrozenNode`]: enum.Error.html#variant.FrozenNode
+    /// [`get_mut`]: struct.Arena.html#method.get_mut
+    pub fn freeze_subtree(&mut self, token: Token) {
+        self.set_frozen(token, true)
+    }
+
+    /// Unfreezes the subtree rooted at the given node. See [`freeze_subtree`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// arena.freeze_subtree(root_token);
+    /// assert!(root_token.try_append(&mut arena, "Germanic").is_err());
+    /// arena.unfreeze_subtree(root_token);
+    /// assert!(root_token.try_append(&mut arena, "Germanic").is_ok());
+    /// ```
+    ///
+    /// [`freeze_subtree`]: struct.Arena.html#method.freeze_subtree
+    pub fn unfreeze_subtree(&mut self, token: Token) {
+        self.set_frozen(token, false)
+    }
+
+    fn set_frozen(&mut self, token: Token, frozen: bool) {
+        let tokens: Vec<_> = token.subtree_tokens(self, TraversalOrder::Pre)
+            .collect();
+        for t in tokens {
+            self.node_mut(t).frozen = frozen;
+        }
+    }
+
+    /// Converts the arena into one with the same tree structures where the data
+    /// of each node is computed from its old data, which is moved into the
+    /// closure. The tokens from the old arena remain valid in the new one and
+    /// refer to the corresponding nodes. Use [`map_ref`] to keep the old arena
+    /// around.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Expr { Number(i64), Add }
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("+");
+    /// let one = root_token.append(&mut arena, "1");
+    /// root_token.append(&mut arena, "2");
+    ///
+    /// let ast = arena.map(|s| match s.parse() {
+    ///     Ok(n) => Expr::Number(n),
+    ///     Err(_) => Expr::Add
+    /// });
+    /// assert_eq!(ast[root_token].data, Expr::Add);
+    /// assert_eq!(ast[one].data, Expr::Number(1));
+    /// assert_eq!(ast[one].parent(), Some(root_token));
+    /// ```
+    ///
+    /// [`map_ref`]: struct.Arena.html#method.map_ref
+    pub fn map<U, F>(self, mut f: F) -> Arena<U> where F: FnMut(T) -> U {
+        let allocator = self.allocator.into_map(|_, node| {
+            let links = node.with_data(());
+            links.with_data(f(node.data))
+        });
+        Arena { allocator }
+    }
+
+    /// Like [`map`] but with a closure that may fail. Stops at the first error
+    /// and returns it along with the token of the node whose data caused it.
+    /// The nodes are visited in the order they are laid out in memory, which
+    /// is not necessarily any kind of traversal order.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("1");
+    /// root_token.append(&mut arena, "2");
+    /// let parsed = arena.try_map(|s| s.parse::<i64>()).unwrap();
+    /// assert_eq!(parsed[root_token].data, 1);
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("1");
+    /// let bad = root_token.append(&mut arena, "two");
+    /// let (token, _) = arena.try_map(|s| s.parse::<i64>()).unwrap_err();
+    /// assert_eq!(token, bad);
+    /// ```
+    ///
+    /// [`map`]: struct.Arena.html#method.map
+    pub fn try_map<U, E, F>(self, mut f: F) -> Result<Arena<U>, (Token, E)>
+        where F: FnMut(T) -> Result<U, E> {
+        let allocator = self.allocator.try_into_map(|_, node| {
+            let links = node.with_data(());
+            f(node.data).map(|data| links.with_data(data))
+        })?;
+        Ok(Arena { allocator })
+    }
+
+    /// Creates a new arena with the same tree structures where the data of each
+    /// node is computed from a reference to its old data. The tokens from the
+    /// old arena remain valid in the new one and refer to the corresponding
+    /// nodes.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    ///
+    /// let lengths = arena.map_ref(|s| s.len());
+    /// assert_eq!(lengths[germanic].data, 8);
+    /// assert_eq!(arena[germanic].data, "Germanic");
+    /// ```
+    pub fn map_ref<U, F>(&self, mut f: F) -> Arena<U> where F: FnMut(&T) -> U {
+        let allocator = self.allocator.map(|_, node| node.with_data(f(&node.data)));
+        Arena { allocator }
+    }
+
+    /// Creates a new arena with the same tree structures where the data of each
+    /// node is computed from its token and its old data. The tokens from the
+    /// old arena remain valid in the new one and refer to the corresponding
+    /// nodes.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    ///
+    /// let new_arena = arena.map_with_token(|token, data| {
+    ///     match arena[token].parent() {
+    ///         None => data.to_string(),
+    ///         Some(parent) => format!("{}/{}", arena[parent].data, data)
+    ///     }
+    /// });
+    /// assert_eq!(new_arena[germanic].data, "Indo-European/Germanic");
+    /// assert_eq!(new_arena[germanic].parent(), Some(root_token));
+    /// ```
+    pub fn map_with_token<U, F>(&self, f: F) -> Arena<U>
+        where F: Fn(Token, &T) -> U {
+        let allocator = self.allocator.map(|token, node| {
+            node.with_data(f(token, &node.data))
+        });
+        Arena { allocator }
+    }
+
+    /// Checks the internal consistency of the arena: parent/child and sibling
+    /// links must agree with each other, no node may be its own ancestor, and
+    /// the bookkeeping of the allocator must match the occupied cells. Returns
+    /// a description of the first inconsistency found, if any. Mostly useful
+    /// for debugging code that manipulates the tree.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
+    /// arena.remove(germanic);
+    ///
+    /// assert!(arena.validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<(), String> {
+        self.allocator.validate()?;
+        let node_count = self.node_count();
+        let get = |token: Token, role: &str, of: Token| match self.get(token) {
+            Some(node) => Ok(node),
+            None => Err(format!("{} {:?} of {:?} is not in the arena",
+                                role, token, of))
+        };
+
+        let mut child_count = 0;
+        for (token, node) in self.allocator.iter() {
+            if node.token != token {
+                return Err(format!("node at {:?} claims to be {:?}",
+                                   token, node.token))
+            }
+
+            if let Some(next) = node.next_sibling {
+                let sibling = get(next, "next sibling", token)?;
+                if sibling.previous_sibling != Some(token) {
+                    return Err(format!("previous sibling of {:?} is not {:?}",
+                                       next, token))
+                }
+                if sibling.parent != node.parent {
+                    return Err(format!("siblings {:?} and {:?} have different \
+                                        parents", token, next))
+                }
+            }
+            if let Some(previous) = node.previous_sibling {
+                let sibling = get(previous, "previous sibling", token)?;
+                if sibling.next_sibling != Some(token) {
+                    return Err(format!("next sibling of {:?} is not {:?}",
+                                       previous, token))
+                }
+            }
+
+            // walk the children
+            let mut previous_child = None;
+            let mut next_child = node.first_child;
+            while let Some(child) = next_child {
+                child_count += 1;
+                if child_count > node_count {
+                    return Err(format!("children of {:?} form a cycle", token))
+                }
+                let child_node = get(child, "child", token)?;
+                if child_node.parent != Some(token) {
+                    return Err(format!("parent of {:?} is not {:?}",
+                                       child, token))
+                }
+                if child_node.previous_sibling != previous_child {
+                    return Err(format!("{:?} is out of the sibling chain of \
+                                        {:?}", child, token))
+                }
+                previous_child = Some(child);
+                next_child = child_node.next_sibling;
+            }
+            if node.last_child != previous_child {
+                return Err(format!("last child of {:?} is not {:?}",
+                                   token, previous_child))
+            }
+
+            // walk the ancestors
+            let mut depth = 0;
+            let mut next_ancestor = node.parent;
+            while let Some(ancestor) = next_ancestor {
+                depth += 1;
+                if depth > node_count {
+                    return Err(format!("{:?} is its own ancestor", token))
+                }
+                next_ancestor = get(ancestor, "ancestor", token)?.parent;
+            }
+        }
+
+        // every child found above has its parent set accordingly, so this
+        // means that no node with a parent is missing from the children of
+        // its parent
+        let with_parent = self.allocator.iter()
+            .filter(|(_, node)| node.parent.is_some())
+            .count();
+        match with_parent == child_count {
+            true => Ok(()),
+            false => Err(format!("{} nodes have a parent but only {} are \
+                                  listed as children", with_parent,
+                                  child_count))
+        }
+    }
+
+    /// Renders the subtree rooted at the given node in the GraphViz DOT
+    /// language for debugging. Each node is identified by its index in the
+    /// arena and labeled with the result of the closure. The output can be
+    /// rendered with the GraphViz tools, e.g. `dot -Tsvg tree.dot`.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let dot = arena.to_dot(root_token, |data| data.to_string());
+    /// assert_eq!(dot, "digraph {
+    ///     1 [label=\"Indo-European\"];
+    ///     2 [label=\"Germanic\"];
+    ///     3 [label=\"Romance\"];
+    ///     1 -> 2;
+    ///     1 -> 3;
+    /// }
+    /// ");
+    /// ```
+    #[doc(alias = "graphviz")]
+    pub fn to_dot<F>(&self, root: Token, label: F) -> String
+        where F: Fn(&T) -> String {
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        for node in root.subtree(self, TraversalOrder::Pre) {
+            let mut escaped = String::new();
+            for c in label(&node.data).chars() {
+                if c == '"' || c == '\\' { escaped.push('\\') }
+                escaped.push(c);
+            }
+            nodes.push_str(&format!("    {} [label=\"{}\"];\n",
+                                    node.token.index, escaped));
+            match node.parent {
+                Some(parent) if node.token != root => edges.push_str(
+                    &format!("    {} -> {};\n", parent.index, node.token.index)),
+                _ => ()  // the edge to the parent of the root is left out
+            }
+        }
+        format!("digraph {{\n{}{}}}\n", nodes, edges)
+    }
+
+    /// Renders the subtree rooted at the given node as text with box-drawing
+    /// connectors, in the manner of the `tree` command. Each node is labeled
+    /// with the result of the closure and every line ends with a newline.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// germanic.append(&mut arena, "Swedish");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let text = arena.pretty_print(root_token, |data| data.to_string());
+    /// assert_eq!(text, "\
+    /// Indo-European
+    /// ├── Germanic
+    /// │   ├── English
+    /// │   └── Swedish
+    /// └── Romance
+    /// ");
+    /// ```
+    pub fn pretty_print<F>(&self, root: Token, label: F) -> String
+        where F: Fn(&T) -> String {
+        let mut out = String::new();
+        // the ancestors of the current node below the root, each with whether
+        // more siblings follow it
+        let mut path: Vec<(Token, bool)> = Vec::new();
+        for node in root.subtree(self, TraversalOrder::Pre) {
+            if node.token != root {
+                while let Some(&(token, _)) = path.last() {
+                    match Some(token) == node.parent {
+                        true => break,
+                        false => { path.pop(); }
+                    }
+                }
+                for &(_, more) in path.iter() {
+                    out.push_str(match more {
+                        true => "│   ",
+                        false => "    "
+                    });
+                }
+                let more = node.next_sibling.is_some();
+                out.push_str(match more {
+                    true => "├── ",
+                    false => "└── "
+                });
+                path.push((node.token, more));
+            }
+            out.push_str(&label(&node.data));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl<T> Arena<T> where T: Clone {
+    /// Moves subtree with the root at the given node into its own arena. To
+    /// detach a given subtree root node from a tree into its own while
+    /// remaining in the same arena, use [`detach`] instead. To move the data
+    /// rather than cloning it, use [`split_off`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "a0";
+    /// let (mut arena1, root1) = Arena::with_data(root_data);
+    ///
+    /// let node1 = root1.append(&mut arena1, "a1");
+    /// let node2 = root1.append(&mut arena1, "b1");
+    /// let grandchild1 = node1.append(&mut arena1, "a2");
+    /// let grandchild2 = node2.append(&mut arena1, "b2");
+    ///
+    /// // split tree
+    /// let (arena2, root2) = arena1.split_at(node2);
+    ///
+    /// let arena1_elt: Vec<_> = root1.subtree(&arena1, TraversalOrder::Pre)
+    ///     .map(|x| x.data).collect();
+    /// let arena2_elt: Vec<_> = root2.subtree(&arena2, TraversalOrder::Pre)
+    ///     .map(|x| x.data).collect();
+    ///
+    /// assert_eq!(&["a0", "a1", "a2"], &arena1_elt[..]);
+    /// assert_eq!(&["b1", "b2"], &arena2_elt[..]);
+    /// ```
+    ///
+    /// [`detach`]: struct.Token.html#method.detach
+    /// [`split_off`]: struct.Arena.html#method.split_off
+    // TODO: could probably be optimized
+    pub fn split_at(&mut self, token: Token) -> (Self, Token) where T: Clone {
+        let root_data = match self.get(token) {
+            Some(node) => node.data.clone(),
+            None => panic!("Invalid token")
+        };
+        let (mut arena, root) = Arena::with_data(root_data);
+        for child_token in token.children_tokens(self) {
+            arena.copy_and_append_subtree(root, self, child_token);
+        }
+        self.uproot(token);
+        (arena, root)
+    }
+
+    /// Copies a sub-tree from one arena and append to the given node of another.
+    /// It does so by walking the tree and copying node by node to the target
+    /// arena.  Potentially expensive operation.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "John";
+    /// let (mut arena1, root_token) = Arena::with_data(root_data);
+    ///
+    /// let node1 = root_token.append(&mut arena1, "Juan");
+    /// let node2 = root_token.append(&mut arena1, "Giovanni");
+    /// let grandchild1 = node1.append(&mut arena1, "Ivan");
+    /// let grandchild2 = node2.append(&mut arena1, "Johann");
+    ///
+    /// // new arena
+    /// let mut arena2 = arena1.clone();
+    ///
+    /// // append "node1" from tree2 under "node2" in tree1
+    /// arena1.copy_and_append_subtree(node2, &arena2, node1);
+    /// let mut subtree = node2.subtree(&arena1, TraversalOrder::Pre);
+    ///
+    /// assert_eq!(subtree.next().unwrap().data, "Giovanni");
+    /// assert_eq!(subtree.next().unwrap().data, "Johann");
+    /// assert_eq!(subtree.next().unwrap().data, "Juan");
+    /// assert_eq!(subtree.next().unwrap().data, "Ivan");
+    /// assert!(subtree.next().is_none());
+    /// ```
+    pub fn copy_and_append_subtree(&mut self, self_token: Token,
+                                   other_tree: &Arena<T>, other_token: Token) {
+        if other_tree.get(other_token).is_none() { panic!("Invalid token") }
+        // the path from the root of the copied subtree to the current node, as
+        // pairs of the tokens in the other arena and in this one
+        let mut path: Vec<(Token, Token)> = Vec::new();
+        for node in other_token.subtree(other_tree, TraversalOrder::Pre) {
+            while let Some(&(other, _)) = path.last() {
+                match Some(other) == node.parent {
+                    true => break,
+                    false => { path.pop(); }
+                }
+            }
+            let new_parent = match path.last() {
+                Some(&(_, new)) => new,
+                None => self_token
+            };
+            let new_token = new_parent.append(self, node.data.clone());
+            path.push((node.token, new_token));
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T> Arena<T> where T: Sync {
+    /// Returns a parallel iterator of the tokens of the root nodes in the
+    /// arena, i.e. one token for each of the trees in the arena. The order of
+    /// iteration is unspecified. Requires the `rayon` feature.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    /// use rayon::prelude::*;
+    ///
+    /// let mut arena = Arena::new();
+    /// for i in 0..10 {
+    ///     let root = arena.new_node(i);
+    ///     root.append(&mut arena, i);
+    /// }
+    ///
+    /// let sum: usize = arena.par_roots()
+    ///     .map(|root| root.subtree(&arena, TraversalOrder::Pre)
+    ///                     .map(|x| x.data)
+    ///                     .sum::<usize>())
+    ///     .sum();
+    /// assert_eq!(sum, 90);
+    /// ```
+    pub fn par_roots(&self) -> impl ParallelIterator<Item=Token> + '_ {
+        self.allocator.par_iter().filter_map(|(token, node)| match node.parent {
+            None => Some(token),
+            Some(_) => None
+        })
+    }
+}
+
+/// Moves the data out of the arena. The data of all nodes in the arena are
+/// yielded regardless of the tree they belong to. The order of iteration is
+/// unspecified.
+///
+/// # Examples:
+///
+/// ```
+/// use atree::Arena;
+///
+/// let root_data = "Indo-European";
+/// let (mut arena, root_token) = Arena::with_data(root_data);
+/// root_token.append(&mut arena, "Germanic");
+/// root_token.append(&mut arena, "Romance");
+///
+/// let mut data: Vec<_> = arena.into_iter().collect();
+/// data.sort();
+/// assert_eq!(&["Germanic", "Indo-European", "Romance"], &data[..]);
+/// ```
+impl<T> IntoIterator for Arena<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { iter: self.allocator.into_iter() }
+    }
+}
+
+/// Prints the trees in the arena with their nodes nested under their parents
+/// in the same way as [`Arena::pretty_print`]. The trees are numbered and come
+/// in storage order while children come in order.
+///
+/// # Examples:
+///
+/// ```
+/// use atree::Arena;
+///
+/// let (mut arena, root_token) = Arena::with_data("Indo-European");
+/// let germanic = root_token.append(&mut arena, "Germanic");
+/// germanic.append(&mut arena, "English");
+/// root_token.append(&mut arena, "Romance");
+/// arena.new_node("Basque");
+///
+/// assert_eq!(format!("{:?}", arena), "\
+/// Arena {
+///     Tree 1:
+///         \"Indo-European\"
+///         ├── \"Germanic\"
+///         │   └── \"English\"
+///         └── \"Romance\"
+///     Tree 2:
+///         \"Basque\"
+/// }");
+/// ```
+///
+/// [`Arena::pretty_print`]: struct.Arena.html#method.pretty_print
+impl<T> fmt::Debug for Arena<T> where T: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let roots = self.iter().filter(|node| node.parent.is_none());
+        let mut empty = true;
+        for (i, root) in roots.enumerate() {
+            if empty { f.write_str("Arena {\n")? }
+            empty = false;
+            writeln!(f, "    Tree {}:", i + 1)?;
+            let tree = self.pretty_print(root.token, |data| format!("{:?}", data));
+            for line in tree.lines() { writeln!(f, "        {}", line)? }
+        }
+        match empty {
+            true => f.write_str("Arena {}"),
+            false => f.write_str("}")
+        }
+    }
+}
+
+impl<T> Index<Token> for Arena<T> {
+    type Output = Node<T>;
+    fn index(&self, index: Token) -> &Self::Output {
+        match self.get(index) {
+            Some(node) => node,
+            None => panic!("Invalid token: {}", index.index)
+        }
+    }
+}
+
+impl<T> IndexMut<Token> for Arena<T> {
+    fn index_mut(&mut self, index: Token) -> &mut Self::Output {
+        match self.allocator.get_mut(index) {
+            Some(node) => match node.frozen {
+                true => panic!("Frozen node: {}", index.index),
+                false => node
+            },
+            None => panic!("Invalid token: {}", index.index)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn update() {
+        #[derive(Debug, PartialEq)]
+        enum Expr { Literal(String), Quoted(String) }
+
+        let (mut arena, root_token) = Arena::with_data(Expr::Literal("a".into()));
+        let child_token = root_token.append(&mut arena, Expr::Literal("b".into()));
+
+        let quote = |expr| match expr {
+            Expr::Literal(s) => Expr::Quoted(s),  // moves the string out
+            quoted => quoted
+        };
+        arena.update(child_token, quote).unwrap();
+        assert_eq!(arena[child_token].data, Expr::Quoted("b".into()));
+        assert_eq!(arena[root_token].data, Expr::Literal("a".into()));
+        assert_eq!(arena[child_token].parent(), Some(root_token));
+        assert_eq!(arena[root_token].first_child(), Some(child_token));
+        assert_eq!(arena.node_count(), 2);
+
+        arena.uproot(child_token);
+        assert!(arena.update(child_token, quote).is_err());
+    }
+
+    #[test]
+    fn update_panics() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchildren = children[1].append_children(&mut arena, 4..6usize);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            arena.update(children[1], |_| panic!("update failed"))
+        }));
+        assert!(result.is_err());
+        assert!(arena.validate().is_ok());
+        assert!(!arena.contains(children[1]));
+        assert_eq!(arena.node_count(), 5);
+        let data: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+        assert_eq!(data, vec![1, 3]);
+        assert!(arena[grandchildren[0]].is_root());
+        assert!(arena[grandchildren[1]].is_root());
+        // the freed cell is reused
+        let token = root_token.append(&mut arena, 6);
+        assert_eq!(token.slot(), children[1].slot());
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn into_iter() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        first_child.append(&mut arena, 4usize);
+        let grandchild = second_child.append(&mut arena, 5usize);
+        grandchild.append(&mut arena, 6usize);
+        arena.new_node(7usize);
+        arena.remove(first_child);
+        arena.uproot(grandchild);
+
+        let mut data: Vec<_> = arena.into_iter().collect();
+        data.sort_unstable();
+        assert_eq!(&[1, 3, 4, 7], &data[..]);
+    }
+
+    #[test]
+    fn map_with_token() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let grandchild = second_child.append(&mut arena, 4usize);
+        arena.uproot(first_child);
+        assert!(arena.validate().is_ok());
+
+        let new_arena = arena.map_with_token(|token, &data| (token, data * 10));
+        assert_eq!(new_arena.node_count(), 3);
+        for token in root_token.subtree_tokens(&arena, TraversalOrder::Pre) {
+            assert_eq!(new_arena[token].data, (token, arena[token].data * 10));
+        }
+        assert_eq!(new_arena[grandchild].parent(), Some(second_child));
+        assert_eq!(new_arena[root_token].first_child(), Some(second_child));
+        assert!(new_arena.validate().is_ok());
+    }
+
+    #[test]
+    fn from_depth_list() {
+        let items = vec![
+            (0, "a"),
+            (1, "b"),
+            (2, "c"),
+            (3, "d"),
+            (1, "e"),
+            (2, "f"),
+            (2, "g"),
+            (0, "h"),
+            (1, "i")
+        ];
+        let (arena, tokens) = Arena::from_depth_list(items).unwrap();
+        assert_eq!(arena.node_count(), 9);
+
+        let data: Vec<_> = tokens[0].subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(&["a", "b", "c", "d", "e", "f", "g"], &data[..]);
+        assert_eq!(arena[tokens[6]].parent(), Some(tokens[4]));
+        assert_eq!(arena[tokens[4]].previous_sibling(), Some(tokens[1]));
+        assert_eq!(arena[tokens[7]].parent(), None);
+        assert_eq!(arena[tokens[8]].parent(), Some(tokens[7]));
+        assert!(arena.validate().is_ok());
+
+        match Arena::from_depth_list(vec![(0, "a"), (1, "b"), (3, "c")]) {
+            Err(Error::InvalidDepth) => (),
+            _ => panic!("accepted a jump of two levels")
+        }
+        assert!(Arena::from_depth_list(vec![(1, "a")]).is_err());
+        let (arena, tokens) = Arena::<&str>::from_depth_list(vec![]).unwrap();
+        assert!(arena.is_empty());
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn sync() {
+        fn is_sync<T: Sync>() {}
+        is_sync::<Arena<usize>>();
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_roots() {
+        use rayon::prelude::*;
+
+        let mut arena = Arena::new();
+        for i in 0..100usize {
+            let root = arena.new_node(i);
+            for j in 0..i % 7 {
+                let child = root.append(&mut arena, i * j);
+                child.append(&mut arena, j);
+            }
+        }
+        let sum_tree = |root: Token| -> usize {
+            root.subtree(&arena, TraversalOrder::Pre).map(|x| x.data).sum()
+        };
+
+        let mut par_roots: Vec<_> = arena.par_roots().collect();
+        par_roots.sort_by_key(|t| t.index);
+        let roots: Vec<_> = arena.allocator.iter()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(token, _)| token)
+            .collect();
+        assert_eq!(roots.len(), 100);
+        assert_eq!(roots, par_roots);
+
+        let par_sum: usize = arena.par_roots().map(sum_tree).sum();
+        let sum: usize = roots.into_iter().map(sum_tree).sum();
+        assert_eq!(par_sum, sum);
+    }
+
+    #[test]
+    fn records() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        let a = root.append(&mut arena, 1);
+        a.append(&mut arena, 2);
+        a.append(&mut arena, 3);
+        let b = root.append(&mut arena, 4);
+        b.append(&mut arena, 5);
+
+        let records = arena.to_records(root);
+        assert_eq!(records.len(), 6);
+        for record in records.iter() {
+            if let Some(parent) = record.parent { assert!(parent < record.id) }
+        }
+        let subtree = arena.to_records(b);
+        assert_eq!(subtree[0], Record { id: 0, parent: None, data: 4 });
+        assert_eq!(subtree[1], Record { id: 1, parent: Some(0), data: 5 });
+
+        let (new_arena, tokens) = Arena::from_records(records.clone()).unwrap();
+        assert!(new_arena.validate().is_ok());
+        assert_eq!(new_arena.node_count(), 6);
+        assert_eq!(new_arena.to_records(tokens[0]), records);
+        let new_data: Vec<_> = tokens[0].subtree(&new_arena, TraversalOrder::Level)
+            .map(|x| x.data).collect();
+        let data: Vec<_> = root.subtree(&arena, TraversalOrder::Level)
+            .map(|x| x.data).collect();
+        assert_eq!(new_data, data);
+
+        let mut duplicate = records;
+        duplicate[2].id = 1;
+        assert!(Arena::from_records(duplicate).is_err());
+    }
+
+    #[test]
+    fn index() {
+        let (mut arena, root) = Arena::with_data(1usize);
+        let child = root.append(&mut arena, 2);
+        assert_eq!(arena[root].data, 1);
+        assert_eq!(arena[child].data, 2);
+        arena[child].data = 3;
+        assert_eq!(arena[child].data, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid token: 2")]
+    fn index_removed_token() {
+        let (mut arena, root) = Arena::with_data(1usize);
+        let child = root.append(&mut arena, 2);
+        arena.remove(child);
+        let _ = &arena[child];
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid token: 2")]
+    fn index_mut_removed_token() {
+        let (mut arena, root) = Arena::with_data(1usize);
+        let child = root.append(&mut arena, 2);
+        arena.remove(child);
+        arena[child].data = 3;
+    }
+
+    #[test]
+    fn get_disjoint_mut() {
+        let (mut arena, root) = Arena::with_data(1usize);
+        let a = root.append(&mut arena, 2);
+        let b = root.append(&mut arena, 3);
+
+        let [x, y] = arena.get_disjoint_mut([b, a]).unwrap();
+        assert_eq!((x.data, y.data), (3, 2));
+        x.data = 30;
+        y.data = 20;
+        assert_eq!(arena[a].data, 20);
+        assert_eq!(arena[b].data, 30);
+
+        let [x, y, z] = arena.get_disjoint_mut([root, a, b]).unwrap();
+        assert_eq!((x.data, y.data, z.data), (1, 20, 30));
+
+        assert!(arena.get_disjoint_mut([a, a]).is_none());
+        assert!(arena.get_disjoint_mut([root, a, root]).is_none());
+
+        arena.remove(b);
+        assert!(arena.get_disjoint_mut([root, b]).is_none());
+        let invalid = Token::new(NonZeroUsize::new(10).unwrap(), 0);
+        assert!(arena.get_disjoint_mut([invalid]).is_none());
+        assert!(arena.get_disjoint_mut([]).is_some());
+    }
+
+    #[test]
+    fn freeze_subtree() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        let template = root.append(&mut arena, 1);
+        let leaf = template.append(&mut arena, 2);
+        let sibling = root.append(&mut arena, 3);
+
+        arena.freeze_subtree(template);
+        assert!(arena[template].is_frozen());
+        assert!(arena[leaf].is_frozen());
+        assert!(!arena[root].is_frozen());
+        assert!(!arena[sibling].is_frozen());
+
+        // the frozen subtree cannot be edited
+        assert!(matches!(template.try_append(&mut arena, 4), Err(Error::FrozenNode)));
+        assert!(matches!(leaf.try_append(&mut arena, 4), Err(Error::FrozenNode)));
+        assert!(arena.get_mut(leaf).is_none());
+        assert!(arena.get_disjoint_mut([root, leaf]).is_none());
+        assert!(matches!(arena.update(leaf, |x| x + 1), Err(Error::FrozenNode)));
+        assert!(matches!(sibling.move_to(&mut arena, template),
+                         Err(Error::FrozenNode)));
+        let other = arena.new_node(5);
+        assert!(matches!(template.append_node(&mut arena, other),
+                         Err(Error::FrozenNode)));
+        assert!(matches!(leaf.insert_node_after(&mut arena, other),
+                         Err(Error::FrozenNode)));
+
+        // but its sibling can
+        let child = sibling.append(&mut arena, 6);
+        sibling.append_node(&mut arena, other).unwrap();
+        arena[sibling].data = 30;
+        template.insert_after(&mut arena, 7);
+        assert!(arena.validate().is_ok());
+
+        // the mutable iterators skip over frozen nodes
+        for node in root.subtree_mut(&mut arena, TraversalOrder::Pre) {
+            node.data += 100;
+        }
+        let data: Vec<_> = root.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data).collect();
+        assert_eq!(data, vec![100, 1, 2, 107, 130, 106, 105]);
+        let data: Vec<_> = root.children_mut(&mut arena).map(|x| x.data).collect();
+        assert_eq!(data, vec![107, 130]);
+        assert_eq!(arena[child].data, 106);
+
+        arena.unfreeze_subtree(template);
+        assert!(!arena[leaf].is_frozen());
+        arena[leaf].data = 20;
+        arena.uproot(template);
+        assert_eq!(arena.node_count(), 5);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Frozen node")]
+    fn append_to_frozen_node() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        arena.freeze_subtree(root);
+        root.append(&mut arena, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Frozen node: 2")]
+    fn index_mut_frozen_node() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        let child = root.append(&mut arena, 1);
+        arena.freeze_subtree(child);
+        arena[child].data = 2;
+    }
+
+    #[test]
+    #[should_panic(expected = "Frozen node")]
+    fn remove_frozen_node() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        let child = root.append(&mut arena, 1);
+        child.append(&mut arena, 2);
+        arena.freeze_subtree(child);
+        arena.remove(child);
+    }
+
+    #[test]
+    #[should_panic(expected = "Frozen node")]
+    fn uproot_frozen_descendant() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        let child = root.append(&mut arena, 1);
+        let grandchild = child.append(&mut arena, 2);
+        arena.freeze_subtree(grandchild);
+        arena.uproot(root);
+    }
+
+    /// Instances of `Tracked` record their own drops so that tests can check
+    /// that every value is dropped exactly once.
+    #[derive(Debug)]
+    struct Tracked {
+        id: usize,
+        drops: Rc<RefCell<Vec<usize>>>
+    }
+
+    impl Clone for Tracked {
+        fn clone(&self) -> Self {
+            let mut drops = self.drops.borrow_mut();
+            drops.push(0);
+            Tracked { id: drops.len() - 1, drops: self.drops.clone() }
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            let mut drops = self.drops.borrow_mut();
+            assert!(drops[self.id] == 0, "double drop of {}", self.id);
+            drops[self.id] += 1;
+        }
+    }
+
+    struct Tracker { drops: Rc<RefCell<Vec<usize>>> }
+
+    impl Tracker {
+        fn new() -> Self { Tracker { drops: Rc::new(RefCell::new(Vec::new())) } }
+
+        fn make(&self) -> Tracked {
+            let mut drops = self.drops.borrow_mut();
+            drops.push(0);
+            Tracked { id: drops.len() - 1, drops: self.drops.clone() }
+        }
+
+        fn live(&self) -> usize {
+            self.drops.borrow().iter().filter(|&&n| n == 0).count()
+        }
+    }
+
+    #[test]
+    fn drop_exactly_once() {
+        let tracker = Tracker::new();
+        let mut arena = Arena::new();
+        let root = arena.new_node(tracker.make());
+        let mut tokens = Vec::new();
+        for i in 0..20 {
+            let parent = match i % 3 { 0 => root, _ => *tokens.last().unwrap() };
+            tokens.push(parent.append(&mut arena, tracker.make()));
+        }
+        assert_eq!(tracker.live(), 21);
+
+        // removing drops the node and nothing else
+        let removed = arena.remove(tokens[4]);
+        drop(removed);
+        assert_eq!(tracker.live(), 20);
+
+        // uprooting drops the whole subtree
+        let size = tokens[9].subtree_size(&arena);
+        arena.uproot(tokens[9]);
+        assert_eq!(tracker.live(), 20 - size);
+
+        // reusing the freed cells does not drop anything
+        for _ in 0..10 { root.append(&mut arena, tracker.make()); }
+        assert_eq!(tracker.live(), 30 - size);
+
+        // replacing data drops the old data only
+        let token = root.append(&mut arena, tracker.make());
+        let new = tracker.make();
+        arena.update(token, |_| new).unwrap();
+        assert_eq!(tracker.live(), 31 - size);
+
+        // clones are independent of the originals
+        let clone = arena.clone();
+        drop(clone);
+        assert_eq!(tracker.live(), 31 - size);
+
+        // moving data out of the arena transfers ownership
+        let mut iter = arena.into_iter();
+        let first = iter.next().unwrap();
+        drop(iter);
+        assert_eq!(tracker.live(), 1);
+        drop(first);
+        assert_eq!(tracker.live(), 0);
+
+        let (mut arena, root) = Arena::with_data(tracker.make());
+        root.append_children(&mut arena, (0..5).map(|_| tracker.make()));
+        drop(arena);
+        assert_eq!(tracker.live(), 0);
+    }
+
+    #[test]
+    fn split_off() {
+        let (mut arena, root) = Arena::with_data(String::from("a"));
+        let b = root.append(&mut arena, String::from("b"));
+        let c = root.append(&mut arena, String::from("c"));
+        let d = root.append(&mut arena, String::from("d"));
+        let e = c.append(&mut arena, String::from("e"));
+        c.append(&mut arena, String::from("f"));
+        e.append(&mut arena, String::from("g"));
+
+        let (new_arena, new_root) = arena.split_off(c);
+        assert_eq!(arena.node_count(), 3);
+        assert_eq!(new_arena.node_count(), 4);
+        assert_eq!(new_arena.capacity(), 4);
+        assert!(arena.validate().is_ok());
+        assert!(new_arena.validate().is_ok());
+
+        let data: Vec<_> = new_root.subtree(&new_arena, TraversalOrder::Pre)
+            .map(|x| x.data.as_str()).collect();
+        assert_eq!(data, vec!["c", "e", "g", "f"]);
+        assert!(new_arena[new_root].parent().is_none());
+        assert!(new_arena[new_root].next_sibling().is_none());
+
+        // the tree is closed up where the subtree used to be
+        let data: Vec<_> = root.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data.as_str()).collect();
+        assert_eq!(data, vec!["a", "b", "d"]);
+        assert_eq!(arena[b].next_sibling(), Some(d));
+        assert_eq!(arena[d].previous_sibling(), Some(b));
+        assert!(arena.get(c).is_none());
+        assert!(arena.get(e).is_none());
+
+        // the freed cells are reused
+        let capacity = arena.capacity();
+        for _ in 0..4 { root.append(&mut arena, String::new()); }
+        assert_eq!(arena.capacity(), capacity);
+    }
+
+    #[test]
+    fn reserve() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        for i in 1..10 {
+            root_token.append(&mut arena, i).append(&mut arena, i);
+        }
+        arena.remove(arena[root_token].first_child().unwrap());
+
+        arena.reserve(100);
+        let capacity = arena.capacity();
+        assert!(capacity - arena.node_count() >= 100);
+        for i in 0..100 { root_token.append(&mut arena, i); }
+        assert_eq!(arena.capacity(), capacity);
+
+        // reserving what is already available is a no-op
+        arena.reserve(capacity - arena.node_count());
+        assert_eq!(arena.capacity(), capacity);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn grow_on_small_stack() {
+        // growing the arena used to walk the whole free list recursively
+        let handle = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let mut arena = Arena::new();
+                let tokens: Vec<_> = (0..200_000usize)
+                    .map(|i| arena.new_node(i))
+                    .collect();
+                for &token in tokens.iter().step_by(2) { arena.remove(token); }
+                arena.reserve(300_000);
+                for i in 0..300_000usize { arena.new_node(i); }
+                assert_eq!(arena.node_count(), 400_000);
+                assert!(arena.validate().is_ok());
+            })
+            .unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[test]
+    fn precompute_depths() {
+        fn naive_lca(arena: &Arena<usize>, a: Token, b: Token) -> Option<Token> {
+            let path: Vec<_> = core::iter::once(a)
+                .chain(a.ancestors_tokens(arena))
+                .collect();
+            core::iter::once(b)
+                .chain(b.ancestors_tokens(arena))
+                .find(|t| path.contains(t))
+        }
+
+        // a small linear congruential generator to build arbitrary trees
+        let mut seed = 12345u64;
+        let mut rand = move |n: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as usize % n
+        };
+        let mut arena = Arena::new();
+        let mut tokens = vec![arena.new_node(0usize), arena.new_node(1usize)];
+        for i in 2..300 {
+            let parent = tokens[rand(tokens.len())];
+            tokens.push(parent.append(&mut arena, i));
+        }
+
+        let index = arena.precompute_depths();
+        for &token in tokens.iter() {
+            assert_eq!(index.depth(token), Some(token.ancestors_tokens(&arena).count()));
+        }
+        for _ in 0..500 {
+            let a = tokens[rand(tokens.len())];
+            let b = tokens[rand(tokens.len())];
+            assert_eq!(index.lca(&arena, a, b), naive_lca(&arena, a, b));
+        }
+        let leaf = tokens[1].subtree_tokens(&arena, TraversalOrder::Post)
+            .next()
+            .unwrap();
+        assert_eq!(index.lca(&arena, leaf, tokens[0]), None);
+        assert_eq!(index.lca(&arena, tokens[0], tokens[0]), Some(tokens[0]));
+
+        // a node in a reused cell is not covered by the old index
+        arena.uproot(leaf);
+        let fresh = tokens[0].append(&mut arena, 300);
+        assert_eq!(fresh.slot(), leaf.slot());
+        assert_eq!(index.depth(fresh), None);
+        assert_eq!(index.lca(&arena, fresh, tokens[0]), None);
+    }
+
+    #[test]
+    fn iter() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        for i in 1..20 { root_token.append(&mut arena, i); }
+        let other_root = arena.new_node(100usize);
+        other_root.append(&mut arena, 200usize);
+        let removed: Vec<_> = root_token.children_tokens(&arena)
+            .step_by(3)
+            .collect();
+        for token in removed { arena.remove(token); }
+
+        assert_eq!(arena.iter().count(), arena.node_count());
+        for node in arena.iter() {
+            assert!(core::ptr::eq(&arena[node.token()], node));
+        }
+
+        for node in arena.iter_mut() { node.data += 1000 }
+        assert_eq!(arena.iter_mut().count(), arena.node_count());
+        assert!(arena.iter().all(|node| node.data >= 1000));
+
+        // frozen nodes are the only live nodes left out
+        arena.freeze_subtree(other_root);
+        let mut visited: Vec<_> = arena.iter_mut()
+            .map(|node| { node.data += 1000; node.token() })
+            .collect();
+        assert_eq!(visited.len(), arena.node_count() - 2);
+        visited.sort();
+        visited.dedup();
+        assert_eq!(visited.len(), arena.node_count() - 2);
+        let unfrozen: Vec<_> = arena.iter()
+            .filter(|node| !node.is_frozen())
+            .map(|node| node.token())
+            .collect();
+        assert_eq!(visited, unfrozen);
+        assert_eq!(arena[other_root].data, 1100);
+    }
+
+    #[test]
+    fn to_dot() {
+        let (mut arena, root_token) = Arena::with_data("root");
+        let a = root_token.append(&mut arena, "a");
+        a.append(&mut arena, "say \"hi\"");
+        root_token.append(&mut arena, "b");
+
+        let dot = arena.to_dot(a, |data| data.to_string());
+        assert_eq!(dot, "digraph {\n    2 [label=\"a\"];\n    \
+                         3 [label=\"say \\\"hi\\\"\"];\n    2 -> 3;\n}\n");
+
+        let dot = arena.to_dot(root_token, |data| data.to_uppercase());
+        for line in ["1 [label=\"ROOT\"];", "2 [label=\"A\"];",
+                     "4 [label=\"B\"];", "1 -> 2;", "2 -> 3;", "1 -> 4;"].iter() {
+            assert!(dot.lines().any(|l| l.trim() == *line));
+        }
+        assert_eq!(dot.lines().filter(|l| l.contains("->")).count(), 3);
+    }
+
+    #[test]
+    fn pretty_print() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchild = children[0].append(&mut arena, 4usize);
+        grandchild.append_children(&mut arena, 5..7usize);
+        children[0].append(&mut arena, 7usize);
+        children[2].append(&mut arena, 8usize).append(&mut arena, 9usize);
+
+        let expected = "\
+0
+├── 1
+│   ├── 4
+│   │   ├── 5
+│   │   └── 6
+│   └── 7
+├── 2
+└── 3
+    └── 8
+        └── 9
+";
+        assert_eq!(arena.pretty_print(root_token, |x| x.to_string()), expected);
+
+        // the siblings of the given node do not show up
+        let expected = "\
+1
+├── 4
+│   ├── 5
+│   └── 6
+└── 7
+";
+        assert_eq!(arena.pretty_print(children[0], |x| x.to_string()), expected);
+        assert_eq!(arena.pretty_print(children[1], |x| x.to_string()), "2\n");
+    }
+
+    #[test]
+    fn extract_if() {
+        let build = || {
+            let (mut arena, root_token) = Arena::with_data(0usize);
+            let children = root_token.append_children(&mut arena, 1..4usize);
+            children[0].append_children(&mut arena, 4..6usize);
+            children[1].append(&mut arena, 6usize).append(&mut arena, 7usize);
+            (arena, root_token)
+        };
+        let data = |arena: &Arena<usize>, root: Token| root
+            .subtree(arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+
+        // all the leaves
+        let (mut arena, root_token) = build();
+        let leaf_tokens: Vec<_> = root_token.leaves_tokens(&arena).collect();
+        let leaves: Vec<_> = leaf_tokens.iter().map(|&t| arena[t].data).collect();
+        let removed = arena.extract_if(root_token, |x| leaves.contains(x));
+        assert_eq!(removed, vec![4, 5, 7, 3]);
+        assert_eq!(data(&arena, root_token), vec![0, 1, 2, 6]);
+        assert!(leaf_tokens.iter().all(|&t| arena.get(t).is_none()));
+        assert!(arena.validate().is_ok());
+
+        // interior nodes, including consecutive generations
+        let (mut arena, root_token) = build();
+        let removed = arena.extract_if(root_token, |x| [1, 2, 6].contains(x));
+        assert_eq!(removed, vec![1, 2, 6]);
+        assert_eq!(data(&arena, root_token), vec![0, 4, 5, 7, 3]);
+        let children: Vec<_> = root_token.children(&arena)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(children, vec![4, 5, 7, 3]);
+        assert!(arena.validate().is_ok());
+
+        // nothing matches
+        let (mut arena, root_token) = build();
+        assert!(arena.extract_if(root_token, |_| false).is_empty());
+        assert_eq!(data(&arena, root_token), vec![0, 1, 4, 5, 2, 6, 7, 3]);
+
+        // the root without a parent leaves its children as roots
+        let (mut arena, root_token) = build();
+        assert_eq!(arena.extract_if(root_token, |&x| x == 0), vec![0]);
+        assert_eq!(arena.node_count(), 7);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn contains() {
+        let mut large_arena = Arena::new();
+        let tokens: Vec<_> = (0..10usize).map(|i| large_arena.new_node(i))
+            .collect();
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let child = root_token.append(&mut arena, 1usize);
+        assert!(arena.contains(root_token));
+        assert!(arena.contains(child));
+
+        arena.remove(child);
+        assert!(!arena.contains(child));
+        assert!(arena.contains(root_token));
+
+        // a token from another, larger arena is out of range here
+        assert!(arena.capacity() < 10);
+        assert!(!arena.contains(tokens[9]));
+        assert!(large_arena.contains(tokens[9]));
+    }
+
+    #[test]
+    fn with_data_and_capacity() {
+        let (mut arena, root_token) = Arena::with_data_and_capacity(1usize, 50);
+        assert_eq!(arena[root_token].data, 1);
+        assert_eq!(arena.node_count(), 1);
+        let capacity = arena.capacity();
+        assert!(capacity >= 50);
+
+        for i in 1..50 { root_token.append(&mut arena, i); }
+        assert_eq!(arena.capacity(), capacity);
+
+        let (arena, root_token) = Arena::with_data_and_capacity(1usize, 0);
+        assert_eq!(arena[root_token].data, 1);
+        assert_eq!(arena.node_count(), 1);
+    }
+
+    #[test]
+    fn growth_strategy() {
+        let capacities = |strategy| {
+            let (mut arena, root_token) = Arena::with_data(0usize);
+            arena.set_growth(strategy);
+            let mut capacities = vec![arena.capacity()];
+            for i in 0..40 {
+                root_token.append(&mut arena, i);
+                if arena.capacity() != *capacities.last().unwrap() {
+                    capacities.push(arena.capacity());
+                }
+            }
+            assert!(arena.validate().is_ok());
+            capacities
+        };
+        assert_eq!(capacities(GrowthStrategy::Double), vec![1, 2, 4, 8, 16, 32, 64]);
+        assert_eq!(capacities(GrowthStrategy::Fixed(8)),
+                   vec![1, 9, 17, 25, 33, 41]);
+        assert_eq!(capacities(GrowthStrategy::Additive(3)),
+                   vec![1, 4, 10, 19, 31, 46]);
+        // growing by nothing would never make room
+        assert_eq!(capacities(GrowthStrategy::Fixed(0)).len(), 41);
+
+        let (mut arena, _) = Arena::with_data(0usize);
+        assert_eq!(arena.growth(), GrowthStrategy::Double);
+        arena.set_growth(GrowthStrategy::Fixed(8));
+        for i in 0..10 { arena.new_node(i); }
+        assert_eq!(arena.capacity(), 17);
+        let root_token = arena.new_node(10);
+        let remap = arena.compact_preserving_order(root_token);
+        assert_eq!(arena.growth(), GrowthStrategy::Fixed(8));
+        while arena.node_count() < arena.capacity() { arena.new_node(0); }
+        let capacity = arena.capacity();
+        remap[&root_token].append(&mut arena, 11);
+        assert_eq!(arena.capacity(), capacity + 8);
+    }
+
+    #[test]
+    fn from_nested() {
+        let leaf = |data| RecursiveTree { data, children: vec![] };
+        let tree = RecursiveTree {
+            data: 0usize,
+            children: vec![
+                RecursiveTree { data: 1, children: vec![leaf(3), leaf(4)] },
+                leaf(2),
+                RecursiveTree {
+                    data: 5,
+                    children: vec![RecursiveTree {
+                        data: 6,
+                        children: vec![leaf(7)]
+                    }]
+                }
+            ]
+        };
+        let (arena, root_token) = Arena::from_nested(tree.clone());
+        assert_eq!(arena.node_count(), 8);
+        assert_eq!(arena.capacity(), 8);
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![0, 1, 3, 4, 2, 5, 6, 7]);
+        assert!(arena.validate().is_ok());
+        assert_eq!(root_token.to_nested(&arena), tree);
+
+        let (arena, root_token) = Arena::from_nested(leaf(9));
+        assert_eq!(arena.node_count(), 1);
+        assert!(arena[root_token].is_leaf());
+    }
+
+    #[test]
+    fn debug() {
+        let mut arena = Arena::default();
+        assert_eq!(format!("{:?}", arena), "Arena {}");
+
+        let root_token = arena.new_node(1usize);
+        assert_eq!(format!("{:?}", arena), "Arena {\n    Tree 1:\n        1\n}");
+
+        let children = root_token.append_children(&mut arena, 2..4usize);
+        children[0].append(&mut arena, 4usize);
+        children[1].append(&mut arena, 5usize);
+        let other_root = arena.new_node(6usize);
+        other_root.append(&mut arena, 7usize);
+        let expected = "\
+Arena {
+    Tree 1:
+        1
+        ├── 2
+        │   └── 4
+        └── 3
+            └── 5
+    Tree 2:
+        6
+        └── 7
+}";
+        assert_eq!(format!("{:?}", arena), expected);
+
+        // freed cells do not show up
+        other_root.remove_children(&mut arena);
+        children[0].detach(&mut arena);
+        let expected = "\
+Arena {
+    Tree 1:
+        1
+        └── 3
+            └── 5
+    Tree 2:
+        2
+        └── 4
+    Tree 3:
+        6
+}";
+        assert_eq!(format!("{:?}", arena), expected);
+    }
+
+    #[test]
+    fn insert_into_empty_arena() {
+        let mut arena = Arena::new();
+        assert!(arena.is_empty());
+        let token = arena.new_node("a");
+        assert_eq!(arena[token].data, "a");
+        assert_eq!(arena.node_count(), 1);
+        assert!(arena.validate().is_ok());
+
+        // the free list runs dry and is refilled right at the boundary
+        for _ in 0..3 {
+            let token = arena.new_node("b");
+            arena.uproot(token);
+            assert_eq!(arena.node_count(), 1);
+            let token = arena.new_node("c");
+            assert_eq!(arena[token].data, "c");
+            arena.uproot(token);
+            assert!(arena.validate().is_ok());
+        }
+        arena.uproot(token);
+        assert!(arena.is_empty());
+        let token = arena.new_node("d");
+        assert_eq!(arena[token].data, "d");
+        assert!(arena.validate().is_ok());
+
+        let mut arena = Arena::with_capacity(0);
+        arena.new_node(1usize);
+        arena.new_node(2usize);
+        assert_eq!(arena.node_count(), 2);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn total_size_bytes() {
+        type Data = [u8; 64];
+        let cell_size = Allocator::<Node<Data>>::cell_size();
+        let size = |arena: &Arena<Data>| core::mem::size_of::<Arena<Data>>()
+            + arena.capacity() * cell_size;
+        let (mut arena, root_token) = Arena::with_data([0; 64]);
+        assert_eq!(arena.total_size_bytes(), size(&arena));
+        for _ in 0..20 { root_token.append(&mut arena, [1; 64]); }
+        assert_eq!(arena.total_size_bytes(), size(&arena));
+        assert!(cell_size >= core::mem::size_of::<Node<Data>>());
+        // removing nodes does not give back memory
+        let before = arena.total_size_bytes();
+        root_token.remove_children(&mut arena);
+        assert_eq!(arena.total_size_bytes(), before);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let (mut arena, root_token) = Arena::with_data(String::from("a"));
+        let children = root_token.append_children(&mut arena,
+            ["b", "c", "d"].iter().map(|x| x.to_string()));
+        let grandchild = children[1].append(&mut arena, String::from("e"));
+        arena.uproot(children[0]);
+        arena.freeze_subtree(children[2]);
+
+        let json = serde_json::to_string(&arena).unwrap();
+        let mut new_arena: Arena<String> = serde_json::from_str(&json).unwrap();
+        assert!(new_arena.validate().is_ok());
+        assert_eq!(new_arena.node_count(), arena.node_count());
+        assert_eq!(new_arena.capacity(), arena.capacity());
+        assert_eq!(format!("{:?}", new_arena), format!("{:?}", arena));
+        assert_eq!(new_arena[grandchild].parent(), Some(children[1]));
+        assert!(new_arena[children[2]].is_frozen());
+        assert!(!new_arena.contains(children[0]));
+        // the free list comes along too
+        assert_eq!(new_arena.new_node(String::from("f")),
+                   arena.new_node(String::from("f")));
+
+        let token: Token = serde_json::from_str(
+            &serde_json::to_string(&grandchild).unwrap()).unwrap();
+        assert_eq!(token, grandchild);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_inconsistent_arena() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let child = root_token.append(&mut arena, 2usize);
+        child.append(&mut arena, 3usize);
+        let json = serde_json::to_string(&arena).unwrap();
+        assert!(serde_json::from_str::<Arena<usize>>(&json).is_ok());
+
+        // the root claims a parent that is not in the arena
+        let dangling = json.replacen("\"parent\":null",
+                                     "\"parent\":{\"index\":9,\"generation\":0}", 1);
+        assert_ne!(dangling, json);
+        assert!(serde_json::from_str::<Arena<usize>>(&dangling).is_err());
+        // more nodes than the allocator has on record
+        let miscounted = json.replacen("\"len\":3", "\"len\":4", 1);
+        assert_ne!(miscounted, json);
+        assert!(serde_json::from_str::<Arena<usize>>(&miscounted).is_err());
+        // the free list points past the end of the arena
+        let past_end = json.replacen("\"head\":4", "\"head\":40", 1);
+        assert_ne!(past_end, json);
+        assert!(serde_json::from_str::<Arena<usize>>(&past_end).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_nested() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let child = root_token.append(&mut arena, 2usize);
+        child.append(&mut arena, 3usize);
+        root_token.append(&mut arena, 4usize);
+
+        let json = serde_json::to_string(&root_token.to_nested(&arena)).unwrap();
+        assert_eq!(json, "{\"data\":1,\"children\":[\
+                          {\"data\":2,\"children\":[\
+                          {\"data\":3,\"children\":[]}]},\
+                          {\"data\":4,\"children\":[]}]}");
+        let tree: RecursiveTree<usize> = serde_json::from_str(&json).unwrap();
+        let (new_arena, new_root) = Arena::from_nested(tree);
+        assert!(root_token.eq_subtree(&arena, new_root, &new_arena));
+    }
+
+    #[test]
+    fn stale_tokens() {
+        let (mut arena, root_token) = Arena::with_data("root");
+        let stale = root_token.append(&mut arena, "old");
+        arena.uproot(stale);
+        // the freed cell is reused right away
+        let fresh = root_token.append(&mut arena, "new");
+        assert_eq!(fresh.index, stale.index);
+        assert_ne!(fresh, stale);
+        assert!(arena.get(stale).is_none());
+        assert!(arena.get_mut(stale).is_none());
+        assert!(!arena.contains(stale));
+        assert_eq!(arena[fresh].data, "new");
+        assert_eq!(fresh.children_tokens(&arena).count(), 0);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn split_subtrees_mut() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let branches = root_token.append_children(&mut arena, 1..5usize);
+        for &branch in branches.iter() {
+            let data = arena[branch].data;
+            let child = branch.append(&mut arena, data * 10);
+            child.append_children(&mut arena, (0..50).map(|i| data * 100 + i));
+        }
+        let (left, right) = (branches[0], branches[2]);
+        let frozen = arena[right].first_child().unwrap();
+        arena.freeze_subtree(frozen);
+
+        let subtrees = arena.split_subtrees_mut(&[left, right]).unwrap();
+        std::thread::scope(|scope| {
+            for subtree in subtrees {
+                scope.spawn(move || subtree.for_each(|node| node.data += 100000));
+            }
+        });
+        let changed = |token: Token| token.subtree(&arena, TraversalOrder::Pre)
+            .filter(|node| node.data >= 100000)
+            .count();
+        assert_eq!(changed(left), 52);
+        assert_eq!(changed(right), 1);  // the rest is frozen
+        assert_eq!(changed(branches[1]), 0);
+        assert_eq!(changed(root_token), 53);
+
+        let grandchild = arena[left].first_child().unwrap();
+        assert!(arena.split_subtrees_mut(&[left, grandchild]).is_none());
+        assert!(arena.split_subtrees_mut(&[grandchild, left]).is_none());
+        assert!(arena.split_subtrees_mut(&[left, left]).is_none());
+        let removed = arena.new_node(0);
+        arena.uproot(removed);
+        assert!(arena.split_subtrees_mut(&[left, removed]).is_none());
+        assert_eq!(arena.split_subtrees_mut(&[]).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn swap_subtrees() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..5usize);
+        for &child in children.iter() {
+            let data = arena[child].data;
+            child.append_children(&mut arena, (0..2).map(|i| data * 10 + i));
+        }
+        let data = |arena: &Arena<usize>| root_token.subtree(arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+
+        // adjacent siblings, either way around
+        arena.swap_subtrees(children[0], children[1]).unwrap();
+        assert_eq!(data(&arena), vec![0, 2, 20, 21, 1, 10, 11, 3, 30, 31, 4, 40, 41]);
+        arena.swap_subtrees(children[0], children[1]).unwrap();
+        // siblings further apart, including the last child
+        arena.swap_subtrees(children[3], children[0]).unwrap();
+        assert_eq!(data(&arena), vec![0, 4, 40, 41, 2, 20, 21, 3, 30, 31, 1, 10, 11]);
+        assert_eq!(arena[root_token].first_child(), Some(children[3]));
+        assert_eq!(arena[root_token].last_child(), Some(children[0]));
+        assert!(arena.validate().is_ok());
+
+        // nodes with different parents
+        let a = arena[children[1]].first_child().unwrap();
+        let b = arena[children[2]].last_child().unwrap();
+        arena.swap_subtrees(a, b).unwrap();
+        assert_eq!(data(&arena), vec![0, 4, 40, 41, 2, 31, 21, 3, 30, 20, 1, 10, 11]);
+        assert_eq!(arena[a].parent(), Some(children[2]));
+        assert!(arena.validate().is_ok());
+
+        // with a node from a different tree
+        let other_root = arena.new_node(100);
+        arena.swap_subtrees(children[1], other_root).unwrap();
+        assert_eq!(data(&arena), vec![0, 4, 40, 41, 100, 3, 30, 20, 1, 10, 11]);
+        assert!(arena[children[1]].is_root());
+        assert!(arena.validate().is_ok());
+
+        arena.swap_subtrees(root_token, root_token).unwrap();
+        assert!(matches!(arena.swap_subtrees(children[2], a),
+                         Err(Error::CyclicGraph)));
+        assert!(matches!(arena.swap_subtrees(a, children[2]),
+                         Err(Error::CyclicGraph)));
+        arena.freeze_subtree(children[3]);
+        let c = arena[children[3]].first_child().unwrap();
+        assert!(matches!(arena.swap_subtrees(c, a), Err(Error::FrozenNode)));
+    }
+
+    #[test]
+    fn swap_parentless_siblings() {
+        // removing a root leaves its children as siblings without a parent
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let x = root_token.append(&mut arena, 1);
+        let y = root_token.append(&mut arena, 2);
+        let z = root_token.append(&mut arena, 3);
+        arena.remove(root_token);
+
+        arena.swap_subtrees(x, y).unwrap();
+        assert!(arena.validate().is_ok());
+        assert_eq!(arena[y].next_sibling(), Some(x));
+        assert_eq!(arena[x].previous_sibling(), Some(y));
+
+        arena.swap_subtrees(z, x).unwrap();
+        assert!(arena.validate().is_ok());
+        assert_eq!(arena[y].next_sibling(), Some(z));
+        assert_eq!(arena[z].next_sibling(), Some(x));
+        assert_eq!(arena[x].next_sibling(), None);
+
+        arena.swap_subtrees(y, x).unwrap();
+        assert!(arena.validate().is_ok());
+        assert_eq!(arena[x].next_sibling(), Some(z));
+        assert_eq!(arena[z].next_sibling(), Some(y));
+        assert_eq!(arena[x].previous_sibling(), None);
+    }
+
+    #[test]
+    fn transplant() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let parent = root_token.append(&mut arena, 1);
+        parent.append(&mut arena, 2);
+        let (mut scratch, scratch_root) = Arena::with_data(10usize);
+        let keep = scratch.new_node(100);
+        let branch = scratch_root.append(&mut scratch, 11);
+        branch.append_children(&mut scratch, 12..15);
+        scratch_root.append(&mut scratch, 15);
+
+        let moved = arena.transplant(&mut scratch, branch, parent);
+        assert!(arena.validate().is_ok());
+        assert!(scratch.validate().is_ok());
+        assert_eq!(scratch.node_count(), 3);
+        assert!(!scratch.contains(branch));
+        assert_eq!(scratch[keep].data, 100);
+        let data: Vec<_> = scratch_root.subtree(&scratch, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![10, 15]);
+        assert_eq!(arena[moved].parent(), Some(parent));
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![0, 1, 2, 11, 12, 13, 14]);
+
+        let moved = arena.transplant(&mut scratch, scratch_root, moved);
+        assert_eq!(moved.children(&arena).next().unwrap().data, 15);
+        assert_eq!(scratch.node_count(), 1);
+        assert_eq!(arena.node_count(), 9);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn drain_subtree() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchild = children[1].append(&mut arena, 20);
+        grandchild.append(&mut arena, 200);
+        children[1].append(&mut arena, 21);
+
+        let drain = arena.drain_subtree(children[1]);
+        assert_eq!(drain.len(), 4);
+        drop(drain);  // removed all the same
+        assert_eq!(arena.node_count(), 3);
+        assert!(!arena.contains(grandchild));
+        assert!(arena.validate().is_ok());
+        let data: Vec<_> = arena.drain_subtree(root_token).collect();
+        assert_eq!(data, vec![0, 1, 3]);
+        assert!(arena.is_empty());
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn retain() {
+        let mut arena = Arena::new();
+        for i in 0..10usize {
+            let root = arena.new_node(i);
+            for j in 0..i {
+                let child = root.append(&mut arena, j);
+                child.append(&mut arena, i + j);
+            }
+        }
+        arena.retain(|&x| x % 3 != 0);
+        assert!(arena.iter().all(|node| node.data % 3 != 0));
+        // every kept node has all its ancestors kept
+        let roots: Vec<_> = arena.iter()
+            .filter(|node| node.is_root())
+            .map(|node| node.data)
+            .collect();
+        assert_eq!(roots.len(), 6);
+        assert!(arena.iter()
+            .all(|node| node.ancestors(&arena).all(|a| a.data % 3 != 0)));
+        assert!(arena.validate().is_ok());
+
+        arena.retain(|_| false);
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn map() {
+        let (mut arena, root_token) = Arena::with_data(String::from("a"));
+        let removed = root_token.append(&mut arena, String::from("b"));
+        let child = root_token.append(&mut arena, String::from("c"));
+        child.append(&mut arena, String::from("d"));
+        arena.uproot(removed);
+        let frozen = arena.new_node(String::from("e"));
+        arena.freeze_subtree(frozen);
+
+        let lengths = arena.map_ref(|s| s.len());
+        assert!(lengths.validate().is_ok());
+        assert_eq!(root_token.to_nested(&lengths),
+                   root_token.to_nested(&arena.map_ref(|_| 1usize)));
+        assert!(lengths[frozen].is_frozen());
+
+        let mut seen = Vec::new();
+        let mapped = arena.map(|s| { seen.push(s.clone()); s + "!" });
+        assert_eq!(seen.len(), 4);
+        assert!(mapped.validate().is_ok());
+        assert_eq!(mapped.capacity(), lengths.capacity());
+        assert_eq!(mapped[child].data, "c!");
+        assert_eq!(mapped[child].parent(), Some(root_token));
+        assert!(!mapped.contains(removed));
+        assert!(mapped[frozen].is_frozen());
+        let data: Vec<_> = root_token.subtree(&mapped, TraversalOrder::Pre)
+            .map(|x| x.data.as_str())
+            .collect();
+        assert_eq!(data, vec!["a!", "c!", "d!"]);
+    }
+
+    #[test]
+    fn try_map() {
+        let (mut arena, root_token) = Arena::with_data(1i32);
+        let children = root_token.append_children(&mut arena, 2..6);
+        arena.uproot(children[0]);
+        let checked = |arena: Arena<i32>| {
+            let mut calls = 0;
+            let result = arena.try_map(|x| {
+                calls += 1;
+                match x % 4 {
+                    0 => Err(x),
+                    _ => Ok(x * 10)
+                }
+            });
+            (result, calls)
+        };
+
+        let (result, calls) = checked(arena.clone());
+        let (token, err) = result.err().unwrap();
+        assert_eq!((token, err), (children[2], 4));
+        assert_eq!(calls, 3);  // stops at the first error
+
+        arena.uproot(children[2]);
+        let (result, calls) = checked(arena);
+        let mapped = result.ok().unwrap();
+        assert_eq!(calls, 3);
+        assert!(mapped.validate().is_ok());
+        let data: Vec<_> = root_token.subtree(&mapped, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![10, 30, 50]);
+    }
+
+    #[test]
+    fn copy_and_append_subtree() {
+        let (mut arena1, root1) = Arena::with_data(0usize);
+        let first_child = root1.append(&mut arena1, 1);
+        first_child.append(&mut arena1, 2);
+        let second_child = root1.append(&mut arena1, 3);
+        second_child.append(&mut arena1, 4).append(&mut arena1, 5);
+        second_child.append(&mut arena1, 6);
+
+        let (mut arena2, root2) = Arena::with_data(10usize);
+        arena2.copy_and_append_subtree(root2, &arena1, root1);
+        let data: Vec<_> = root2.subtree(&arena2, TraversalOrder::Pre)
+            .map(|x| x.data).collect();
+        assert_eq!(data, vec![10, 0, 1, 2, 3, 4, 5, 6]);
+        assert!(arena2.validate().is_ok());
+
+        let (arena3, root3) = arena1.split_at(second_child);
+        let data: Vec<_> = root3.subtree(&arena3, TraversalOrder::Pre)
+            .map(|x| x.data).collect();
+        assert_eq!(data, vec![3, 4, 5, 6]);
+        assert_eq!(arena1.node_count(), 3);
+    }
+
+    #[test]
+    fn uproot_first_child() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let first_child = root_token.append(&mut arena, 1usize);
+        let second_child = root_token.append(&mut arena, 2usize);
+        second_child.append(&mut arena, 3usize);
+
+        arena.uproot(first_child);
+        assert_eq!(arena[second_child].previous_sibling(), None);
+        assert_eq!(arena[root_token].first_child(), Some(second_child));
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn validate() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+        assert!(arena.validate().is_ok());
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        first_child.append(&mut arena, 10usize);
+        let grandchild = second_child.append(&mut arena, 20usize);
+        second_child.insert_before(&mut arena, 5usize);
+        third_child.insert_after(&mut arena, 6usize);
+        grandchild.append(&mut arena, 100usize);
+        assert!(arena.validate().is_ok());
+
+        arena.uproot(grandchild);
+        arena.remove(first_child);
+        second_child.detach(&mut arena);
+        root_token.append(&mut arena, 7usize);
+        assert!(arena.validate().is_ok());
+
+        arena.allocator.set_len(arena.node_count() + 1);
+        assert!(arena.validate().is_err());
+    }
+
+    #[test]
+    fn validate_links() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+        let child = root_token.append(&mut arena, 2usize);
+        let grandchild = child.append(&mut arena, 3usize);
+
+        arena[child].parent = Some(grandchild);
+        assert!(arena.validate().is_err());
+        arena[child].parent = Some(root_token);
+        assert!(arena.validate().is_ok());
+
+        arena[root_token].first_child = None;
+        assert!(arena.validate().is_err());
+        arena[root_token].first_child = Some(child);
+        assert!(arena.validate().is_ok());
+
+        arena[root_token].last_child = None;
+        assert!(arena.validate().is_err());
+    }
+
+    #[test]
+    fn compact_invalidates_stale_tokens() {
+        let mut arena = Arena::new();
+        let a = arena.new_node("a");
+        let b = arena.new_node("b");
+        let c = arena.new_node("c");
+        arena.uproot(a);
+
+        let remap = arena.compact();
+        assert!(arena.get(b).is_none());
+        assert!(arena.get(c).is_none());
+        assert_eq!(arena[remap[&b]].data, "b");
+        assert_eq!(arena[remap[&c]].data, "c");
+        // cells past the compacted ones are of the new generation as well
+        let b = remap[&b];
+        let d = b.append(&mut arena, "d");
+        let e = d.append(&mut arena, "e");
+        assert!(arena.get(a).is_none());
+        assert_eq!(arena[e].data, "e");
+
+        let remap = arena.compact_preserving_order(b);
+        assert!(arena.get(b).is_none());
+        assert!(arena.get(d).is_none());
+        assert!(arena.get(e).is_none());
+        assert_eq!(arena[remap[&e]].data, "e");
+    }
+
+    #[test]
+    fn compact() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let mut tokens = vec![root_token];
+        for i in 1..50 {
+            let parent = tokens[i / 3];
+            tokens.push(parent.append(&mut arena, i));
+        }
+        for &token in tokens.iter().skip(2).step_by(4) {
+            if arena.contains(token) { arena.uproot(token) }
+        }
+        let survivors: Vec<_> = tokens.iter()
+            .copied()
+            .filter(|&t| arena.contains(t))
+            .collect();
+        let nested = root_token.to_nested(&arena);
+        let node_count = arena.node_count();
+
+        let remap = arena.compact();
+        assert!(arena.validate().is_ok());
+        assert_eq!(arena.capacity(), node_count);
+        assert_eq!(remap.len(), node_count);
+        assert_eq!(remap[&root_token].to_nested(&arena), nested);
+        // the nodes keep their relative order in memory
+        let indices: Vec<_> = survivors.iter()
+            .map(|t| remap[t].slot())
+            .collect();
+        assert_eq!(indices, (0..node_count).collect::<Vec<_>>());
+
+        let mut arena: Arena<usize> = Arena::new();
+        assert!(arena.compact().is_empty());
+        assert!(arena.is_empty());
+        arena.new_node(1);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..10usize);
+        arena.reserve(50);
+        arena.uproot(children[2]);
+        arena.uproot(children[8]);
+        arena.uproot(children[7]);
+        arena.shrink_to_fit();
+        assert!(arena.validate().is_ok());
+        // the hole left by children[2] is kept
+        assert_eq!(arena.capacity(), 8);
+        assert!(!arena.contains(children[8]));
+        assert_eq!(arena[children[6]].data, 7);
+
+        // the hole is filled first and stale tokens stay stale once the
+        // released cells are reused
+        let token = root_token.append(&mut arena, 20);
+        assert_eq!(token.index, children[2].index);
+        let tokens = root_token.append_children(&mut arena, 21..30usize);
+        assert!(arena.validate().is_ok());
+        assert!(tokens.iter().any(|t| t.index == children[8].index));
+        assert!(!arena.contains(children[8]));
+        assert!(!arena.contains(children[7]));
+
+        let mut arena: Arena<usize> = Arena::with_capacity(10);
+        arena.shrink_to_fit();
+        assert_eq!(arena.capacity(), 0);
+        arena.new_node(1);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn compact_preserving_order() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let other_root = arena.new_node(100usize);
+        other_root.append(&mut arena, 200usize);
+        let mut children = root_token.append_children(&mut arena, 2..6usize);
+        for &child in children.iter() {
+            let data = arena[child].data;
+            child.append_children(&mut arena, (0..3).map(|i| data * 10 + i));
+        }
+        arena.remove(children.remove(1));
+        children[2].insert_before(&mut arena, 7usize);
+        children[1].append(&mut arena, 8usize);
+        children[0].move_to(&mut arena, children[2]).unwrap();
+        let nested = root_token.to_nested(&arena);
+        let other_nested = other_root.to_nested(&arena);
+        let node_count = arena.node_count();
+
+        let remap = arena.compact_preserving_order(root_token);
+        assert!(arena.validate().is_ok());
+        assert_eq!(arena.node_count(), node_count);
+        assert_eq!(arena.capacity(), node_count);
+        assert_eq!(remap.len(), node_count);
+
+        let root_token = remap[&root_token];
+        assert_eq!(root_token.to_nested(&arena), nested);
+        assert_eq!(remap[&other_root].to_nested(&arena), other_nested);
+        let indices: Vec<_> = root_token.subtree_tokens(&arena, TraversalOrder::Pre)
+            .map(|t| t.slot())
+            .collect();
+        assert_eq!(indices, (0..indices.len()).collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,121 @@
+use crate::arena::Arena;
+use crate::token::Token;
+
+impl<T: Ord> Arena<T> {
+    /// Inserts the data into the binary search tree under `root` and returns
+    /// the token of the new node. The first child of a node stands for its
+    /// left subtree and the second child for its right subtree. Data that is
+    /// less than a node goes to its left and everything else goes to its
+    /// right. Requires the `bst` feature.
+    ///
+    /// This assumes that the tree is only ever modified through `bst_insert`.
+    /// A node with a single child is told apart by comparing the child with
+    /// the node, so a lone right child comes first among the children. Keep
+    /// that in mind when using methods that go by position, such as
+    /// [`inorder_successor`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(5);
+    /// let three = arena.bst_insert(root_token, 3);
+    /// let eight = arena.bst_insert(root_token, 8);
+    /// let four = arena.bst_insert(root_token, 4);
+    ///
+    /// assert_eq!(arena[root_token].first_child(), Some(three));
+    /// assert_eq!(arena[eight].parent(), Some(root_token));
+    /// assert_eq!(arena[four].parent(), Some(three));
+    /// ```
+    ///
+    /// [`inorder_successor`]: struct.Token.html#method.inorder_successor
+    pub fn bst_insert(&mut self, root: Token, data: T) -> Token {
+        let mut token = root;
+        loop {
+            let node = &self[token];
+            let (left, right) = match node.first_child {
+                None => (None, None),
+                Some(first) => match self[first].next_sibling {
+                    Some(second) => (Some(first), Some(second)),
+                    None => match self[first].data < node.data {
+                        true => (Some(first), None),
+                        false => (None, Some(first))
+                    }
+                }
+            };
+            match (data < node.data, left, right) {
+                (true, Some(left), _) => token = left,
+                (true, None, Some(right)) =>
+                    return right.insert_before(self, data),
+                (false, _, Some(right)) => token = right,
+                _ => return token.append(self, data)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iter::TraversalOrder;
+
+    fn inorder(arena: &Arena<i32>, token: Token, out: &mut Vec<i32>) {
+        let node = &arena[token];
+        let mut children = token.children_tokens(arena);
+        let (left, right) = match (children.next(), children.next()) {
+            (Some(first), None) => match arena[first].data < node.data {
+                true => (Some(first), None),
+                false => (None, Some(first))
+            },
+            (first, second) => (first, second)
+        };
+        if let Some(left) = left { inorder(arena, left, out) }
+        out.push(node.data);
+        if let Some(right) = right { inorder(arena, right, out) }
+    }
+
+    #[test]
+    fn bst_insert() {
+        let (mut arena, root_token) = Arena::with_data(50);
+        let mut expected = vec![50];
+        // a deterministic shuffle with plenty of duplicates
+        for i in 0..200 {
+            let data = (i * 37 + 11) % 101;
+            arena.bst_insert(root_token, data);
+            expected.push(data);
+        }
+        expected.sort();
+        let mut data = Vec::new();
+        inorder(&arena, root_token, &mut data);
+        assert_eq!(data, expected);
+        assert!(arena.validate().is_ok());
+        assert!(root_token.subtree_tokens(&arena, TraversalOrder::Pre)
+            .all(|t| t.children_tokens(&arena).count() <= 2));
+    }
+
+    #[test]
+    fn bst_insert_lone_right_child() {
+        let (mut arena, two) = Arena::with_data(2);
+        let three = arena.bst_insert(two, 3);
+        assert_eq!(arena[two].first_child(), Some(three));
+        let one = arena.bst_insert(two, 1);
+        let children: Vec<_> = two.children_tokens(&arena).collect();
+        assert_eq!(children, vec![one, three]);
+        // ascending input degenerates into a chain of right children
+        let (mut arena, root_token) = Arena::with_data(0);
+        for i in 1..10 { arena.bst_insert(root_token, i); }
+        let preorder: Vec<_> = root_token
+            .subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(preorder, (0..10).collect::<Vec<_>>());
+        let mut data = Vec::new();
+        inorder(&arena, root_token, &mut data);
+        assert_eq!(data, (0..10).collect::<Vec<_>>());
+    }
+}
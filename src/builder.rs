@@ -0,0 +1,143 @@
+//! A module that contains a builder for streaming construction of trees.
+use alloc::vec::Vec;
+
+use crate::Error;
+use crate::arena::Arena;
+use crate::token::Token;
+
+/// A builder that constructs trees in an arena from a stream of open, close
+/// and leaf events, much like the events emitted by a SAX-style parser. The
+/// builder keeps a stack of the nodes that are currently open. New nodes are
+/// appended to the innermost open node, or become roots of new trees if no
+/// node is open.
+///
+/// # Examples:
+///
+/// ```
+/// use atree::ArenaBuilder;
+///
+/// // <html><head/><body><p>text</p></body></html>
+/// let mut builder = ArenaBuilder::new();
+/// let html = builder.open("html");
+/// builder.leaf("head");
+/// let body = builder.open("body");
+/// builder.open("p");
+/// builder.leaf("text");
+/// builder.close().unwrap();
+/// builder.close().unwrap();
+/// builder.close().unwrap();
+///
+/// let (arena, roots) = builder.build().unwrap();
+/// assert_eq!(roots, vec![html]);
+/// let mut children = html.children(&arena).map(|x| x.data);
+/// assert_eq!(children.next(), Some("head"));
+/// assert_eq!(children.next(), Some("body"));
+/// assert!(children.next().is_none());
+/// assert_eq!(body.subtree_size(&arena), 3);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ArenaBuilder<T> {
+    arena: Arena<T>,
+    roots: Vec<Token>,
+    stack: Vec<Token>
+}
+
+impl<T> ArenaBuilder<T> {
+    /// Initializes a new `ArenaBuilder<T>`.
+    pub fn new() -> Self {
+        ArenaBuilder { arena: Arena::new(), roots: Vec::new(), stack: Vec::new() }
+    }
+
+    /// Adds a node with the given data and opens it such that subsequent nodes
+    /// become its descendants until it is closed. Returns the token of the new
+    /// node.
+    pub fn open(&mut self, data: T) -> Token {
+        let token = self.leaf(data);
+        self.stack.push(token);
+        token
+    }
+
+    /// Closes the innermost open node. Returns error if there is no open node.
+    pub fn close(&mut self) -> Result<(), Error> {
+        match self.stack.pop() {
+            None => Err(Error::Unbalanced),
+            Some(_) => Ok(())
+        }
+    }
+
+    /// Adds a node with the given data without opening it. Returns the token
+    /// of the new node.
+    pub fn leaf(&mut self, data: T) -> Token {
+        match self.stack.last() {
+            Some(&parent) => parent.append(&mut self.arena, data),
+            None => {
+                let token = self.arena.new_node(data);
+                self.roots.push(token);
+                token
+            }
+        }
+    }
+
+    /// Returns the number of nodes that are currently open.
+    pub fn depth(&self) -> usize { self.stack.len() }
+
+    /// Finishes building and returns the arena along with the tokens of the
+    /// roots of the trees in the order they were added. Returns error if any
+    /// node is left open.
+    pub fn build(self) -> Result<(Arena<T>, Vec<Token>), Error> {
+        match self.stack.is_empty() {
+            true => Ok((self.arena, self.roots)),
+            false => Err(Error::Unbalanced)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iter::TraversalOrder;
+
+    #[test]
+    fn build() {
+        let mut builder = ArenaBuilder::new();
+        let a = builder.open(1usize);
+        builder.leaf(2);
+        builder.open(3);
+        assert_eq!(builder.depth(), 2);
+        builder.leaf(4);
+        builder.leaf(5);
+        builder.close().unwrap();
+        builder.leaf(6);
+        builder.close().unwrap();
+        assert_eq!(builder.depth(), 0);
+        let b = builder.leaf(7);
+        let c = builder.open(8);
+        builder.leaf(9);
+        builder.close().unwrap();
+
+        let (arena, roots) = builder.build().unwrap();
+        assert_eq!(roots, vec![a, b, c]);
+        assert!(arena.validate().is_ok());
+        let data: Vec<_> = a.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data).collect();
+        assert_eq!(data, vec![1, 2, 3, 4, 5, 6]);
+        let data: Vec<_> = a.children(&arena).map(|x| x.data).collect();
+        assert_eq!(data, vec![2, 3, 6]);
+        assert!(b.is_leaf(&arena));
+        assert_eq!(c.subtree_size(&arena), 2);
+    }
+
+    #[test]
+    fn unbalanced() {
+        let mut builder = ArenaBuilder::new();
+        builder.open(1usize);
+        builder.close().unwrap();
+        assert!(matches!(builder.close(), Err(Error::Unbalanced)));
+
+        let mut builder = ArenaBuilder::new();
+        builder.open(1usize);
+        builder.open(2);
+        builder.close().unwrap();
+        assert!(matches!(builder.build(), Err(Error::Unbalanced)));
+    }
+}
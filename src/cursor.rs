@@ -0,0 +1,377 @@
+//! A module that contains cursors for navigating trees.
+use alloc::vec::Vec;
+
+use crate::Error;
+use crate::arena::Arena;
+use crate::node::Node;
+use crate::token::Token;
+
+/// A cursor that points at a node in an arena and can be moved around the
+/// tree the node belongs to. The methods that move the cursor return the data
+/// of the node the cursor lands on, or `None` if there is no such node, in
+/// which case the cursor stays where it is.
+///
+/// This `struct` is created by the [`cursor`] methods on `Token` and `Arena`.
+///
+/// # Examples:
+///
+/// ```
+/// use atree::Arena;
+///
+/// let (mut arena, root_token) = Arena::with_data("Indo-European");
+/// let germanic = root_token.append(&mut arena, "Germanic");
+/// germanic.append(&mut arena, "English");
+/// germanic.append(&mut arena, "Swedish");
+///
+/// let mut cursor = root_token.cursor(&arena);
+/// assert_eq!(cursor.first_child(), Some(&"Germanic"));
+/// assert_eq!(cursor.last_child(), Some(&"Swedish"));
+/// assert_eq!(cursor.previous_sibling(), Some(&"English"));
+/// assert_eq!(cursor.previous_sibling(), None);
+/// assert_eq!(cursor.data(), &"English");
+/// assert_eq!(cursor.root(), &"Indo-European");
+/// ```
+///
+/// [`cursor`]: struct.Token.html#method.cursor
+pub struct Cursor<'a, T> {
+    arena: &'a Arena<T>,
+    token: Token
+}
+
+impl<T> Clone for Cursor<'_, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Cursor<'_, T> {}
+
+impl<'a, T> Cursor<'a, T> {
+    /// Initializes a cursor at the given node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    pub (crate) fn new(arena: &'a Arena<T>, token: Token) -> Self {
+        if arena.get(token).is_none() { panic!("Invalid token") }
+        Cursor { arena, token }
+    }
+
+    /// Returns the token of the node the cursor points at.
+    pub fn token(&self) -> Token { self.token }
+
+    /// Returns a reference to the node the cursor points at.
+    pub fn node(&self) -> &'a Node<T> { &self.arena[self.token] }
+
+    /// Returns a reference to the data of the node the cursor points at.
+    pub fn data(&self) -> &'a T { &self.node().data }
+
+    /// Moves the cursor along the given link, if any.
+    fn follow(&mut self, link: Option<Token>) -> Option<&'a T> {
+        let token = link?;
+        self.token = token;
+        Some(self.data())
+    }
+
+    /// Moves the cursor to the parent of the current node.
+    pub fn parent(&mut self) -> Option<&'a T> {
+        self.follow(self.node().parent)
+    }
+
+    /// Moves the cursor to the first child of the current node.
+    pub fn first_child(&mut self) -> Option<&'a T> {
+        self.follow(self.node().first_child)
+    }
+
+    /// Moves the cursor to the last child of the current node.
+    pub fn last_child(&mut self) -> Option<&'a T> {
+        self.follow(self.node().last_child)
+    }
+
+    /// Moves the cursor to the next sibling of the current node.
+    pub fn next_sibling(&mut self) -> Option<&'a T> {
+        self.follow(self.node().next_sibling)
+    }
+
+    /// Moves the cursor to the previous sibling of the current node.
+    pub fn previous_sibling(&mut self) -> Option<&'a T> {
+        self.follow(self.node().previous_sibling)
+    }
+
+    /// Moves the cursor to the root of the tree. Unlike the other moves this
+    /// always succeeds, so the data is returned as is.
+    pub fn root(&mut self) -> &'a T {
+        while self.parent().is_some() {}
+        self.data()
+    }
+
+    /// Moves the cursor to the given node, which may be in any tree in the
+    /// arena. Returns `None` and leaves the cursor where it is if the token
+    /// does not correspond to a node in the arena.
+    pub fn jump(&mut self, token: Token) -> Option<&'a T> {
+        let node = self.arena.get(token)?;
+        self.token = token;
+        Some(&node.data)
+    }
+}
+
+/// A cursor like [`Cursor`] that can also edit the tree around the node it
+/// points at. Only one `CursorMut` can exist for an arena at a time, but the
+/// edits do not invalidate the cursor and the tokens of new nodes are handed
+/// back for later use.
+///
+/// This `struct` is created by the [`cursor_mut`] methods on `Token` and
+/// `Arena`.
+///
+/// # Examples:
+///
+/// ```
+/// use atree::Arena;
+/// use atree::iter::TraversalOrder;
+///
+/// let (mut arena, root_token) = Arena::with_data("Indo-European");
+/// let germanic = root_token.append(&mut arena, "Germanic");
+///
+/// let mut cursor = germanic.cursor_mut(&mut arena);
+/// cursor.append_child("English");
+/// cursor.insert_before("Celtic");
+/// cursor.insert_after("Romance");
+/// cursor.next_sibling();
+/// cursor.append_child("Italian");
+///
+/// let subtree: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+///     .map(|x| x.data)
+///     .collect();
+/// assert_eq!(subtree, vec!["Indo-European", "Celtic", "Germanic", "English",
+///                          "Romance", "Italian"]);
+/// ```
+///
+/// [`Cursor`]: struct.Cursor.html
+/// [`cursor_mut`]: struct.Token.html#method.cursor_mut
+pub struct CursorMut<'a, T> {
+    arena: &'a mut Arena<T>,
+    token: Token
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// Initializes a cursor at the given node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    pub (crate) fn new(arena: &'a mut Arena<T>, token: Token) -> Self {
+        if arena.get(token).is_none() { panic!("Invalid token") }
+        CursorMut { arena, token }
+    }
+
+    /// Returns the token of the node the cursor points at.
+    pub fn token(&self) -> Token { self.token }
+
+    /// Returns a reference to the node the cursor points at.
+    pub fn node(&self) -> &Node<T> { &self.arena[self.token] }
+
+    /// Returns a reference to the data of the node the cursor points at.
+    pub fn data(&self) -> &T { &self.node().data }
+
+    /// Returns a mutable reference to the data of the node the cursor points
+    /// at, or `None` if the node is frozen.
+    pub fn data_mut(&mut self) -> Option<&mut T> {
+        self.arena.get_mut(self.token).map(|node| &mut node.data)
+    }
+
+    /// Returns an immutable cursor at the same node, for example to look
+    /// around without moving this cursor.
+    pub fn as_cursor(&self) -> Cursor<'_, T> {
+        Cursor { arena: self.arena, token: self.token }
+    }
+
+    /// Moves the cursor along the given link, if any.
+    fn follow(&mut self, link: Option<Token>) -> Option<&T> {
+        let token = link?;
+        self.token = token;
+        Some(self.data())
+    }
+
+    /// Moves the cursor to the parent of the current node.
+    pub fn parent(&mut self) -> Option<&T> {
+        self.follow(self.node().parent)
+    }
+
+    /// Moves the cursor to the first child of the current node.
+    pub fn first_child(&mut self) -> Option<&T> {
+        self.follow(self.node().first_child)
+    }
+
+    /// Moves the cursor to the last child of the current node.
+    pub fn last_child(&mut self) -> Option<&T> {
+        self.follow(self.node().last_child)
+    }
+
+    /// Moves the cursor to the next sibling of the current node.
+    pub fn next_sibling(&mut self) -> Option<&T> {
+        self.follow(self.node().next_sibling)
+    }
+
+    /// Moves the cursor to the previous sibling of the current node.
+    pub fn previous_sibling(&mut self) -> Option<&T> {
+        self.follow(self.node().previous_sibling)
+    }
+
+    /// Moves the cursor to the root of the tree.
+    pub fn root(&mut self) -> &T {
+        while let Some(parent) = self.node().parent { self.token = parent }
+        self.data()
+    }
+
+    /// Moves the cursor to the given node, which may be in any tree in the
+    /// arena. Returns `None` and leaves the cursor where it is if the token
+    /// does not correspond to a node in the arena.
+    pub fn jump(&mut self, token: Token) -> Option<&T> {
+        self.arena.get(token)?;
+        self.follow(Some(token))
+    }
+
+    /// Inserts a new node with the given data as the previous sibling of the
+    /// current node and returns its token. The cursor does not move.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the current node is a root node or if its parent is frozen.
+    pub fn insert_before(&mut self, data: T) -> Token {
+        self.token.insert_before(self.arena, data)
+    }
+
+    /// Inserts a new node with the given data as the next sibling of the
+    /// current node and returns its token. The cursor does not move.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the current node is a root node or if its parent is frozen.
+    pub fn insert_after(&mut self, data: T) -> Token {
+        self.token.insert_after(self.arena, data)
+    }
+
+    /// Appends a new node with the given data as the last child of the current
+    /// node and returns its token. The cursor does not move.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the current node is frozen.
+    pub fn append_child(&mut self, data: T) -> Token {
+        self.token.append(self.arena, data)
+    }
+
+    /// Removes the current node along with its descendants and returns their
+    /// data in pre-order. The cursor moves to the next sibling of the removed
+    /// node if there is one, or else to its previous sibling or else to its
+    /// parent. Returns `None` and leaves the tree untouched if the current
+    /// node is a root node, as the cursor would have nowhere to go.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if any node in the subtree or the parent of the current node is
+    /// frozen.
+    pub fn remove_current(&mut self) -> Option<Vec<T>> {
+        let node = self.node();
+        let next = node.next_sibling
+            .or(node.previous_sibling)
+            .or(node.parent)?;
+        let removed = self.arena.drain_subtree(self.token).collect();
+        self.token = next;
+        Some(removed)
+    }
+
+    /// Attaches a free-standing tree in the arena as the last child of the
+    /// current node, the same as [`graft`]. The cursor does not move. Returns
+    /// error if the current node is within the other tree, if the "root node"
+    /// of the other tree is not really a root node or if the current node is
+    /// frozen.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// [`graft`]: struct.Token.html#method.graft
+    pub fn splice_subtree(&mut self, root: Token) -> Result<(), Error> {
+        self.token.graft(self.arena, root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cursor() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchild = children[1].append(&mut arena, 20);
+        let elsewhere = arena.new_node(100);
+
+        let mut cursor = arena.cursor(root_token);
+        assert_eq!(cursor.parent(), None);
+        assert_eq!(cursor.next_sibling(), None);
+        assert_eq!(cursor.token(), root_token);
+        assert_eq!(cursor.first_child(), Some(&1));
+        assert_eq!(cursor.next_sibling(), Some(&2));
+        // a copy moves independently
+        let mut other = cursor;
+        assert_eq!(other.first_child(), Some(&20));
+        assert_eq!(other.first_child(), None);
+        assert_eq!(other.token(), grandchild);
+        assert_eq!(cursor.token(), children[1]);
+        assert_eq!(cursor.next_sibling(), Some(&3));
+        assert_eq!(cursor.next_sibling(), None);
+        assert_eq!(cursor.parent(), Some(&0));
+        assert_eq!(other.root(), &0);
+        assert_eq!(other.token(), root_token);
+
+        assert_eq!(cursor.jump(elsewhere), Some(&100));
+        assert_eq!(cursor.node().token(), elsewhere);
+        arena.uproot(grandchild);
+        let mut cursor = arena.cursor(root_token);
+        assert_eq!(cursor.jump(grandchild), None);
+        assert_eq!(cursor.token(), root_token);
+    }
+
+    #[test]
+    fn cursor_mut() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let child = root_token.append(&mut arena, 2);
+        let spare = arena.new_node(10);
+        spare.append(&mut arena, 11);
+
+        let mut cursor = arena.cursor_mut(child);
+        let first = cursor.insert_before(1);
+        let last = cursor.insert_after(3);
+        let grandchild = cursor.append_child(20);
+        *cursor.data_mut().unwrap() += 100;
+        assert_eq!(cursor.data(), &102);
+        assert_eq!(cursor.as_cursor().next_sibling(), Some(&3));
+        assert_eq!(cursor.token(), child);
+        assert!(cursor.splice_subtree(spare).is_ok());
+        assert!(matches!(cursor.splice_subtree(spare), Err(Error::NotARootNode)));
+        assert_eq!(cursor.root(), &0);
+        assert_eq!(cursor.remove_current(), None);
+        assert_eq!(cursor.jump(grandchild), Some(&20));
+        assert_eq!(cursor.parent(), Some(&102));
+        assert_eq!(root_token.to_nested(&arena).children.len(), 3);
+
+        let mut cursor = arena.cursor_mut(child);
+        assert_eq!(cursor.remove_current(), Some(vec![102, 20, 10, 11]));
+        assert_eq!(cursor.token(), last);
+        assert_eq!(cursor.remove_current(), Some(vec![3]));
+        assert_eq!(cursor.token(), first);
+        assert_eq!(cursor.remove_current(), Some(vec![1]));
+        assert_eq!(cursor.token(), root_token);
+        assert!(cursor.first_child().is_none());
+        assert_eq!(arena.node_count(), 1);
+        assert!(arena.validate().is_ok());
+        assert!(!arena.contains(grandchild));
+
+        let child = root_token.append(&mut arena, 1);
+        arena.freeze_subtree(child);
+        let mut cursor = arena.cursor_mut(child);
+        assert!(cursor.data_mut().is_none());
+        assert!(matches!(cursor.splice_subtree(root_token),
+                         Err(Error::FrozenNode)));
+    }
+}
@@ -1,10 +1,13 @@
 #![allow(clippy::match_bool)]
 //! A module that contains different kinds of iterators.
-use std::collections::VecDeque;
-use std::marker::PhantomData;
-use std::mem;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
 
 use crate::Arena;
+use crate::allocator;
 use crate::node::Node;
 use crate::token::Token;
 
@@ -24,13 +27,15 @@ pub (crate) enum Branch {
 }
 
 /// The order in which tree traversal takes place.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TraversalOrder {
     /// Pre-order (depth-first traversal)
     Pre,
     /// Post-order (depth-first traversal)
     Post,
-    /// Level-order (breadth-first traversal)
+    /// Level-order (breadth-first traversal). All nodes at one depth come
+    /// before any node deeper down, and the nodes at the same depth come in
+    /// the order of their parents and then their insertion order.
     Level
 }
 
@@ -219,6 +224,92 @@ impl<'a, T> Iterator for Subtree<'a, T> {
     }
 }
 
+/// An iterator of pairs of corresponding nodes in two subtrees of the same
+/// shape.
+///
+/// This `struct` is created by the `zip_subtree` methods on [`Token`] and
+/// [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.zip_subtree
+/// [`Node`]: ../struct.Node.html#method.zip_subtree
+pub struct ZipSubtree<'a, T, U> {
+    pub (crate) arena: &'a Arena<T>,
+    pub (crate) other_arena: &'a Arena<U>,
+    pub (crate) order: TraversalOrder,
+    // pairs yet to be visited. The flag marks pairs whose children have
+    // already been queued.
+    pub (crate) pending: VecDeque<(Token, Token, bool)>
+}
+
+impl<'a, T, U> ZipSubtree<'a, T, U> {
+    /// Pairs up the children of the given nodes. Returns `None` if the nodes
+    /// do not have the same number of children.
+    fn children(&self, token: Token, other: Token) -> Option<Vec<(Token, Token)>> {
+        let mut children = token.children_tokens(self.arena);
+        let mut other_children = other.children_tokens(self.other_arena);
+        let mut pairs = Vec::new();
+        loop {
+            match (children.next(), other_children.next()) {
+                (None, None) => break Some(pairs),
+                (Some(a), Some(b)) => pairs.push((a, b)),
+                _ => break None
+            }
+        }
+    }
+}
+
+impl<'a, T, U> Iterator for ZipSubtree<'a, T, U> {
+    type Item = (&'a Node<T>, &'a Node<U>);
+    fn next(&mut self) -> Option<(&'a Node<T>, &'a Node<U>)> {
+        loop {
+            let next = match self.order {
+                TraversalOrder::Level => self.pending.pop_front(),
+                _ => self.pending.pop_back()
+            };
+            let (token, other, expanded) = match next {
+                None => break None,
+                Some(pair) => pair
+            };
+            if !expanded {
+                let pairs = match self.children(token, other) {
+                    Some(pairs) => pairs,
+                    None => {
+                        // the shapes diverge here so there is nothing more
+                        // to pair up
+                        self.pending.clear();
+                        break None
+                    }
+                };
+                match self.order {
+                    TraversalOrder::Level => self.pending.extend(
+                        pairs.into_iter().map(|(a, b)| (a, b, false))),
+                    TraversalOrder::Pre => self.pending.extend(
+                        pairs.into_iter().rev().map(|(a, b)| (a, b, false))),
+                    TraversalOrder::Post => {
+                        self.pending.push_back((token, other, true));
+                        self.pending.extend(
+                            pairs.into_iter().rev().map(|(a, b)| (a, b, false)));
+                        continue
+                    }
+                }
+            }
+            break Some((&self.arena[token], &self.other_arena[other]))
+        }
+    }
+}
+
+/// An iterator of the tokens of the subtree nodes of a given node paired with
+/// references to the nodes.
+///
+/// This `struct` is created by the `subtree_entries` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.subtree_entries
+/// [`Node`]: ../struct.Node.html#method.subtree_entries
+pub struct SubtreeEntries<'a, T> {
+    pub (crate) token_iter: SubtreeTokens<'a, T>
+}
+
 /// An iterator of mutable references of the subtree nodes of a given node.
 ///
 /// This `struct` is created by the [`subtree_mut`] method on `Token`. See
@@ -226,29 +317,207 @@ impl<'a, T> Iterator for Subtree<'a, T> {
 ///
 /// [`subtree_mut`]: ../struct.Token.html#method.subtree_mut
 pub struct SubtreeMut<'a, T: 'a> {
-    pub (crate) arena: *mut Arena<T>,
-    pub (crate) iter: SubtreeTokens<'a, T>,
-    pub (crate) marker: PhantomData<&'a mut T>
+    pub (crate) iter: vec::IntoIter<&'a mut Node<T>>
+}
+
+impl<'a, T> SubtreeMut<'a, T> {
+    /// Borrows the unfrozen nodes of each of the groups of tokens mutably.
+    /// Returns `None` if any of the tokens does not correspond to a node in
+    /// the arena or if any two of the groups share a node.
+    pub (crate) fn split(arena: &'a mut Arena<T>, groups: &[Vec<Token>])
+        -> Option<Vec<Self>> {
+        let groups = arena.allocator.get_disjoint_groups_mut(groups)?;
+        let subtrees = groups.into_iter()
+            .map(|nodes| {
+                let nodes: Vec<_> = nodes.into_iter()
+                    .filter(|node| !node.frozen)
+                    .collect();
+                SubtreeMut { iter: nodes.into_iter() }
+            })
+            .collect();
+        Some(subtrees)
+    }
 }
 
 impl<'a, T> Iterator for SubtreeMut<'a, T> {
     type Item = &'a mut Node<T>;
-    fn next(&mut self) -> Option<&'a mut Node<T>> {
-        match self.iter.next() {
+    fn next(&mut self) -> Option<&'a mut Node<T>> { self.iter.next() }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+}
+
+/// An iterator of tokens of the leaves in the subtree of a given node.
+///
+/// This `struct` is created by the `leaves_tokens` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.leaves_tokens
+/// [`Node`]: ../struct.Node.html#method.leaves_tokens
+pub struct LeavesTokens<'a, T> {
+    pub (crate) arena: &'a Arena<T>,
+    pub (crate) iter: SubtreeTokens<'a, T>
+}
+
+impl<'a, T> Iterator for LeavesTokens<'a, T> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            match self.iter.next() {
+                None => break None,
+                Some(token) => if self.arena[token].is_leaf() {
+                    break Some(token)
+                }
+            }
+        }
+    }
+}
+
+/// An iterator of references of the leaves in the subtree of a given node.
+///
+/// This `struct` is created by the `leaves` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.leaves
+/// [`Node`]: ../struct.Node.html#method.leaves
+pub struct Leaves<'a, T> {
+    pub (crate) token_iter: LeavesTokens<'a, T>
+}
+
+/// An iterator of tokens of the descendants of a given node that are a fixed
+/// number of levels below it.
+///
+/// This `struct` is created by the `descendants_at_depth_tokens` methods on
+/// [`Token`] and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.descendants_at_depth_tokens
+/// [`Node`]: ../struct.Node.html#method.descendants_at_depth_tokens
+pub struct DescendantsAtDepthTokens<'a, T> {
+    pub (crate) arena: &'a Arena<T>,
+    // nodes one level above the target depth whose children are yet to be
+    // yielded
+    pub (crate) parents: VecDeque<Token>,
+    pub (crate) node_token: Option<Token>
+}
+
+impl<'a, T> Iterator for DescendantsAtDepthTokens<'a, T> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if let Some(token) = self.node_token {
+                self.node_token = self.arena[token].next_sibling;
+                break Some(token)
+            }
+            match self.parents.pop_front() {
+                None => break None,
+                Some(parent) => self.node_token = self.arena[parent].first_child
+            }
+        }
+    }
+}
+
+/// An iterator of references of the descendants of a given node that are a
+/// fixed number of levels below it.
+///
+/// This `struct` is created by the `descendants_at_depth` methods on
+/// [`Token`] and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.descendants_at_depth
+/// [`Node`]: ../struct.Node.html#method.descendants_at_depth
+pub struct DescendantsAtDepth<'a, T> {
+    pub (crate) token_iter: DescendantsAtDepthTokens<'a, T>
+}
+
+/// An iterator of tokens of the nodes that come after a given node in
+/// pre-order, excluding its descendants.
+///
+/// This `struct` is created by the `following_tokens` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.following_tokens
+/// [`Node`]: ../struct.Node.html#method.following_tokens
+pub struct FollowingTokens<'a, T> {
+    pub (crate) arena: &'a Arena<T>,
+    pub (crate) root: Token,
+    pub (crate) node_token: Option<Token>
+}
+
+impl<'a, T> Iterator for FollowingTokens<'a, T> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        match self.node_token {
             None => None,
-            Some(node_token) => {
-                let arena = unsafe { self.arena.as_mut().unwrap() };
-                match arena.get_mut(node_token) {
-                    Some(node) => Some(node),
-                    None => None
+            Some(token) => {
+                let (next, _) = preorder_next(token, self.root, Branch::Child,
+                                              self.arena);
+                self.node_token = next;
+                Some(token)
+            }
+        }
+    }
+}
+
+/// An iterator of references of the nodes that come after a given node in
+/// pre-order, excluding its descendants.
+///
+/// This `struct` is created by the `following` methods on [`Token`] and
+/// [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.following
+/// [`Node`]: ../struct.Node.html#method.following
+pub struct Following<'a, T> {
+    pub (crate) token_iter: FollowingTokens<'a, T>
+}
+
+/// An iterator of tokens of the nodes that come before a given node in
+/// pre-order, excluding its ancestors.
+///
+/// This `struct` is created by the `preceding_tokens` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.preceding_tokens
+/// [`Node`]: ../struct.Node.html#method.preceding_tokens
+pub struct PrecedingTokens<'a, T> {
+    pub (crate) arena: &'a Arena<T>,
+    pub (crate) root: Token,
+    pub (crate) node_token: Option<Token>,
+    // the path from the given node up to the root, to be skipped over
+    pub (crate) path: Vec<Token>
+}
+
+impl<'a, T> Iterator for PrecedingTokens<'a, T> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            let token = self.node_token?;
+            let (next, _) = preorder_next(token, self.root, Branch::Child,
+                                          self.arena);
+            self.node_token = next;
+            match self.path.last() == Some(&token) {
+                false => break Some(token),
+                true => {
+                    self.path.pop();
+                    // the given node itself is the last item on the path
+                    if self.path.is_empty() {
+                        self.node_token = None;
+                        break None
+                    }
                 }
             }
         }
     }
 }
 
-unsafe impl<T: Sync> Sync for SubtreeMut<'_, T> {}
-unsafe impl<T: Send> Send for SubtreeMut<'_, T> {}
+/// An iterator of references of the nodes that come before a given node in
+/// pre-order, excluding its ancestors.
+///
+/// This `struct` is created by the `preceding` methods on [`Token`] and
+/// [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.preceding
+/// [`Node`]: ../struct.Node.html#method.preceding
+pub struct Preceding<'a, T> {
+    pub (crate) token_iter: PrecedingTokens<'a, T>
+}
 
 /// An iterator of tokens of siblings that follow a given node.
 ///
@@ -279,9 +548,28 @@ pub struct PrecedingSiblingTokens<'a, T> {
 /// This `struct` is created by the `children_tokens` methods on
 /// [`Token`] and [`Node`]. See their documentation for more.
 ///
+/// Nodes do not keep count of their children, so the size hint of the
+/// iterator (and of the other iterators over children) only tells apart no
+/// children, one child and more than one. An exact length would take a walk
+/// down the sibling list, and collecting more than two children into a `Vec`
+/// may therefore still reallocate.
+///
 /// [`Token`]: ../struct.Token.html#method.children_tokens
 /// [`Node`]: ../struct.Node.html#method.children_tokens
 pub struct ChildrenTokens<'a, T> {
+    pub (crate) arena: &'a Arena<T>,
+    pub (crate) node_token: Option<Token>,
+    pub (crate) back_token: Option<Token>
+}
+
+/// An iterator of tokens of the children of a given node in reverse order.
+///
+/// This `struct` is created by the `children_rev_tokens` methods on
+/// [`Token`] and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.children_rev_tokens
+/// [`Node`]: ../struct.Node.html#method.children_rev_tokens
+pub struct ChildrenRevTokens<'a, T> {
     pub (crate) arena: &'a Arena<T>,
     pub (crate) node_token: Option<Token>
 }
@@ -298,6 +586,45 @@ pub struct AncestorTokens<'a, T> {
     pub (crate) node_token: Option<Token>
 }
 
+/// An iterator of tokens of the ancestors of a given node below a boundary
+/// node.
+///
+/// This `struct` is created by the `ancestors_until_tokens` methods on
+/// [`Token`] and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.ancestors_until_tokens
+/// [`Node`]: ../struct.Node.html#method.ancestors_until_tokens
+pub struct AncestorsUntilTokens<'a, T> {
+    pub (crate) arena: &'a Arena<T>,
+    pub (crate) node_token: Option<Token>,
+    pub (crate) stop: Token
+}
+
+impl<'a, T> Iterator for AncestorsUntilTokens<'a, T> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        match self.node_token {
+            Some(token) if token != self.stop => {
+                self.node_token = self.arena[token].parent;
+                Some(token)
+            },
+            _ => None
+        }
+    }
+}
+
+/// An iterator of references to the ancestors of a given node below a
+/// boundary node.
+///
+/// This `struct` is created by the `ancestors_until` methods on [`Token`] and
+/// [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.ancestors_until
+/// [`Node`]: ../struct.Node.html#method.ancestors_until
+pub struct AncestorsUntil<'a, T> {
+    pub (crate) token_iter: AncestorsUntilTokens<'a, T>
+}
+
 /// An iterator of references to siblings that precede a given node.
 ///
 /// This `struct` is created by the `preceding_siblings` methods on
@@ -331,6 +658,29 @@ pub struct Children<'a, T> {
     pub (crate) token_iter: ChildrenTokens<'a, T>
 }
 
+/// An iterator of references to the children of a given node in reverse order.
+///
+/// This `struct` is created by the `children_rev` methods on
+/// [`Token`] and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.children_rev
+/// [`Node`]: ../struct.Node.html#method.children_rev
+pub struct ChildrenRev<'a, T> {
+    pub (crate) token_iter: ChildrenRevTokens<'a, T>
+}
+
+/// An iterator of the tokens of the children of a given node paired with
+/// references to the nodes.
+///
+/// This `struct` is created by the `children_entries` methods on
+/// [`Token`] and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.children_entries
+/// [`Node`]: ../struct.Node.html#method.children_entries
+pub struct ChildrenEntries<'a, T> {
+    pub (crate) token_iter: ChildrenTokens<'a, T>
+}
+
 /// An iterator of references to the ancestors of a given node.
 ///
 /// This `struct` is created by the `ancestors` methods on
@@ -390,6 +740,115 @@ pub struct AncestorsMut<'a, T: 'a> {
     pub (crate) marker: PhantomData<&'a mut T>
 }
 
+/// An iterator of references to the data of the subtree nodes of a given
+/// node.
+///
+/// This `struct` is created by the `subtree_data` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.subtree_data
+/// [`Node`]: ../struct.Node.html#method.subtree_data
+pub struct SubtreeData<'a, T> {
+    pub (crate) node_iter: Subtree<'a, T>
+}
+
+/// An iterator of references to the data of the children of a given node.
+///
+/// This `struct` is created by the `children_data` methods on [`Token`]
+/// and [`Node`]. See their documentation for more.
+///
+/// [`Token`]: ../struct.Token.html#method.children_data
+/// [`Node`]: ../struct.Node.html#method.children_data
+pub struct ChildrenData<'a, T> {
+    pub (crate) node_iter: Children<'a, T>
+}
+
+/// An iterator of mutable references to the data of the subtree nodes of a
+/// given node.
+///
+/// This `struct` is created by the [`subtree_data_mut`] method on `Token`.
+/// See its documentation for more.
+///
+/// [`subtree_data_mut`]: ../struct.Token.html#method.subtree_data_mut
+pub struct SubtreeDataMut<'a, T: 'a> {
+    pub (crate) node_iter: SubtreeMut<'a, T>
+}
+
+/// An iterator of mutable references to the data of the children of a given
+/// node.
+///
+/// This `struct` is created by the [`children_data_mut`] method on `Token`.
+/// See its documentation for more.
+///
+/// [`children_data_mut`]: ../struct.Token.html#method.children_data_mut
+pub struct ChildrenDataMut<'a, T: 'a> {
+    pub (crate) node_iter: ChildrenMut<'a, T>
+}
+
+/// An iterator of references to all the nodes in an arena.
+///
+/// This `struct` is created by the [`iter`] method on `Arena`. See its
+/// documentation for more.
+///
+/// [`iter`]: ../struct.Arena.html#method.iter
+pub struct Nodes<'a, T> {
+    pub (crate) iter: allocator::Iter<'a, Node<T>>
+}
+
+impl<'a, T> Iterator for Nodes<'a, T> {
+    type Item = &'a Node<T>;
+    fn next(&mut self) -> Option<&'a Node<T>> { self.iter.next() }
+    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+}
+
+impl<'a, T> ExactSizeIterator for Nodes<'a, T> {}
+
+/// An iterator of mutable references to all the nodes in an arena.
+///
+/// This `struct` is created by the [`iter_mut`] method on `Arena`. See its
+/// documentation for more.
+///
+/// [`iter_mut`]: ../struct.Arena.html#method.iter_mut
+pub struct NodesMut<'a, T> {
+    pub (crate) iter: allocator::IterMut<'a, Node<T>>
+}
+
+impl<'a, T> Iterator for NodesMut<'a, T> {
+    type Item = &'a mut Node<T>;
+    fn next(&mut self) -> Option<&'a mut Node<T>> {
+        // frozen nodes are skipped over
+        self.iter.find(|node| !node.frozen)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+/// An iterator that moves the data out of an arena.
+///
+/// This `struct` is created by the `into_iter` method on [`Arena`] (provided
+/// by the `IntoIterator` trait). See its documentation for more.
+///
+/// [`Arena`]: ../struct.Arena.html#impl-IntoIterator
+pub struct IntoIter<T> {
+    pub (crate) iter: allocator::IntoIter<Node<T>>
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        match self.iter.next() {
+            Some(node) => Some(node.data),
+            None => None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) { self.iter.size_hint() }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
 /// A macro that implements the `Iterator` trait on iterators (aside from ones
 /// related to subtree traversal.
 macro_rules! iterator {
@@ -409,6 +868,16 @@ macro_rules! iterator {
                     }
                 }
             }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                // the nodes all come from the arena. The links are followed one
+                // at a time with no count kept anywhere, so a tighter bound
+                // would take walking the rest of the way
+                match self.node_token {
+                    None => (0, Some(0)),
+                    Some(_) => (1, Some(self.arena.node_count()))
+                }
+            }
         }
     };
 
@@ -424,6 +893,53 @@ macro_rules! iterator {
                     None => None
                 }
             }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.token_iter.size_hint()
+            }
+        }
+    };
+
+    (@entry struct $name:ident) => {
+        impl<'a, T> Iterator for $name<'a, T> {
+            type Item = (Token, &'a Node<T>);
+            fn next(&mut self) -> Option<(Token, &'a Node<T>)> {
+                match self.token_iter.next() {
+                    Some(node_token) => self.token_iter.arena.get(node_token)
+                        .map(|node| (node_token, node)),
+                    None => None
+                }
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.token_iter.size_hint()
+            }
+        }
+    };
+
+    (@data struct $name:ident) => {
+        impl<'a, T> Iterator for $name<'a, T> {
+            type Item = &'a T;
+            fn next(&mut self) -> Option<&'a T> {
+                self.node_iter.next().map(|node| &node.data)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.node_iter.size_hint()
+            }
+        }
+    };
+
+    (@data_mut struct $name:ident) => {
+        impl<'a, T> Iterator for $name<'a, T> {
+            type Item = &'a mut T;
+            fn next(&mut self) -> Option<&'a mut T> {
+                self.node_iter.next().map(|node| &mut node.data)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.node_iter.size_hint()
+            }
         }
     };
 
@@ -431,15 +947,18 @@ macro_rules! iterator {
         impl<'a, T> Iterator for $name<'a, T> {
             type Item = &'a mut Node<T>;
             fn next(&mut self) -> Option<&'a mut Node<T>> {
-                match self.node_token {
-                    None => None,
-                    Some(curr_node_token) => {
-                        let arena = unsafe { self.arena.as_mut().unwrap() };
-                        match arena.get_mut(curr_node_token) {
-                            None => None,
-                            Some(curr_node) => {
-                                self.node_token = curr_node.$field;
-                                Some(curr_node)
+                loop {
+                    match self.node_token {
+                        None => break None,
+                        Some(curr_node_token) => {
+                            let arena = unsafe { self.arena.as_mut().unwrap() };
+                            match arena.allocator.get_mut(curr_node_token) {
+                                None => break None,
+                                Some(curr_node) => {
+                                    self.node_token = curr_node.$field;
+                                    // frozen nodes are skipped over
+                                    if !curr_node.frozen { break Some(curr_node) }
+                                }
                             }
                         }
                     }
@@ -454,13 +973,103 @@ macro_rules! iterator {
 
 iterator!(@token struct FollowingSiblingTokens > next_sibling);
 iterator!(@token struct PrecedingSiblingTokens > previous_sibling);
-iterator!(@token struct ChildrenTokens > next_sibling);
+iterator!(@token struct ChildrenRevTokens > previous_sibling);
 iterator!(@token struct AncestorTokens > parent);
 iterator!(@node struct PrecedingSiblings);
 iterator!(@node struct FollowingSiblings);
 iterator!(@node struct Children);
+iterator!(@node struct ChildrenRev);
 iterator!(@node struct Ancestors);
+iterator!(@node struct AncestorsUntil);
+iterator!(@node struct Leaves);
+iterator!(@node struct DescendantsAtDepth);
+iterator!(@node struct Following);
+iterator!(@node struct Preceding);
+iterator!(@entry struct SubtreeEntries);
+iterator!(@entry struct ChildrenEntries);
+iterator!(@data struct SubtreeData);
+iterator!(@data struct ChildrenData);
+iterator!(@data_mut struct SubtreeDataMut);
+iterator!(@data_mut struct ChildrenDataMut);
 iterator!(@mut struct PrecedingSiblingsMut > previous_sibling);
 iterator!(@mut struct FollowingSiblingsMut > next_sibling);
 iterator!(@mut struct ChildrenMut > next_sibling);
 iterator!(@mut struct AncestorsMut > parent);
+
+impl<'a, T> ChildrenTokens<'a, T> {
+    /// Advances one of the two cursors along the given link. Both cursors are
+    /// cleared once they have met so that no node is yielded twice.
+    fn step<F>(&mut self, token: Token, link: F) -> Token
+        where F: FnOnce(&Node<T>) -> Option<Token> {
+        let node = match self.arena.get(token) {
+            None => panic!("Stale token: {:?} is not found in the arena. Check \
+                            code", token),
+            Some(node) => node
+        };
+        match self.node_token == self.back_token {
+            true => {
+                self.node_token = None;
+                self.back_token = None;
+            },
+            false => {
+                let next = link(node);
+                match self.node_token == Some(token) {
+                    true => self.node_token = next,
+                    false => self.back_token = next
+                }
+            }
+        }
+        token
+    }
+}
+
+impl<'a, T> Iterator for ChildrenTokens<'a, T> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        let token = self.node_token?;
+        Some(self.step(token, |node| node.next_sibling))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // without a child count only the two ends are known for certain
+        match (self.node_token, self.back_token) {
+            (None, _) | (_, None) => (0, Some(0)),
+            (Some(front), Some(back)) => match front == back {
+                true => (1, Some(1)),
+                false => (2, Some(self.arena.node_count()))
+            }
+        }
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ChildrenTokens<'a, T> {
+    fn next_back(&mut self) -> Option<Token> {
+        let token = self.back_token?;
+        Some(self.step(token, |node| node.previous_sibling))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Children<'a, T> {
+    fn next_back(&mut self) -> Option<&'a Node<T>> {
+        match self.token_iter.next_back() {
+            Some(node_token) => self.token_iter.arena.get(node_token),
+            None => None
+        }
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ChildrenData<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.node_iter.next_back().map(|node| &node.data)
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for ChildrenEntries<'a, T> {
+    fn next_back(&mut self) -> Option<(Token, &'a Node<T>)> {
+        match self.token_iter.next_back() {
+            Some(node_token) => self.token_iter.arena.get(node_token)
+                .map(|node| (node_token, node)),
+            None => None
+        }
+    }
+}
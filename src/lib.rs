@@ -1,4 +1,5 @@
 #![doc(html_root_url = "https://docs.rs/atree/0.5.2")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 //! An arena based tree structure, backed by a custom allocator (ultimately
 //! built on `Vec`) that makes node removal a possibility. On top of the basic
 //! node insertion and removal operations, there are also many kinds of
@@ -7,7 +8,10 @@
 //!
 //! Most of the code in the crate is `unsafe` free, except for the mutable
 //! iterators, where the `unsafe` code is lifted from the core Rust
-//! implementation of `IterMut`.
+//! implementation of `IterMut`, and the methods that hand out mutable
+//! references to several nodes at once such as `Arena::get_disjoint_mut`
+//! and `Arena::split_subtrees_mut`, where the `unsafe` code turns pointers
+//! to distinct cells of the backing `Vec` into references.
 //!
 //! # General Guide to the API
 //!
@@ -33,7 +37,13 @@
 //! follows semver rules when the API does change.
 //!
 //! # Crate Feature Flags
-//!   - `serde`: support for serde 1.x. Optional feature/dependency.
+//!   - `std`: enabled by default. Without it the crate is `no_std` and only
+//!     depends on `core` and `alloc`.
+//!   - `serde`: support for serde 1.x. Optional feature/dependency. `Arena`,
+//!     `Node` and `Token` are serialized as they are laid out in memory, free
+//!     cells and all, so tokens stay valid across a round trip.
+//!   - `rayon`: parallel iterators with rayon 1.x. Optional feature/dependency.
+//!   - `bst`: a helper for keeping binary search trees in an arena.
 //!
 //! # Usage Examples
 //!
@@ -216,19 +226,123 @@
 #[macro_use]
 extern crate serde;
 
-mod alloc;
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+mod allocator;
 mod arena;
+#[cfg(feature = "bst")]
+mod bst;
+mod builder;
+mod cursor;
 pub mod iter;
 mod node;
 mod token;
 
-pub use token::Token;
-pub use arena::Arena;
+pub use token::{Move, Token};
+pub use arena::{Arena, DepthIndex, Record, RecursiveTree};
+pub use allocator::GrowthStrategy;
+pub use builder::ArenaBuilder;
+pub use cursor::{Cursor, CursorMut};
 pub use node::Node;
 
+#[doc(hidden)]
+pub use alloc::vec as __vec;  // for the tree! macro
+
 #[derive(Clone, Copy, Debug)]
 /// The Error type
+///
+/// With the `std` feature enabled, `Error` implements `std::error::Error` and
+/// works with `?` in functions that return boxed errors.
+///
+/// # Examples:
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use atree::Arena;
+///
+/// fn graft() -> Result<(), Box<dyn std::error::Error>> {
+///     let (mut arena, root_token) = Arena::with_data("Indo-European");
+///     let germanic = root_token.append(&mut arena, "Germanic");
+///     root_token.move_to(&mut arena, germanic)?;
+///     Ok(())
+/// }
+///
+/// let err = graft().unwrap_err();
+/// assert_eq!(err.to_string(), "operation would result in a cyclic graph");
+/// # }
+/// ```
 pub enum Error {
     /// Not a root node error
-    NotARootNode
+    NotARootNode,
+    /// Invalid token error
+    InvalidToken,
+    /// Cyclic graph error
+    CyclicGraph,
+    /// Invalid depth error
+    InvalidDepth,
+    /// Invalid record error
+    InvalidRecord,
+    /// Frozen node error
+    FrozenNode,
+    /// Unbalanced open and close error
+    Unbalanced,
+    /// Index out of bounds error
+    IndexOutOfBounds
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let msg = match self {
+            Error::NotARootNode => "node is not a root node",
+            Error::InvalidToken => "token does not correspond to a node in the \
+                                    arena",
+            Error::CyclicGraph => "operation would result in a cyclic graph",
+            Error::InvalidDepth => "depth is not valid for the given position",
+            Error::InvalidRecord => "record refers to a missing or duplicate id",
+            Error::FrozenNode => "node is frozen",
+            Error::Unbalanced => "open and close calls are unbalanced",
+            Error::IndexOutOfBounds => "index is out of bounds"
+        };
+        f.write_str(msg)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Error::NotARootNode.to_string(), "node is not a root node");
+        assert_eq!(Error::InvalidToken.to_string(),
+                   "token does not correspond to a node in the arena");
+        assert_eq!(Error::CyclicGraph.to_string(),
+                   "operation would result in a cyclic graph");
+        assert_eq!(Error::InvalidDepth.to_string(),
+                   "depth is not valid for the given position");
+        assert_eq!(Error::InvalidRecord.to_string(),
+                   "record refers to a missing or duplicate id");
+        assert_eq!(Error::FrozenNode.to_string(), "node is frozen");
+        assert_eq!(Error::Unbalanced.to_string(),
+                   "open and close calls are unbalanced");
+        assert_eq!(Error::IndexOutOfBounds.to_string(), "index is out of bounds");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn boxed_error() {
+        fn replace() -> Result<(), Box<dyn std::error::Error>> {
+            let (mut arena, root_token) = Arena::with_data(1usize);
+            let child = root_token.append(&mut arena, 2usize);
+            let other = child.append(&mut arena, 3usize);
+            child.replace_node(&mut arena, other)?;
+            Ok(())
+        }
+        assert_eq!(replace().unwrap_err().to_string(), "node is not a root node");
+    }
 }
@@ -0,0 +1,87 @@
+/// Builds a new arena holding the tree described by a nested literal and
+/// returns it along with the token of the root node, in the same way as
+/// [`Arena::from_nested`]. Each node is written as its data, optionally
+/// followed by `=>` and a bracketed list of its children.
+///
+/// # Examples:
+///
+/// ```
+/// use atree::tree;
+/// use atree::iter::TraversalOrder;
+///
+/// let (arena, root_token) = tree!("Indo-European" => [
+///     "Germanic" => ["English", "Scots"],
+///     "Romance"
+/// ]);
+///
+/// let mut iter = root_token.subtree(&arena, TraversalOrder::Pre)
+///     .map(|x| x.data);
+/// assert_eq!(iter.next(), Some("Indo-European"));
+/// assert_eq!(iter.next(), Some("Germanic"));
+/// assert_eq!(iter.next(), Some("English"));
+/// assert_eq!(iter.next(), Some("Scots"));
+/// assert_eq!(iter.next(), Some("Romance"));
+/// assert!(iter.next().is_none());
+/// ```
+///
+/// [`Arena::from_nested`]: struct.Arena.html#method.from_nested
+#[macro_export]
+macro_rules! tree {
+    (@node $data:expr => [$($children:tt)*]) => {
+        $crate::RecursiveTree {
+            data: $data,
+            children: $crate::tree!(@children [] $($children)*)
+        }
+    };
+    (@node $data:expr) => {
+        $crate::RecursiveTree { data: $data, children: $crate::__vec![] }
+    };
+
+    // munches through the list of children one at a time
+    (@children [$($done:expr,)*]) => { $crate::__vec![$($done),*] };
+    (@children [$($done:expr,)*] $data:expr => [$($c:tt)*] $(, $($rest:tt)*)?)
+        => {
+        $crate::tree!(@children
+                      [$($done,)* $crate::tree!(@node $data => [$($c)*]),]
+                      $($($rest)*)?)
+    };
+    (@children [$($done:expr,)*] $data:expr $(, $($rest:tt)*)?) => {
+        $crate::tree!(@children [$($done,)* $crate::tree!(@node $data),]
+                      $($($rest)*)?)
+    };
+
+    ($($tree:tt)+) => {
+        $crate::Arena::from_nested($crate::tree!(@node $($tree)+))
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::iter::TraversalOrder;
+
+    #[test]
+    fn tree() {
+        let (arena, root_token) = tree!(1 => [2 => [3, 4 => [5]], 6, 7 => [],]);
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7]);
+        let depths: Vec<_> = root_token.subtree_tokens(&arena, TraversalOrder::Pre)
+            .map(|t| t.ancestors_tokens(&arena).count())
+            .collect();
+        assert_eq!(depths, vec![0, 1, 2, 2, 3, 1, 1]);
+        assert!(arena.validate().is_ok());
+
+        let (arena, root_token) = tree!(String::from("root"));
+        assert_eq!(arena.node_count(), 1);
+        assert_eq!(arena[root_token].data, "root");
+
+        // arbitrary expressions work as data
+        let x = 10;
+        let (arena, root_token) = tree!(x + 1 => [x * 2, { x - 1 }]);
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![11, 20, 9]);
+    }
+}
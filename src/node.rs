@@ -12,6 +12,7 @@ use crate::iter::*;
 /// [`get`]: struct.Arena.html#method.get
 /// [`get_mut`]: struct.Arena.html#method.get_mut
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Node<T> {
     /// The `data` field.
     pub data: T,
@@ -25,15 +26,49 @@ pub struct Node<T> {
     pub (crate) next_sibling: Option<Token>,
     /// The "first child" node.
     pub (crate) first_child: Option<Token>,
+    /// The "last child" node.
+    pub (crate) last_child: Option<Token>,
+    /// Whether the node is frozen.
+    pub (crate) frozen: bool,
 }
 
 impl<T> Node<T> {
+    /// Creates a node with the given data in the place of the current one.
+    pub (crate) fn with_data<U>(&self, data: U) -> Node<U> {
+        Node {
+            data,
+            token: self.token,
+            parent: self.parent,
+            previous_sibling: self.previous_sibling,
+            next_sibling: self.next_sibling,
+            first_child: self.first_child,
+            last_child: self.last_child,
+            frozen: self.frozen
+        }
+    }
+
     /// Returns the token of the given node.
     pub fn token(&self) -> Token { self.token }
 
     /// Checks whether a given node is actually a leaf.
     pub fn is_leaf(&self) -> bool { self.first_child.is_none() }
 
+    /// Checks whether a given node is a root node, i.e. it has no parent.
+    pub fn is_root(&self) -> bool { self.parent.is_none() }
+
+    /// Checks whether a given node is the only child of its parent. Root nodes
+    /// are not considered only children.
+    pub fn is_only_child(&self) -> bool {
+        self.parent.is_some()
+            && self.previous_sibling.is_none()
+            && self.next_sibling.is_none()
+    }
+
+    /// Checks whether a given node is frozen. See [`freeze_subtree`].
+    ///
+    /// [`freeze_subtree`]: struct.Arena.html#method.freeze_subtree
+    pub fn is_frozen(&self) -> bool { self.frozen }
+
     /// Returns an iterator of tokens of ancestor nodes.
     ///
     /// # Examples:
@@ -60,6 +95,57 @@ impl<T> Node<T> {
         self.token.ancestors_tokens(arena)
     }
 
+    /// Returns an iterator of tokens of ancestor nodes, starting from the
+    /// parent and stopping right before the `stop` node. See
+    /// [`Token::ancestors_until_tokens`] for more.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    ///
+    /// let node = &arena[english];
+    /// let mut ancestors = node.ancestors_until_tokens(&arena, root_token);
+    /// assert_eq!(ancestors.next(), Some(germanic));
+    /// assert!(ancestors.next().is_none());
+    /// ```
+    ///
+    /// [`Token::ancestors_until_tokens`]: struct.Token.html#method.ancestors_until_tokens
+    pub fn ancestors_until_tokens<'a>(&self, arena: &'a Arena<T>, stop: Token)
+        -> AncestorsUntilTokens<'a, T> {
+        self.token.ancestors_until_tokens(arena, stop)
+    }
+
+    /// Returns an iterator of references of ancestor nodes, starting from the
+    /// parent and stopping right before the `stop` node. See
+    /// [`Token::ancestors_until`] for more.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    ///
+    /// let node = &arena[english];
+    /// let mut ancestors = node.ancestors_until(&arena, root_token)
+    ///     .map(|x| x.data);
+    /// assert_eq!(ancestors.next(), Some("Germanic"));
+    /// assert!(ancestors.next().is_none());
+    /// ```
+    ///
+    /// [`Token::ancestors_until`]: struct.Token.html#method.ancestors_until
+    pub fn ancestors_until<'a>(&self, arena: &'a Arena<T>, stop: Token)
+        -> AncestorsUntil<'a, T> {
+        self.token.ancestors_until(arena, stop)
+    }
+
     /// Returns the first child of the node.
     ///
     /// # Examples
@@ -76,6 +162,22 @@ impl<T> Node<T> {
     /// ```
     pub fn first_child(&self) -> Option<Token> { self.first_child }
 
+    /// Returns the last child of the node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Germanic");
+    /// root_token.append(&mut arena, "English");
+    /// let swedish = root_token.append(&mut arena, "Swedish");
+    ///
+    /// let root = &arena[root_token];
+    /// assert_eq!(root.last_child(), Some(swedish));
+    /// ```
+    pub fn last_child(&self) -> Option<Token> { self.last_child }
+
     /// Returns the parent of the node.
     ///
     /// # Examples
@@ -206,6 +308,31 @@ impl<T> Node<T> {
         self.token.children_tokens(arena)
     }
 
+    /// Returns an iterator of tokens of child nodes in the reverse order of
+    /// insertion.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let first_child_token = root_token.append(&mut arena, "Romance");
+    /// let second_child_token = root_token.append(&mut arena, "Germanic");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut children_tokens = root.children_rev_tokens(&arena);
+    /// assert_eq!(children_tokens.next(), Some(second_child_token));
+    /// assert_eq!(children_tokens.next(), Some(first_child_token));
+    /// assert!(children_tokens.next().is_none());
+    /// ```
+    pub fn children_rev_tokens<'a>(&self, arena: &'a Arena<T>)
+        -> ChildrenRevTokens<'a, T> {
+        self.token.children_rev_tokens(arena)
+    }
+
     /// Returns an iterator of references of ancestor nodes.
     ///
     /// # Examples:
@@ -312,6 +439,73 @@ impl<T> Node<T> {
         self.token.children(arena)
     }
 
+    /// Returns an iterator of the tokens of child nodes paired with references
+    /// to the nodes, in the order of insertion.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut entries = root.children_entries(&arena)
+    ///     .map(|(token, node)| (token, node.data));
+    /// assert_eq!(entries.next(), Some((germanic, "Germanic")));
+    /// assert!(entries.next().is_none());
+    /// ```
+    pub fn children_entries<'a>(&self, arena: &'a Arena<T>)
+        -> ChildrenEntries<'a, T> {
+        self.token.children_entries(arena)
+    }
+
+    /// Returns an iterator of references to the data of the child nodes in the
+    /// order of insertion.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let root = &arena[root_token];
+    /// let data: Vec<_> = root.children_data(&arena).collect();
+    /// assert_eq!(data, [&"Germanic", &"Romance"]);
+    /// ```
+    pub fn children_data<'a>(&self, arena: &'a Arena<T>)
+        -> ChildrenData<'a, T> {
+        self.token.children_data(arena)
+    }
+
+    /// Returns an iterator of child node references in the reverse order of
+    /// insertion.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, "Romance");
+    /// root_token.append(&mut arena, "Germanic");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut children = root.children_rev(&arena);
+    /// assert_eq!(children.next().unwrap().data, "Germanic");
+    /// assert_eq!(children.next().unwrap().data, "Romance");
+    /// assert!(children.next().is_none());
+    /// ```
+    pub fn children_rev<'a>(&self, arena: &'a Arena<T>) -> ChildrenRev<'a, T> {
+        self.token.children_rev(arena)
+    }
+
     /// Returns an iterator of tokens of subtree nodes of the given node.
     ///
     /// # Examples:
@@ -353,6 +547,156 @@ impl<T> Node<T> {
         self.token.subtree_tokens(arena, order)
     }
 
+    /// Returns an iterator of tokens of the leaves in the subtree of the given
+    /// node in pre-order.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let albanian = root_token.append(&mut arena, "Albanian");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut leaves = root.leaves_tokens(&arena);
+    /// assert_eq!(leaves.next(), Some(english));
+    /// assert_eq!(leaves.next(), Some(albanian));
+    /// assert!(leaves.next().is_none());
+    /// ```
+    pub fn leaves_tokens<'a>(&self, arena: &'a Arena<T>) -> LeavesTokens<'a, T> {
+        self.token.leaves_tokens(arena)
+    }
+
+    /// Returns an iterator of references of the leaves in the subtree of the
+    /// given node in pre-order.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Albanian");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut leaves = root.leaves(&arena);
+    /// assert_eq!(leaves.next().unwrap().data, "English");
+    /// assert_eq!(leaves.next().unwrap().data, "Albanian");
+    /// assert!(leaves.next().is_none());
+    /// ```
+    pub fn leaves<'a>(&self, arena: &'a Arena<T>) -> Leaves<'a, T> {
+        self.token.leaves(arena)
+    }
+
+    /// Returns an iterator of tokens of the nodes that come after the given
+    /// node in a pre-order traversal of the tree it belongs to, excluding its
+    /// descendants.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    ///
+    /// let mut following = arena[germanic].following_tokens(&arena);
+    /// assert_eq!(following.next(), Some(romance));
+    /// assert!(following.next().is_none());
+    /// ```
+    pub fn following_tokens<'a>(&self, arena: &'a Arena<T>)
+        -> FollowingTokens<'a, T> {
+        self.token.following_tokens(arena)
+    }
+
+    /// Returns an iterator of references of the nodes that come after the
+    /// given node in a pre-order traversal of the tree it belongs to, excluding
+    /// its descendants.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let mut following = arena[germanic].following(&arena);
+    /// assert_eq!(following.next().unwrap().data, "Romance");
+    /// assert!(following.next().is_none());
+    /// ```
+    pub fn following<'a>(&self, arena: &'a Arena<T>) -> Following<'a, T> {
+        self.token.following(arena)
+    }
+
+    /// Returns an iterator of tokens of the nodes that come before the given
+    /// node in a pre-order traversal of the tree it belongs to, excluding its
+    /// ancestors.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    ///
+    /// let mut preceding = arena[romance].preceding_tokens(&arena);
+    /// assert_eq!(preceding.next(), Some(germanic));
+    /// assert_eq!(preceding.next(), Some(english));
+    /// assert!(preceding.next().is_none());
+    /// ```
+    pub fn preceding_tokens<'a>(&self, arena: &'a Arena<T>)
+        -> PrecedingTokens<'a, T> {
+        self.token.preceding_tokens(arena)
+    }
+
+    /// Returns an iterator of references of the nodes that come before the
+    /// given node in a pre-order traversal of the tree it belongs to, excluding
+    /// its ancestors.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    ///
+    /// let mut preceding = arena[romance].preceding(&arena);
+    /// assert_eq!(preceding.next().unwrap().data, "Germanic");
+    /// assert_eq!(preceding.next().unwrap().data, "English");
+    /// assert!(preceding.next().is_none());
+    /// ```
+    pub fn preceding<'a>(&self, arena: &'a Arena<T>) -> Preceding<'a, T> {
+        self.token.preceding(arena)
+    }
+
     /// Returns an iterator of references of subtree nodes of the given node.
     ///
     /// # Examples:
@@ -387,8 +731,128 @@ impl<T> Node<T> {
         self.token.subtree(arena, order)
     }
 
-    pub (crate) fn remove_descendants(&mut self, arena: &mut Arena<T>) {
-        self.token.remove_descendants(arena)
+    /// Returns an iterator of tokens of the descendants that are exactly
+    /// `depth` levels below the given node, from left to right.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut tokens = root.descendants_at_depth_tokens(&arena, 2);
+    /// assert_eq!(tokens.next(), Some(english));
+    /// assert!(tokens.next().is_none());
+    /// ```
+    pub fn descendants_at_depth_tokens<'a>(&self, arena: &'a Arena<T>,
+                                           depth: usize)
+        -> DescendantsAtDepthTokens<'a, T> {
+        self.token.descendants_at_depth_tokens(arena, depth)
+    }
+
+    /// Returns an iterator of references of the descendants that are exactly
+    /// `depth` levels below the given node, from left to right.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut nodes = root.descendants_at_depth(&arena, 2).map(|x| x.data);
+    /// assert_eq!(nodes.next(), Some("English"));
+    /// assert!(nodes.next().is_none());
+    /// ```
+    pub fn descendants_at_depth<'a>(&self, arena: &'a Arena<T>, depth: usize)
+        -> DescendantsAtDepth<'a, T> {
+        self.token.descendants_at_depth(arena, depth)
+    }
+
+    /// Returns an iterator of references to the data of the subtree nodes of
+    /// the given node.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// root_token.append(&mut arena, "Germanic");
+    ///
+    /// let root = &arena[root_token];
+    /// let data: Vec<_> = root.subtree_data(&arena, TraversalOrder::Pre)
+    ///     .collect();
+    /// assert_eq!(data, [&"Indo-European", &"Germanic"]);
+    /// ```
+    pub fn subtree_data<'a>(&self, arena: &'a Arena<T>, order: TraversalOrder)
+        -> SubtreeData<'a, T> {
+        self.token.subtree_data(arena, order)
+    }
+
+    /// Returns an iterator of the tokens of subtree nodes of the given node
+    /// paired with references to the nodes.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    ///
+    /// let root = &arena[root_token];
+    /// let mut entries = root.subtree_entries(&arena, TraversalOrder::Pre)
+    ///     .map(|(token, node)| (token, node.data));
+    /// assert_eq!(entries.next(), Some((root_token, "Indo-European")));
+    /// assert_eq!(entries.next(), Some((germanic, "Germanic")));
+    /// assert!(entries.next().is_none());
+    /// ```
+    pub fn subtree_entries<'a>(&self, arena: &'a Arena<T>,
+                               order: TraversalOrder)
+        -> SubtreeEntries<'a, T> {
+        self.token.subtree_entries(arena, order)
+    }
+
+    /// Returns an iterator that walks the subtrees of the given nodes in
+    /// lockstep and yields pairs of corresponding nodes. Stops at the first
+    /// pair of nodes with different numbers of children. See
+    /// [`Token::zip_subtree`] for more.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut values, root_token) = Arena::with_data(1usize);
+    /// root_token.append(&mut values, 2usize);
+    /// let (mut names, other_root) = Arena::with_data("one");
+    /// other_root.append(&mut names, "two");
+    ///
+    /// let root = &values[root_token];
+    /// let mut pairs = root.zip_subtree(&values, other_root, &names,
+    ///                                  TraversalOrder::Pre)
+    ///     .map(|(a, b)| (a.data, b.data));
+    /// assert_eq!(pairs.next(), Some((1, "one")));
+    /// assert_eq!(pairs.next(), Some((2, "two")));
+    /// assert!(pairs.next().is_none());
+    /// ```
+    ///
+    /// [`Token::zip_subtree`]: struct.Token.html#method.zip_subtree
+    pub fn zip_subtree<'a, U>(&self, arena: &'a Arena<T>, other: Token,
+                              other_arena: &'a Arena<U>, order: TraversalOrder)
+        -> ZipSubtree<'a, T, U> {
+        self.token.zip_subtree(arena, other, other_arena, order)
     }
 }
 
@@ -1,49 +1,198 @@
 #![allow(clippy::match_bool)]
-use std::collections::VecDeque;
-use std::marker::PhantomData;
-use std::num::NonZeroUsize;
-use std::mem::MaybeUninit;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::convert::TryFrom;
+use core::num::{NonZeroU32, NonZeroUsize};
 
 use crate::Error;
 use crate::iter::*;
+use crate::arena::{Arena, RecursiveTree};
+use crate::cursor::{Cursor, CursorMut};
+#[cfg(feature = "rayon")]
 use crate::node::Node;
-use crate::arena::Arena;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
 /// A `Token` is a handle to a node in the arena.
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+///
+/// Tokens are generational: once a node is removed, its token stays invalid
+/// even after the freed cell is reused for another node, so a stale token is
+/// never mistaken for a handle to the new node. (The generation of a cell
+/// only wraps around after it has been reused 2^32 times.)
+///
+/// The index of a token is a 32-bit integer that is never zero, so both a
+/// `Token` and an `Option<Token>` take up eight bytes, the width of a pointer
+/// on 64-bit targets. An arena holds at most `u32::MAX` nodes as a result.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Token {
-    pub (crate) index: NonZeroUsize
+    pub (crate) index: NonZeroU32,
+    pub (crate) generation: u32
+}
+
+/// A single step of a route through a tree. See [`Token::route_to`].
+///
+/// [`Token::route_to`]: struct.Token.html#method.route_to
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Move {
+    /// Move up to the parent of the current node.
+    Up,
+    /// Move down to the given child of the current node.
+    Down(Token)
 }
 
 fn node_operation<T>(
     self_token: Token,
     arena: &mut Arena<T>,
     other_token: Token,
-    func: fn(Token, &mut Arena<T>, T) -> Token
+    func: fn(Token, &mut Arena<T>, Token)
 ) -> Result<(), Error> {
-    // only a placeholder to get around some trait requirements so I can
-    // reuse code. The uninitialized data will be removed so no risk here.
-    let dummy_data: T = unsafe { MaybeUninit::zeroed().assume_init() };
-    let token = func(self_token, arena, dummy_data);
-    token.replace_node(arena, other_token)?;
-    arena.remove(token);  // remove uninitialized data
+    if arena.get(self_token).is_none() { panic!("Invalid token") }
+    match arena.get(other_token) {
+        None => panic!("Invalid token"),
+        // check that the other node is really a root node of its own
+        Some(node) => match (node.parent, node.previous_sibling,
+                             node.next_sibling) {
+            (None, None, None) => (),
+            _ => return Err(Error::NotARootNode)
+        }
+    }
+    func(self_token, arena, other_token);
     Ok(())
 }
 
+/// Checks whether the given node is frozen. Returns false if there is no node.
+fn is_frozen<T>(arena: &Arena<T>, token: Option<Token>) -> bool {
+    match token.and_then(|t| arena.get(t)) {
+        None => false,
+        Some(node) => node.frozen
+    }
+}
+
+/// Links a free-standing node as the last child of the given node.
+fn link_as_last_child<T>(self_token: Token, arena: &mut Arena<T>, other: Token) {
+    let self_node = arena.node_mut(self_token);
+    let previous_sibling = self_node.last_child;
+    self_node.last_child = Some(other);
+    match previous_sibling {
+        None => self_node.first_child = Some(other),
+        Some(last_child) => arena.node_mut(last_child).next_sibling = Some(other)
+    }
+    let node = arena.node_mut(other);
+    node.parent = Some(self_token);
+    node.previous_sibling = previous_sibling;
+}
+
+/// Links a free-standing node as the first child of the given node.
+fn link_as_first_child<T>(self_token: Token, arena: &mut Arena<T>, other: Token) {
+    let self_node = arena.node_mut(self_token);
+    let next_sibling = self_node.first_child;
+    self_node.first_child = Some(other);
+    match next_sibling {
+        None => self_node.last_child = Some(other),
+        Some(first_child) =>
+            arena.node_mut(first_child).previous_sibling = Some(other)
+    }
+    let node = arena.node_mut(other);
+    node.parent = Some(self_token);
+    node.next_sibling = next_sibling;
+}
+
+/// Links a free-standing node as the previous sibling of the given node.
+fn link_as_previous_sibling<T>(self_token: Token, arena: &mut Arena<T>,
+                               other: Token) {
+    let (self_parent, self_previous_sibling) = match arena.get(self_token) {
+        None => panic!("Invalid token"),
+        Some(node) => (node.parent, node.previous_sibling)
+    };
+    match self_previous_sibling {
+        Some(sibling) => match arena.allocator.get_mut(sibling) {
+            None => panic!("Corrupt arena"),
+            Some(node) => node.next_sibling = Some(other)
+        },
+        None => match self_parent {
+            None => panic!("Cannot insert as the previous sibling of the \
+                            root node"),
+            Some(p) => match arena.allocator.get_mut(p) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.first_child = Some(other)
+            }
+        }
+    }
+    arena.node_mut(self_token).previous_sibling = Some(other);  // already checked
+    let node = arena.node_mut(other);
+    node.parent = self_parent;
+    node.previous_sibling = self_previous_sibling;
+    node.next_sibling = Some(self_token);
+}
+
+/// Links a free-standing node as the next sibling of the given node.
+fn link_as_next_sibling<T>(self_token: Token, arena: &mut Arena<T>,
+                           other: Token) {
+    let (self_parent, self_next_sibling) = match arena.get(self_token) {
+        None => panic!("Invalid token"),
+        Some(node) => (node.parent, node.next_sibling)
+    };
+    match self_next_sibling {
+        Some(sibling) => match arena.allocator.get_mut(sibling) {
+            None => panic!("Corrupt arena"),
+            Some(node) => node.previous_sibling = Some(other)
+        },
+        None => if let Some(p) = self_parent {
+            match arena.allocator.get_mut(p) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.last_child = Some(other)
+            }
+        }
+    }
+    arena.node_mut(self_token).next_sibling = Some(other);  // already checked
+    let node = arena.node_mut(other);
+    node.parent = self_parent;
+    node.previous_sibling = Some(self_token);
+    node.next_sibling = self_next_sibling;
+}
+
+/// Relinks the children of the given node so that they appear in the given
+/// order. The slice has to hold exactly the current children of the node.
+fn relink_children<T>(self_token: Token, arena: &mut Arena<T>,
+                      children: &[Token]) {
+    let node = arena.node_mut(self_token);
+    node.first_child = children.first().copied();
+    node.last_child = children.last().copied();
+    for (i, &child) in children.iter().enumerate() {
+        let previous_sibling = match i {
+            0 => None,
+            _ => Some(children[i - 1])
+        };
+        let node = arena.node_mut(child);
+        node.previous_sibling = previous_sibling;
+        node.next_sibling = children.get(i + 1).copied();
+    }
+}
+
 impl Token {
-    /// Checks whether a given node is actually a leaf.
+    /// Makes a token for the cell at the given (one-based) index.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
-    pub fn is_leaf<T>(self, arena: &Arena<T>) -> bool {
-        match arena.get(self) {
-            None => panic!("Invalid token"),
-            Some(node) => node.is_leaf()
+    /// Panics if the index does not fit in 32 bits.
+    pub (crate) fn new(index: NonZeroUsize, generation: u32) -> Token {
+        match u32::try_from(index.get()) {
+            Ok(index) => Token { index: NonZeroU32::new(index).unwrap(), generation },
+            Err(_) => panic!("Arena capacity overflow")
         }
     }
 
-    /// Creates a new node with the given data and append to the given node.
+    /// The zero-based index of the cell the token points to.
+    pub (crate) fn slot(self) -> usize { self.index.get() as usize - 1 }
+
+    /// Returns a cursor pointing at the current node. See [`Cursor`] for
+    /// details.
     ///
     /// # Panics:
     ///
@@ -53,48 +202,24 @@ impl Token {
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
-    ///
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// let next_node_token = root_token.append(&mut arena, "Germanic");
-    /// next_node_token.append(&mut arena, "Romance");
-    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre);
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
     ///
-    /// assert_eq!(subtree.next().unwrap().data, "Indo-European");
-    /// assert_eq!(subtree.next().unwrap().data, "Germanic");
-    /// assert_eq!(subtree.next().unwrap().data, "Romance");
+    /// let mut cursor = root_token.cursor(&arena);
+    /// cursor.first_child();
+    /// assert_eq!(cursor.first_child(), Some(&"English"));
+    /// assert_eq!(cursor.parent(), Some(&"Germanic"));
     /// ```
-    pub fn append<T>(self, arena: &mut Arena<T>, data: T) -> Token {
-        let new_node_token = arena.allocator.head();
-        let previous_sibling = match self.children_mut(arena).last() {
-            None => {
-                // children_mut will have checked indexability so this will not
-                // fail
-                arena[self].first_child = Some(new_node_token);
-                None
-            },
-            Some(last_child) => {
-                last_child.next_sibling = Some(new_node_token);
-                Some(last_child.token)
-            }
-        };
-
-        let node = Node {
-            data,
-            token: new_node_token,
-            parent: Some(self),
-            previous_sibling,
-            next_sibling: None,
-            first_child: None
-        };
-        arena.set(new_node_token, node);
-        new_node_token
+    ///
+    /// [`Cursor`]: struct.Cursor.html
+    pub fn cursor<T>(self, arena: &Arena<T>) -> Cursor<'_, T> {
+        Cursor::new(arena, self)
     }
 
-    /// Creates a new node with the given data and sets as the previous sibling
-    /// of the current node.
+    /// Returns a cursor pointing at the current node that can also edit the
+    /// tree. See [`CursorMut`] for details.
     ///
     /// # Panics:
     ///
@@ -104,161 +229,157 @@ impl Token {
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let mut cursor = root_token.cursor_mut(&mut arena);
+    /// let germanic = cursor.append_child("Germanic");
+    /// assert_eq!(cursor.first_child(), Some(&"Germanic"));
+    /// assert_eq!(cursor.token(), germanic);
+    /// ```
     ///
-    /// let child2 = root_token.append(&mut arena, "Germanic");
-    /// let child4 = root_token.append(&mut arena, "Slavic");
-    /// child2.append(&mut arena, "English");
-    /// // insert in between children 2 and 4
-    /// let child3 = child4.insert_before(&mut arena, "Romance");
-    /// // insert before child 2
-    /// let child1 = child2.insert_before(&mut arena, "Celtic");
+    /// [`CursorMut`]: struct.CursorMut.html
+    pub fn cursor_mut<T>(self, arena: &mut Arena<T>) -> CursorMut<'_, T> {
+        CursorMut::new(arena, self)
+    }
+
+    /// Checks whether a given node is actually a leaf.
     ///
-    /// let subtree: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data)
-    ///     .collect();
-    /// assert_eq!(&["Indo-European", "Celtic", "Germanic", "English", "Romance", "Slavic"],
-    ///            &subtree[..]);
-    /// ```
-    pub fn insert_before<T>(self, arena: &mut Arena<T>, data: T) -> Token {
-        let new_node_token = arena.allocator.head();
-        let (self_parent, self_previous_sibling) = match arena.get(self) {
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    pub fn is_leaf<T>(self, arena: &Arena<T>) -> bool {
+        match arena.get(self) {
             None => panic!("Invalid token"),
-            Some(node) => (node.parent, node.previous_sibling)
-        };
-        arena[self].previous_sibling = Some(new_node_token);  // already checked
-        let previous_sibling = match self_previous_sibling {
-            Some(sibling) => match arena.get_mut(sibling) {
-                None => panic!("Corrupt arena"),
-                Some(ref mut node) => {
-                    node.next_sibling = Some(new_node_token);
-                    Some(sibling)
-                }
-            },
-            None => match self_parent {
-                None => panic!("Cannot insert as the previous sibling of the \
-                                root node"),
-                Some(p) => match arena.get_mut(p) {
-                    None => panic!("Corrupt arena"),
-                    Some(ref mut node) => {
-                        node.first_child = Some(new_node_token);
-                        None
-                    }
-                }
-            }
-        };
-
-        let node = Node {
-            data,
-            token: new_node_token,
-            parent: self_parent,
-            previous_sibling,
-            next_sibling: Some(self),
-            first_child: None
-        };
-        arena.set(new_node_token, node);
-        new_node_token
+            Some(node) => node.is_leaf()
+        }
     }
 
-    /// Set a node in the arena as the next sibling of the given node. Returns
-    /// error if the "other node" is not a root node of a tree (as in it already
-    /// has a parent and/or siblings).
-    ///
-    /// **Note**: for performance reasons, this operation does not check whether
-    /// the "self" node is in fact a descendant of the other tree. A cyclic
-    /// graph may result.
+    /// Checks whether a given node is a root node, i.e. it has no parent.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// // root node that we will attach subtrees to
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root) = Arena::with_data(root_data);
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// assert!(root_token.is_root(&arena));
+    /// assert!(!germanic.is_root(&arena));
+    /// ```
+    pub fn is_root<T>(self, arena: &Arena<T>) -> bool {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.is_root()
+        }
+    }
+
+    /// Checks whether a given node is the only child of its parent. Root nodes
+    /// are not considered only children.
     ///
-    /// let germanic = root.append(&mut arena, "Germanic");
-    /// let scots = germanic.append(&mut arena, "German");
-    /// let english = germanic.append(&mut arena, "English");
+    /// # Panics:
     ///
-    /// let romance = arena.new_node("Romance");
-    /// let french = romance.append(&mut arena, "French");
-    /// let spanish = romance.append(&mut arena, "Spanish");
+    /// Panics if the token does not correspond to a node in the arena.
     ///
-    /// germanic.insert_node_after(&mut arena, romance);
+    /// # Examples:
     ///
-    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("Indo-European"));
-    /// assert_eq!(iter.next(), Some("Germanic"));
-    /// assert_eq!(iter.next(), Some("German"));
-    /// assert_eq!(iter.next(), Some("English"));
-    /// assert_eq!(iter.next(), Some("Romance"));
-    /// assert_eq!(iter.next(), Some("French"));
-    /// assert_eq!(iter.next(), Some("Spanish"));
-    /// assert!(iter.next().is_none())
     /// ```
-    pub fn insert_node_after<T>(self, arena: &mut Arena<T>, other: Token)
-        -> Result<(), Error> {
-        node_operation(self, arena, other, Token::insert_after)
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// assert!(germanic.is_only_child(&arena));
+    /// assert!(!root_token.is_only_child(&arena));
+    ///
+    /// root_token.append(&mut arena, "Romance");
+    /// assert!(!germanic.is_only_child(&arena));
+    /// ```
+    pub fn is_only_child<T>(self, arena: &Arena<T>) -> bool {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.is_only_child()
+        }
     }
 
-    /// Set a node in the arena as the previous sibling of the given node.
-    /// Returns error if the "other node" is not a root node of a tree (as in it
-    /// already has a parent and/or siblings).
+    /// Returns the token of the parent of the given node.
     ///
-    /// **Note**: for performance reasons, this operation does not check whether
-    /// the "self" node is in fact a descendant of the other tree. A cyclic
-    /// graph may result.
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// assert_eq!(germanic.parent(&arena), Some(root_token));
+    /// assert!(root_token.parent(&arena).is_none());
+    /// ```
+    pub fn parent<T>(self, arena: &Arena<T>) -> Option<Token> {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.parent
+        }
+    }
+
+    /// Returns the token of the sibling that comes right before the given
+    /// node.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// // root node that we will attach subtrees to
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root) = Arena::with_data(root_data);
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// assert_eq!(romance.previous_sibling(&arena), Some(germanic));
+    /// assert!(germanic.previous_sibling(&arena).is_none());
+    /// ```
+    #[doc(alias = "prev_sibling")]
+    pub fn previous_sibling<T>(self, arena: &Arena<T>) -> Option<Token> {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.previous_sibling
+        }
+    }
+
+    /// Returns the token of the sibling that comes right after the given node.
     ///
-    /// let germanic = root.append(&mut arena, "Germanic");
-    /// let scots = germanic.append(&mut arena, "German");
-    /// let english = germanic.append(&mut arena, "English");
+    /// # Panics:
     ///
-    /// let romance = arena.new_node("Romance");
-    /// let french = romance.append(&mut arena, "French");
-    /// let spanish = romance.append(&mut arena, "Spanish");
+    /// Panics if the token does not correspond to a node in the arena.
     ///
-    /// germanic.insert_node_before(&mut arena, romance);
+    /// # Examples:
     ///
-    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("Indo-European"));
-    /// assert_eq!(iter.next(), Some("Romance"));
-    /// assert_eq!(iter.next(), Some("French"));
-    /// assert_eq!(iter.next(), Some("Spanish"));
-    /// assert_eq!(iter.next(), Some("Germanic"));
-    /// assert_eq!(iter.next(), Some("German"));
-    /// assert_eq!(iter.next(), Some("English"));
-    /// assert!(iter.next().is_none())
     /// ```
-    pub fn insert_node_before<T>(self, arena: &mut Arena<T>, other: Token)
-        -> Result<(), Error> {
-        node_operation(self, arena, other, Token::insert_before)
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// assert_eq!(germanic.next_sibling(&arena), Some(romance));
+    /// assert!(romance.next_sibling(&arena).is_none());
+    /// ```
+    pub fn next_sibling<T>(self, arena: &Arena<T>) -> Option<Token> {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.next_sibling
+        }
     }
 
-    /// Creates a new node with the given data and sets as the next sibling of
-    /// the current node.
+    /// Checks whether the subtree of the given node is a perfect tree, i.e.
+    /// all leaves are at the same depth and all other nodes have the same
+    /// number of children.
     ///
     /// # Panics:
     ///
@@ -268,333 +389,215 @@ impl Token {
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// assert!(root_token.is_perfect(&arena));
     ///
-    /// let child1 = root_token.append(&mut arena, "Romance");
-    /// let child3 = root_token.append(&mut arena, "Germanic");
-    /// child1.append(&mut arena, "French");
-    /// // insert betwern children 1 and 4
-    /// let child2 = child1.insert_after(&mut arena, "Celtic");
-    /// // insert after child 3
-    /// child3.insert_after(&mut arena, "Slavic");
+    /// germanic.append(&mut arena, "English");
+    /// germanic.append(&mut arena, "Swedish");
+    /// assert!(!root_token.is_perfect(&arena));
     ///
-    /// let subtree: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data)
-    ///     .collect();
-    /// assert_eq!(&["Indo-European", "Romance", "French", "Celtic", "Germanic", "Slavic"],
-    ///            &subtree[..]);
+    /// romance.append(&mut arena, "French");
+    /// romance.append(&mut arena, "Italian");
+    /// assert!(root_token.is_perfect(&arena));
     /// ```
-    pub fn insert_after<T>(self, arena: &mut Arena<T>, data: T) -> Token {
-        let new_node_token = arena.allocator.head();
-        let (self_parent, self_next_sibling) = match arena.get(self) {
-            None => panic!("Invalid token"),
-            Some(node) => (node.parent, node.next_sibling)
-        };
-        arena[self].next_sibling = Some(new_node_token);  // already checked
-        let next_sibling = match self_next_sibling {
-            None => None,
-            Some(sibling) => match arena.get_mut(sibling) {
-                None => panic!("Corrupt arena"),
-                Some(ref mut node) => {
-                    node.previous_sibling = Some(new_node_token);
-                    Some(sibling)
+    pub fn is_perfect<T>(self, arena: &Arena<T>) -> bool {
+        if arena.get(self).is_none() { panic!("Invalid token") }
+        let mut arity = None;
+        let mut level = vec![self];
+        loop {
+            let mut next_level = Vec::new();
+            let mut has_leaves = false;
+            for token in level {
+                let before = next_level.len();
+                next_level.extend(token.children_tokens(arena));
+                match next_level.len() - before {
+                    0 => has_leaves = true,
+                    n => match arity {
+                        None => arity = Some(n),
+                        Some(m) => if n != m { return false }
+                    }
                 }
-            },
-        };
-
-        let node = Node {
-            data,
-            token: new_node_token,
-            parent: self_parent,
-            previous_sibling: Some(self),
-            next_sibling,
-            first_child: None
-        };
-        arena.set(new_node_token, node);
-        new_node_token
+            }
+            match (has_leaves, next_level.is_empty()) {
+                (true, true) => break true,  // all leaves are on this level
+                (true, false) => break false,
+                _ => level = next_level
+            }
+        }
     }
 
-    /// Attaches a different tree in the arena to a node. Returns error if the
-    /// "root node" of the other tree is not really a root node (as in it
-    /// already has a parent and/or siblings). To attach a tree from a different
-    /// arena, use [`copy_and_append_subtree`] instead.
-    ///
-    /// **Note**: for performance reasons, this operation does not check whether
-    /// the "self" node is in fact a descendant of the other tree. A cyclic
-    /// graph may result.
+    /// Checks whether the subtree of the given node is a complete tree, i.e.
+    /// all levels are full except possibly the last one, which is filled from
+    /// left to right. A level is full when every node on the level above has
+    /// as many children as the node with the most children in the subtree.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// // root node that we will attach subtrees to
     /// let root_data = "Indo-European";
-    /// let (mut arena, root) = Arena::with_data(root_data);
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// romance.append(&mut arena, "French");
+    /// assert!(!root_token.is_complete(&arena));
+    ///
+    /// germanic.append(&mut arena, "English");
+    /// germanic.append(&mut arena, "Swedish");
+    /// assert!(root_token.is_complete(&arena));
+    /// ```
+    pub fn is_complete<T>(self, arena: &Arena<T>) -> bool {
+        let tokens: Vec<_> = self.subtree_tokens(arena, TraversalOrder::Level)
+            .collect();
+        let child_counts: Vec<_> = tokens.iter()
+            .map(|token| token.children_tokens(arena).count())
+            .collect();
+        let arity = child_counts.iter().copied().max().unwrap_or(0);
+        // once a node with fewer children than the maximum is found, the rest
+        // of the nodes in level order must all be leaves
+        match child_counts.iter().position(|&n| n < arity) {
+            None => true,
+            Some(i) => child_counts[i + 1..].iter().all(|&n| n == 0)
+        }
+    }
+
+    /// Returns the number of nodes strictly below the given node, i.e. the
+    /// number of its descendants.
     ///
-    /// // the Germanic branch
-    /// let germanic = arena.new_node("Germanic");
-    /// let west = germanic.append(&mut arena, "West");
-    /// let scots = west.append(&mut arena, "Scots");
-    /// let english = west.append(&mut arena, "English");
+    /// # Panics:
     ///
-    /// // the Romance branch
-    /// let romance = arena.new_node("Romance");
-    /// let french = romance.append(&mut arena, "French");
-    /// let italian = romance.append(&mut arena, "Italian");
+    /// Panics if the token does not correspond to a node in the arena.
     ///
-    /// // attach subtrees
-    /// root.append_node(&mut arena, romance).unwrap();
-    /// root.append_node(&mut arena, germanic).unwrap();
+    /// # Examples:
     ///
-    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("Indo-European"));
-    /// assert_eq!(iter.next(), Some("Romance"));
-    /// assert_eq!(iter.next(), Some("French"));
-    /// assert_eq!(iter.next(), Some("Italian"));
-    /// assert_eq!(iter.next(), Some("Germanic"));
-    /// assert_eq!(iter.next(), Some("West"));
-    /// assert_eq!(iter.next(), Some("Scots"));
-    /// assert_eq!(iter.next(), Some("English"));
-    /// assert!(iter.next().is_none())
     /// ```
+    /// use atree::Arena;
     ///
-    /// [`copy_and_append_subtree`]: struct.Arena.html#method.copy_and_append_subtree
-    pub fn append_node<T>(self, arena: &mut Arena<T>, other: Self)
-        -> Result<(), Error> {
-        node_operation(self, arena, other, Token::append)
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// assert_eq!(root_token.descendant_count(&arena), 3);
+    /// assert_eq!(germanic.descendant_count(&arena), 1);
+    /// assert_eq!(english.descendant_count(&arena), 0);
+    /// ```
+    pub fn descendant_count<T>(self, arena: &Arena<T>) -> usize {
+        // explicit stack instead of recursion so deep trees cannot overflow
+        let mut stack: Vec<Token> = self.children_tokens(arena).collect();
+        let mut count = 0;
+        while let Some(token) = stack.pop() {
+            count += 1;
+            stack.extend(token.children_tokens(arena));
+        }
+        count
     }
 
-    /// Detaches the given node and its descendants into its own tree while
-    /// keeping it in the same arena. To detach and allocate the subtree into its
-    /// own arena, use [`split_at`] instead.
+    /// Returns the number of nodes in the subtree of the given node, including
+    /// the node itself. The count is exact and takes a traversal of the
+    /// subtree. See [`subtree_size_hint`] for a cheap upper bound.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// // root node that we will attach subtrees to
     /// let root_data = "Indo-European";
-    /// let (mut arena, root) = Arena::with_data(root_data);
-    ///
-    /// // the Germanic branch
-    /// let germanic = root.append(&mut arena, "Germanic");
-    /// let west = germanic.append(&mut arena, "West");
-    /// let scots = west.append(&mut arena, "Scots");
-    /// let english = west.append(&mut arena, "English");
-    ///
-    /// // detach the west branch from the main tree
-    /// west.detach(&mut arena);
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// // the west branch is gone from the original tree
-    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("Indo-European"));
-    /// assert_eq!(iter.next(), Some("Germanic"));
-    /// assert!(iter.next().is_none());
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
     ///
-    /// // it exists on its own
-    /// let mut iter = west.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("West"));
-    /// assert_eq!(iter.next(), Some("Scots"));
-    /// assert_eq!(iter.next(), Some("English"));
-    /// assert!(iter.next().is_none());
+    /// assert_eq!(root_token.subtree_size(&arena), 4);
+    /// assert_eq!(english.subtree_size(&arena), 1);
     /// ```
     ///
-    /// [`split_at`]: struct.Arena.html#method.split_at
-    pub fn detach<T>(self, arena: &mut Arena<T>) {
-        let (parent, previous_sibling, next_sibling) = match arena.get_mut(self) {
-            None => panic!("Invalid token"),
-            Some(node) => {
-                let parent = node.parent;
-                let previous_sibling = node.previous_sibling;
-                let next_sibling = node.next_sibling;
-                node.parent = None;
-                node.previous_sibling = None;
-                node.next_sibling = None;
-                (parent, previous_sibling, next_sibling)
-            }
-        };
-
-        match previous_sibling {
-            Some(token) => match arena.get_mut(token) {
-                None => panic!("Corrupt arena"),
-                Some(node) => node.next_sibling = next_sibling
-            },
-            None => if let Some(token) = parent {
-                match arena.get_mut(token) {
-                    None => panic!("Corrupt arena"),
-                    Some(n) => n.first_child = next_sibling
-                }
-            }
-        }
-
-        if let Some(token) = next_sibling {
-            match arena.get_mut(token) {
-                None => panic!("Corrupt arena"),
-                Some(node) => node.previous_sibling = previous_sibling
-            }
-        }
+    /// [`subtree_size_hint`]: struct.Token.html#method.subtree_size_hint
+    pub fn subtree_size<T>(self, arena: &Arena<T>) -> usize {
+        self.descendant_count(arena) + 1
     }
 
-    /// Replace the subtree of self with the subtree of other. Does not remove
-    /// self or its descendants but simply makes it a standalone tree within the
-    /// arena.
-    ///
-    /// **Note**: for performance reasons, this operation does not check whether
-    /// the "other" node is in fact a descendant of the parent tree of self. A
-    /// cyclic graph may result.
+    /// Returns an upper bound on the number of nodes in the subtree of the
+    /// given node without visiting the subtree. Only the ancestors of the node
+    /// are walked, so this is cheap even for large subtrees. The bound is
+    /// exact when the node's ancestors and the subtree itself make up the
+    /// whole arena; use [`subtree_size`] when the exact count is needed, which
+    /// traverses the subtree. Useful for pre-sizing buffers.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
+    ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
-    ///
-    /// // root node that we will attach subtrees to
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root) = Arena::with_data(root_data);
-    ///
-    /// // the Germanic branch
-    /// let germanic = root.append(&mut arena, "Germanic");
-    /// let west = germanic.append(&mut arena, "West");
-    /// let scots = west.append(&mut arena, "Scots");
-    /// let english = west.append(&mut arena, "English");
-    ///
-    /// // the slavic branch
-    /// let slavic = root.append(&mut arena, "Slavic");
-    /// let polish = slavic.append(&mut arena, "Polish");
-    /// let russian = slavic.append(&mut arena, "Russian");
-    ///
-    /// // the Romance branch
-    /// let romance = arena.new_node("Romance");
-    /// let french = romance.append(&mut arena, "French");
-    /// let italian = romance.append(&mut arena, "Italian");
     ///
-    /// // replace_node germanic with romance
-    /// germanic.replace_node(&mut arena, romance).unwrap();
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
     ///
-    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-    ///     .map(|x| x.data);
-    /// assert_eq!(iter.next(), Some("Indo-European"));
-    /// assert_eq!(iter.next(), Some("Romance"));
-    /// assert_eq!(iter.next(), Some("French"));
-    /// assert_eq!(iter.next(), Some("Italian"));
-    /// assert_eq!(iter.next(), Some("Slavic"));
-    /// assert_eq!(iter.next(), Some("Polish"));
-    /// assert_eq!(iter.next(), Some("Russian"));
-    /// assert!(iter.next().is_none());
+    /// assert_eq!(root_token.subtree_size_hint(&arena), 4);
+    /// assert!(romance.subtree_size_hint(&arena) >= 1);
     /// ```
-    pub fn replace_node<T>(self, arena: &mut Arena<T>, other: Token)
-        -> Result<(), Error> {
-        let self_node = match arena.get(self) {
-            None => panic!("Invalid token"),
-            Some(n) => n
-        };
-        let parent = self_node.parent;
-        let previous_sibling = self_node.previous_sibling;
-        let next_sibling = self_node.next_sibling;
-
-        let other_node = match arena.get_mut(other) {
-            None => panic!("Invalid token"),
-            Some(n) => n
-        };
-
-        // check that the other node is really a root node of its own
-        match (other_node.previous_sibling,
-               other_node.next_sibling,
-               other_node.parent) {
-            (None, None, None) => (),
-            _ => return Err(Error::NotARootNode)
-        }
-
-        // replace_node the self node with the other node
-        other_node.parent = parent;
-        other_node.next_sibling = next_sibling;
-        other_node.previous_sibling = previous_sibling;
-
-        let self_node = &mut arena[self];  // indexability has been checked
-        self_node.parent = None;
-        self_node.previous_sibling = None;
-        self_node.next_sibling = None;
-
-        // update previous_sibling, next_sibling and parent of the self node
-        match previous_sibling {
-            Some(sibling) => match arena.get_mut(sibling) {
-                None => panic!("Corrupt arena"),
-                Some(node) => node.next_sibling = Some(other)
-            },
-            None => if let Some(p) = parent {
-                match arena.get_mut(p) {
-                    None => panic!("Corrupt arena"),
-                    Some(node) => node.first_child = Some(other)
-                }
-            }
-        }
-
-        if let Some(sibling) = next_sibling {
-            match arena.get_mut(sibling) {
-                None => panic!("Corrupt arena"),
-                Some(node) => node.previous_sibling = Some(other)
-            }
-        }
-
-        Ok(())
+    ///
+    /// [`subtree_size`]: struct.Token.html#method.subtree_size
+    pub fn subtree_size_hint<T>(self, arena: &Arena<T>) -> usize {
+        arena.node_count() - self.ancestors_tokens(arena).count()
     }
 
-    /// Returns an iterator of tokens of ancestor nodes.
+    /// Creates a new node with the given data and append to the given node.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// let child_token = root_token.append(&mut arena, "Germanic");
-    /// let grandchild_token = child_token.append(&mut arena, "English");
-    /// let mut ancestors_tokens = grandchild_token.ancestors_tokens(&arena);
+    /// let next_node_token = root_token.append(&mut arena, "Germanic");
+    /// next_node_token.append(&mut arena, "Romance");
+    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre);
     ///
-    /// assert_eq!(ancestors_tokens.next(), Some(child_token));
-    /// assert_eq!(ancestors_tokens.next(), Some(root_token));
-    /// assert!(ancestors_tokens.next().is_none());
+    /// assert_eq!(subtree.next().unwrap().data, "Indo-European");
+    /// assert_eq!(subtree.next().unwrap().data, "Germanic");
+    /// assert_eq!(subtree.next().unwrap().data, "Romance");
     /// ```
-    pub fn ancestors_tokens<'a, T>(self, arena: &'a Arena<T>)
-        -> AncestorTokens<'a, T> {
-        let parent = match arena.get(self) {
-            Some(n) => n.parent,
-            None => panic!("Invalid token")
-        };
-        AncestorTokens { arena, node_token: parent }
+    pub fn append<T>(self, arena: &mut Arena<T>, data: T) -> Token {
+        match self.try_append(arena, data) {
+            Ok(token) => token,
+            Err(Error::FrozenNode) => panic!("Frozen node"),
+            Err(_) => panic!("Invalid token")
+        }
     }
 
-    /// Returns an iterator of tokens of siblings preceding the current node.
-    ///
-    /// # Panics:
-    ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Creates a new node with the given data and append to the given node.
+    /// Unlike [`append`], returns error instead of panicking if the token does
+    /// not correspond to a node in the arena or if the node is frozen.
     ///
     /// # Examples:
     ///
@@ -604,30 +607,30 @@ impl Token {
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// let first_child_token = root_token.append(&mut arena, "Germanic");
-    /// let second_child_token = root_token.append(&mut arena, "Romance");
-    /// let third_child_token = root_token.append(&mut arena, "Slavic");
-    /// root_token.append(&mut arena, "Hellenic");
+    /// let germanic = root_token.try_append(&mut arena, "Germanic").unwrap();
+    /// assert_eq!(arena[germanic].parent(), Some(root_token));
     ///
-    /// let mut sibling_tokens = third_child_token.preceding_siblings_tokens(&arena);
-    /// assert_eq!(sibling_tokens.next(), Some(second_child_token));
-    /// assert_eq!(sibling_tokens.next(), Some(first_child_token));
-    /// assert!(sibling_tokens.next().is_none());
+    /// arena.uproot(germanic);
+    /// assert!(germanic.try_append(&mut arena, "English").is_err());
     /// ```
-    pub fn preceding_siblings_tokens<'a, T>(self, arena: &'a Arena<T>)
-        -> PrecedingSiblingTokens<'a, T> {
-        let previous_sibling = match arena.get(self) {
-            Some(n) => n.previous_sibling,
-            None => panic!("Invalid token")
-        };
-        PrecedingSiblingTokens { arena, node_token: previous_sibling }
+    ///
+    /// [`append`]: struct.Token.html#method.append
+    pub fn try_append<T>(self, arena: &mut Arena<T>, data: T)
+        -> Result<Token, Error> {
+        if arena.get(self).is_none() { return Err(Error::InvalidToken) }
+        if is_frozen(arena, Some(self)) { return Err(Error::FrozenNode) }
+        let new_node_token = arena.new_node(data);
+        link_as_last_child(self, arena, new_node_token);
+        Ok(new_node_token)
     }
 
-    /// Returns an iterator of tokens of siblings following the current node.
+    /// Creates a new node with the given data and inserts it as the first child
+    /// of the given node.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
     ///
     /// # Examples:
     ///
@@ -637,26 +640,30 @@ impl Token {
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, "Romance");
-    /// let second_child_token = root_token.append(&mut arena, "Germanic");
-    /// let third_child_token = root_token.append(&mut arena, "Slavic");
-    /// let fourth_child_token = root_token.append(&mut arena, "Hellenic");
+    /// root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.prepend(&mut arena, "Romance");
     ///
-    /// let mut sibling_tokens = second_child_token.following_siblings_tokens(&arena);
-    /// assert_eq!(sibling_tokens.next(), Some(third_child_token));
-    /// assert_eq!(sibling_tokens.next(), Some(fourth_child_token));
-    /// assert!(sibling_tokens.next().is_none());
+    /// let mut children = root_token.children(&arena).map(|x| x.data);
+    /// assert_eq!(children.next(), Some("Romance"));
+    /// assert_eq!(children.next(), Some("Germanic"));
+    /// assert!(children.next().is_none());
+    /// assert_eq!(arena[root_token].first_child(), Some(romance));
     /// ```
-    pub fn following_siblings_tokens<'a, T>(self, arena: &'a Arena<T>)
-        -> FollowingSiblingTokens<'a, T> {
-        let next_sibling = match arena.get(self) {
-            Some(n) => n.next_sibling,
-            None => panic!("Invalid token")
-        };
-        FollowingSiblingTokens { arena, node_token: next_sibling }
+    pub fn prepend<T>(self, arena: &mut Arena<T>, data: T) -> Token {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => if node.frozen { panic!("Frozen node") }
+        }
+        let new_node_token = arena.new_node(data);
+        link_as_first_child(self, arena, new_node_token);
+        new_node_token
     }
 
-    /// Returns an iterator of tokens of child nodes in the order of insertion.
+    /// Creates a new node with the given data and inserts it among the children
+    /// of the given node so that it becomes the child at the given (zero-based)
+    /// index. An index equal to the number of children appends the new node.
+    /// Returns error if the index is greater than the number of children or if
+    /// the node is frozen.
     ///
     /// # Panics:
     ///
@@ -670,32 +677,48 @@ impl Token {
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// let first_child_token = root_token.append(&mut arena, "Romance");
-    /// let second_child_token = root_token.append(&mut arena, "Germanic");
-    /// let third_child_token = root_token.append(&mut arena, "Slavic");
-    /// let fourth_child_token = root_token.append(&mut arena, "Hellenic");
+    /// root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Slavic");
+    /// root_token.insert_child_at(&mut arena, 1, "Romance").unwrap();
+    /// root_token.insert_child_at(&mut arena, 3, "Celtic").unwrap();
+    /// assert!(root_token.insert_child_at(&mut arena, 10, "Greek").is_err());
     ///
-    /// let mut children_tokens = root_token.children_tokens(&arena);
-    /// assert_eq!(children_tokens.next(), Some(first_child_token));
-    /// assert_eq!(children_tokens.next(), Some(second_child_token));
-    /// assert_eq!(children_tokens.next(), Some(third_child_token));
-    /// assert_eq!(children_tokens.next(), Some(fourth_child_token));
-    /// assert!(children_tokens.next().is_none());
+    /// let children: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+    /// assert_eq!(children, vec!["Germanic", "Romance", "Slavic", "Celtic"]);
     /// ```
-    pub fn children_tokens<'a, T>(self, arena: &'a Arena<T>)
-        -> ChildrenTokens<'a, T> {
-        let first_child = match arena.get(self) {
-            Some(n) => n.first_child,
-            None => panic!("Invalid token")
+    pub fn insert_child_at<T>(self, arena: &mut Arena<T>, index: usize, data: T)
+        -> Result<Token, Error> {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => if node.frozen { return Err(Error::FrozenNode) }
+        }
+        let new_node_token = match self.children_tokens(arena).nth(index) {
+            Some(child) => {
+                let token = arena.new_node(data);
+                link_as_previous_sibling(child, arena, token);
+                token
+            },
+            None => match self.children_tokens(arena).count() == index {
+                true => {
+                    let token = arena.new_node(data);
+                    link_as_last_child(self, arena, token);
+                    token
+                },
+                false => return Err(Error::IndexOutOfBounds)
+            }
         };
-        ChildrenTokens { arena, node_token: first_child }
+        Ok(new_node_token)
     }
 
-    /// Returns an iterator of references of ancestor nodes.
+    /// Creates new nodes with the given data and appends them to the given node
+    /// in iteration order. Returns the tokens of the new nodes in the same
+    /// order. Backing storage is reserved once based on the size hint of the
+    /// iterator.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
     ///
     /// # Examples:
     ///
@@ -705,20 +728,44 @@ impl Token {
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// let child_token = root_token.append(&mut arena, "Germanic");
-    /// let grandchild_token = child_token.append(&mut arena, "Swedish");
-    /// let mut ancestors = grandchild_token.ancestors(&arena);
+    /// root_token.append(&mut arena, "Romance");
+    /// let tokens = root_token.append_children(&mut arena, vec!["Germanic", "Slavic"]);
+    /// assert_eq!(tokens.len(), 2);
     ///
-    /// assert_eq!(ancestors.next().unwrap().data, "Germanic");
-    /// assert_eq!(ancestors.next().unwrap().data, "Indo-European");
-    /// assert!(ancestors.next().is_none());
+    /// let mut children = root_token.children(&arena);
+    /// assert_eq!(children.next().unwrap().data, "Romance");
+    /// assert_eq!(children.next().unwrap().data, "Germanic");
+    /// assert_eq!(children.next().unwrap().data, "Slavic");
+    /// assert!(children.next().is_none());
     /// ```
-    pub fn ancestors<'a, T>(self, arena: &'a Arena<T>) -> Ancestors<'a, T> {
-        Ancestors { token_iter: self.ancestors_tokens(arena) }
+    pub fn append_children<T, I>(self, arena: &mut Arena<T>, data: I)
+        -> Vec<Token> where I: IntoIterator<Item=T> {
+        if arena.get(self).is_none() { panic!("Invalid token") }
+        if is_frozen(arena, Some(self)) { panic!("Frozen node") }
+        let iter = data.into_iter();
+        let (lower, _) = iter.size_hint();
+        arena.reserve(lower);
+
+        let mut tokens = Vec::with_capacity(lower);
+        let mut previous_sibling = arena[self].last_child;
+        for d in iter {
+            let token = arena.new_node(d);
+            match previous_sibling {
+                None => arena.node_mut(self).first_child = Some(token),
+                Some(sibling) => arena.node_mut(sibling).next_sibling = Some(token)
+            }
+            let node = arena.node_mut(token);
+            node.parent = Some(self);
+            node.previous_sibling = previous_sibling;
+            previous_sibling = Some(token);
+            tokens.push(token);
+        }
+        arena.node_mut(self).last_child = previous_sibling;
+        tokens
     }
 
-    /// Returns an iterator of references of sibling nodes preceding the current
-    /// node.
+    /// Creates a new node with the given data and sets as the previous sibling
+    /// of the current node.
     ///
     /// # Panics:
     ///
@@ -728,86 +775,139 @@ impl Token {
     ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, "Romance");
-    /// root_token.append(&mut arena, "Germanic");
-    /// let third_child_token = root_token.append(&mut arena, "Slavic");
-    /// root_token.append(&mut arena, "Celtic");
+    /// let child2 = root_token.append(&mut arena, "Germanic");
+    /// let child4 = root_token.append(&mut arena, "Slavic");
+    /// child2.append(&mut arena, "English");
+    /// // insert in between children 2 and 4
+    /// let child3 = child4.insert_before(&mut arena, "Romance");
+    /// // insert before child 2
+    /// let child1 = child2.insert_before(&mut arena, "Celtic");
     ///
-    /// let mut siblings = third_child_token.preceding_siblings(&arena);
-    /// assert_eq!(siblings.next().unwrap().data, "Germanic");
-    /// assert_eq!(siblings.next().unwrap().data, "Romance");
-    /// assert!(siblings.next().is_none());
+    /// let subtree: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data)
+    ///     .collect();
+    /// assert_eq!(&["Indo-European", "Celtic", "Germanic", "English", "Romance", "Slavic"],
+    ///            &subtree[..]);
     /// ```
-    pub fn preceding_siblings<'a, T>(self, arena: &'a Arena<T>)
-        -> PrecedingSiblings<'a, T> {
-        PrecedingSiblings { token_iter: self.preceding_siblings_tokens(arena) }
+    pub fn insert_before<T>(self, arena: &mut Arena<T>, data: T) -> Token {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => match node.parent {
+                None => panic!("Cannot insert as the previous sibling of the \
+                                root node"),
+                Some(p) => if is_frozen(arena, Some(p)) { panic!("Frozen node") }
+            }
+        }
+        let new_node_token = arena.new_node(data);
+        link_as_previous_sibling(self, arena, new_node_token);
+        new_node_token
     }
 
-    /// Returns an iterator of references of sibling nodes following the current
-    /// node.
+    /// Set a node in the arena as the next sibling of the given node. Returns
+    /// error if the "other node" is not a root node of a tree (as in it already
+    /// has a parent and/or siblings).
+    ///
+    /// **Note**: for performance reasons, this operation does not check whether
+    /// the "self" node is in fact a descendant of the other tree. A cyclic
+    /// graph may result.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
-    ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
+    /// // root node that we will attach subtrees to
     /// let root_data = "Indo-European";
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let (mut arena, root) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, "Romance");
-    /// let second_child_token = root_token.append(&mut arena, "Germanic");
-    /// root_token.append(&mut arena, "Slavic");
-    /// root_token.append(&mut arena, "Hellenic");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let scots = germanic.append(&mut arena, "German");
+    /// let english = germanic.append(&mut arena, "English");
     ///
-    /// let mut siblings = second_child_token.following_siblings(&arena);
-    /// assert_eq!(siblings.next().unwrap().data, "Slavic");
-    /// assert_eq!(siblings.next().unwrap().data, "Hellenic");
-    /// assert!(siblings.next().is_none());
+    /// let romance = arena.new_node("Romance");
+    /// let french = romance.append(&mut arena, "French");
+    /// let spanish = romance.append(&mut arena, "Spanish");
+    ///
+    /// germanic.insert_node_after(&mut arena, romance);
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("German"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert_eq!(iter.next(), Some("French"));
+    /// assert_eq!(iter.next(), Some("Spanish"));
+    /// assert!(iter.next().is_none())
     /// ```
-    pub fn following_siblings<'a, T>(self, arena: &'a Arena<T>)
-        -> FollowingSiblings<'a, T> {
-        FollowingSiblings { token_iter: self.following_siblings_tokens(arena) }
+    pub fn insert_node_after<T>(self, arena: &mut Arena<T>, other: Token)
+        -> Result<(), Error> {
+        let parent = arena.get(self).and_then(|node| node.parent);
+        if is_frozen(arena, parent) { return Err(Error::FrozenNode) }
+        node_operation(self, arena, other, link_as_next_sibling)
     }
 
-    /// Returns an iterator of child node references in the order of insertion.
+    /// Set a node in the arena as the previous sibling of the given node.
+    /// Returns error if the "other node" is not a root node of a tree (as in it
+    /// already has a parent and/or siblings).
+    ///
+    /// **Note**: for performance reasons, this operation does not check whether
+    /// the "self" node is in fact a descendant of the other tree. A cyclic
+    /// graph may result.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
-    ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
+    /// // root node that we will attach subtrees to
     /// let root_data = "Indo-European";
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// let (mut arena, root) = Arena::with_data(root_data);
     ///
-    /// let first_child_token = root_token.append(&mut arena, "Germanic");
-    /// let second_child_token = root_token.append(&mut arena, "Romance");
-    /// let third_child_token = root_token.append(&mut arena, "Slavic");
-    /// let fourth_child_token = root_token.append(&mut arena, "Celtic");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let scots = germanic.append(&mut arena, "German");
+    /// let english = germanic.append(&mut arena, "English");
     ///
-    /// let mut children = root_token.children(&arena);
-    /// assert_eq!(children.next().unwrap().data, "Germanic");
-    /// assert_eq!(children.next().unwrap().data, "Romance");
-    /// assert_eq!(children.next().unwrap().data, "Slavic");
-    /// assert_eq!(children.next().unwrap().data, "Celtic");
-    /// assert!(children.next().is_none());
+    /// let romance = arena.new_node("Romance");
+    /// let french = romance.append(&mut arena, "French");
+    /// let spanish = romance.append(&mut arena, "Spanish");
+    ///
+    /// germanic.insert_node_before(&mut arena, romance);
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert_eq!(iter.next(), Some("French"));
+    /// assert_eq!(iter.next(), Some("Spanish"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("German"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert!(iter.next().is_none())
     /// ```
-    pub fn children<'a, T>(self, arena: &'a Arena<T>) -> Children<'a, T> {
-        Children { token_iter: self.children_tokens(arena) }
+    pub fn insert_node_before<T>(self, arena: &mut Arena<T>, other: Token)
+        -> Result<(), Error> {
+        let parent = arena.get(self).and_then(|node| node.parent);
+        if is_frozen(arena, parent) { return Err(Error::FrozenNode) }
+        node_operation(self, arena, other, link_as_previous_sibling)
     }
 
-    /// Returns an iterator of mutable ancestor node references.
+    /// Creates a new node with the given data and sets as the next sibling of
+    /// the current node.
     ///
     /// # Panics:
     ///
@@ -817,367 +917,2931 @@ impl Token {
     ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = 1usize;
+    /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// let child_token = root_token.append(&mut arena, 2usize);
-    /// let grandchild_token = child_token.append(&mut arena, 3usize);
-    /// let great_grandchild_token = grandchild_token.append(&mut arena, 4usize);
-    /// let ggreat_grandchild_token = great_grandchild_token.append(&mut arena, 5usize);
-    ///
-    /// for x in ggreat_grandchild_token.ancestors_mut(&mut arena) {
-    ///     x.data += 2;
-    /// }
+    /// let child1 = root_token.append(&mut arena, "Romance");
+    /// let child3 = root_token.append(&mut arena, "Germanic");
+    /// child1.append(&mut arena, "French");
+    /// // insert betwern children 1 and 4
+    /// let child2 = child1.insert_after(&mut arena, "Celtic");
+    /// // insert after child 3
+    /// child3.insert_after(&mut arena, "Slavic");
     ///
-    /// let mut ancestors = ggreat_grandchild_token.ancestors(&arena);
-    /// assert_eq!(ancestors.next().unwrap().data, 6usize);
-    /// assert_eq!(ancestors.next().unwrap().data, 5usize);
-    /// assert_eq!(ancestors.next().unwrap().data, 4usize);
-    /// assert_eq!(ancestors.next().unwrap().data, 3usize);
-    /// assert!(ancestors.next().is_none());
+    /// let subtree: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data)
+    ///     .collect();
+    /// assert_eq!(&["Indo-European", "Romance", "French", "Celtic", "Germanic", "Slavic"],
+    ///            &subtree[..]);
     /// ```
-    pub fn ancestors_mut<'a, T>(self, arena: &'a mut Arena<T>)
-        -> AncestorsMut<'a, T> {
-        AncestorsMut {
-            arena: arena as *mut Arena<T>,
-            node_token: Some(self),
-            marker: PhantomData::default()
+    pub fn insert_after<T>(self, arena: &mut Arena<T>, data: T) -> Token {
+        match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => if is_frozen(arena, node.parent) {
+                panic!("Frozen node")
+            }
         }
+        let new_node_token = arena.new_node(data);
+        link_as_next_sibling(self, arena, new_node_token);
+        new_node_token
     }
 
-    /// Returns an iterator of mutable references of sibling nodes that follow
-    /// the current node.
+    /// Creates a copy of the given node (but not of its descendants) and inserts
+    /// it as the next sibling of the node. For a root node, the copy becomes
+    /// the root of a new tree instead. Returns the token of the copy.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// parent of the node is frozen.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
     ///
-    /// let root_data = 1usize;
+    /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, 2usize);
-    /// let second_child_token = root_token.append(&mut arena, 3usize);
-    /// root_token.append(&mut arena, 4usize);
-    /// root_token.append(&mut arena, 5usize);
-    ///
-    /// for x in second_child_token.following_siblings_mut(&mut arena) {
-    ///     x.data += 2;
-    /// }
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let copy = germanic.duplicate(&mut arena);
     ///
-    /// let mut children = root_token.children(&arena);
-    /// assert_eq!(children.next().unwrap().data, 2usize);
-    /// assert_eq!(children.next().unwrap().data, 3usize);
-    /// assert_eq!(children.next().unwrap().data, 6usize);
-    /// assert_eq!(children.next().unwrap().data, 7usize);
-    /// assert!(children.next().is_none());
+    /// assert_eq!(arena[germanic].next_sibling(), Some(copy));
+    /// assert_eq!(arena[copy].data, "Germanic");
+    /// assert!(copy.is_leaf(&arena));
     /// ```
-    pub fn following_siblings_mut<'a, T>(self, arena: &'a mut Arena<T>)
-        -> FollowingSiblingsMut<'a, T> {
-        let next_sibling = match arena.get(self) {
-            Some(n) => n.next_sibling,
-            None => panic!("Invalid token")
+    pub fn duplicate<T>(self, arena: &mut Arena<T>) -> Token where T: Clone {
+        let (data, parent) = match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => (node.data.clone(), node.parent)
         };
-        FollowingSiblingsMut {
-            arena: arena as *mut Arena<T>,
-            node_token: next_sibling,
-            marker: PhantomData::default()
+        match parent {
+            None => arena.new_node(data),
+            Some(_) => self.insert_after(arena, data)
         }
     }
 
-    /// Returns an iterator of mutable references of sibling nodes that precede
-    /// the current node.
+    /// Copies the subtree of the given node within the same arena and attaches
+    /// the copy as the last child of `new_parent`, or leaves it as a tree of
+    /// its own if `new_parent` is `None`. Returns the token of the root of the
+    /// copy. The original subtree is left untouched, so it can serve as a
+    /// template to stamp out any number of copies. To copy a subtree from a
+    /// different arena, use [`copy_and_append_subtree`] instead.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if either token does not correspond to a node in the arena or if
+    /// the new parent is frozen.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = 1usize;
+    /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, 2usize);
-    /// root_token.append(&mut arena, 3usize);
-    /// root_token.append(&mut arena, 4usize);
-    /// let fourth_child_token = root_token.append(&mut arena, 5usize);
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let west = root_token.append(&mut arena, "West");
     ///
-    /// for x in fourth_child_token.preceding_siblings_mut(&mut arena) {
-    ///     x.data += 5;
-    /// }
+    /// let copy = germanic.clone_subtree(&mut arena, Some(west));
+    /// assert_eq!(arena[copy].parent(), Some(west));
     ///
-    /// let mut children = root_token.children(&arena);
-    /// assert_eq!(children.next().unwrap().data, 7usize);
-    /// assert_eq!(children.next().unwrap().data, 8usize);
-    /// assert_eq!(children.next().unwrap().data, 9usize);
-    /// assert_eq!(children.next().unwrap().data, 5usize);
-    /// assert!(children.next().is_none());
+    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(subtree.next(), Some("Indo-European"));
+    /// assert_eq!(subtree.next(), Some("Germanic"));
+    /// assert_eq!(subtree.next(), Some("English"));
+    /// assert_eq!(subtree.next(), Some("West"));
+    /// assert_eq!(subtree.next(), Some("Germanic"));
+    /// assert_eq!(subtree.next(), Some("English"));
+    /// assert!(subtree.next().is_none());
     /// ```
-    pub fn preceding_siblings_mut<'a, T>(self, arena: &'a mut Arena<T>)
-        -> PrecedingSiblingsMut<'a, T> {
-        let previous_sibling = match arena.get(self) {
-            Some(n) => n.previous_sibling,
-            None => panic!("Invalid token")
-        };
-        PrecedingSiblingsMut {
-            arena: arena as *mut Arena<T>,
-            node_token: previous_sibling,
-            marker: PhantomData::default()
+    ///
+    /// [`copy_and_append_subtree`]: struct.Arena.html#method.copy_and_append_subtree
+    #[doc(alias = "copy_subtree")]
+    pub fn clone_subtree<T>(self, arena: &mut Arena<T>, new_parent: Option<Token>)
+        -> Token where T: Clone {
+        if let Some(p) = new_parent {
+            if arena.get(p).is_none() { panic!("Invalid token") }
+        }
+        // take a snapshot first since the new parent may be within the subtree
+        let nodes: Vec<(Token, Option<Token>, T)> =
+            self.subtree(arena, TraversalOrder::Pre)
+                .map(|node| (node.token, node.parent, node.data.clone()))
+                .collect();
+        // the path from the root of the subtree to the current node, as pairs
+        // of the original and the copy
+        let mut path: Vec<(Token, Token)> = Vec::new();
+        for (token, parent, data) in nodes {
+            while let Some(&(original, _)) = path.last() {
+                match Some(original) == parent {
+                    true => break,
+                    false => { path.pop(); }
+                }
+            }
+            let new_token = match path.last() {
+                Some(&(_, copy)) => copy.append(arena, data),
+                None => match new_parent {
+                    Some(p) => p.append(arena, data),
+                    None => arena.new_node(data)
+                }
+            };
+            path.push((token, new_token));
         }
+        path[0].1  // the root of the copy is never popped off
     }
 
-    /// Returns an iterator of mutable references of child nodes in the order of
-    /// insertion.
+    /// Attaches a different tree in the arena to a node. Returns error if the
+    /// "root node" of the other tree is not really a root node (as in it
+    /// already has a parent and/or siblings). To attach a tree from a different
+    /// arena, use [`copy_and_append_subtree`] instead.
+    ///
+    /// **Note**: for performance reasons, this operation does not check whether
+    /// the "self" node is in fact a descendant of the other tree. A cyclic
+    /// graph may result.
     ///
     /// # Panics:
     ///
     /// Panics if the token does not correspond to a node in the arena.
     ///
     /// # Examples:
-    ///
     /// ```
     /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = 1usize;
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    /// // root node that we will attach subtrees to
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, 2usize);
-    /// root_token.append(&mut arena, 3usize);
-    /// let third_child_token = root_token.append(&mut arena, 4usize);
-    /// root_token.append(&mut arena, 5usize);
-    /// let grandchild = third_child_token.append(&mut arena, 10usize);
+    /// // the Germanic branch
+    /// let germanic = arena.new_node("Germanic");
+    /// let west = germanic.append(&mut arena, "West");
+    /// let scots = west.append(&mut arena, "Scots");
+    /// let english = west.append(&mut arena, "English");
     ///
-    /// for x in root_token.children_mut(&mut arena) {
-    ///     x.data += 10;
-    /// }
+    /// // the Romance branch
+    /// let romance = arena.new_node("Romance");
+    /// let french = romance.append(&mut arena, "French");
+    /// let italian = romance.append(&mut arena, "Italian");
     ///
-    /// let mut children = root_token.children(&arena);
-    /// assert_eq!(children.next().unwrap().data, 12);
-    /// assert_eq!(children.next().unwrap().data, 13);
-    /// assert_eq!(children.next().unwrap().data, 14);
-    /// assert_eq!(children.next().unwrap().data, 15);
-    /// assert_eq!(arena.get(grandchild).unwrap().data, 10);
-    /// assert!(children.next().is_none());
+    /// // attach subtrees
+    /// root.append_node(&mut arena, romance).unwrap();
+    /// root.append_node(&mut arena, germanic).unwrap();
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert_eq!(iter.next(), Some("French"));
+    /// assert_eq!(iter.next(), Some("Italian"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("West"));
+    /// assert_eq!(iter.next(), Some("Scots"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert!(iter.next().is_none())
     /// ```
-    pub fn children_mut<'a, T>(self, arena: &'a mut Arena<T>)
-        -> ChildrenMut<'a, T> {
-        let first_child = match arena.get(self) {
-            Some(n) => n.first_child,
-            None => panic!("Invalid token")
-        };
-        ChildrenMut {
-            arena: arena as *mut Arena<T>,
-            node_token: first_child,
-            marker: PhantomData::default()
-        }
+    ///
+    /// [`copy_and_append_subtree`]: struct.Arena.html#method.copy_and_append_subtree
+    pub fn append_node<T>(self, arena: &mut Arena<T>, other: Self)
+        -> Result<(), Error> {
+        if is_frozen(arena, Some(self)) { return Err(Error::FrozenNode) }
+        node_operation(self, arena, other, link_as_last_child)
     }
 
-    /// Returns an iterator of tokens of subtree nodes of the given node.
+    /// Attaches a free-standing tree in the arena, such as one split off with
+    /// [`detach`], as the last child of the given node without copying it.
+    /// Like [`append_node`] but also makes sure that no cycle results. Returns
+    /// error if the given node is within the other tree, if the "root node" of
+    /// the other tree is not really a root node or if the given node is
+    /// frozen.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if either token does not correspond to a node in the arena.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = "Indo-European";
-    /// let (mut arena, root_token) = Arena::with_data(root_data);
-    ///
-    /// let first_child = root_token.append(&mut arena, "Romance");
-    /// let second_child = root_token.append(&mut arena, "Germanic");
-    /// let third_child = root_token.append(&mut arena, "Slavic");
-    /// let first_grandchild = second_child.append(&mut arena, "English");
-    /// let second_grandchild = second_child.append(&mut arena, "Icelandic");
-    /// let fourth_child = root_token.append(&mut arena, "Celtic");
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West");
+    /// let english = west.append(&mut arena, "English");
+    /// let romance = root.append(&mut arena, "Romance");
     ///
-    /// let mut subtree = root_token.subtree_tokens(&arena, TraversalOrder::Pre);
-    /// assert_eq!(subtree.next(), Some(root_token));
-    /// assert_eq!(subtree.next(), Some(first_child));
-    /// assert_eq!(subtree.next(), Some(second_child));
-    /// assert_eq!(subtree.next(), Some(first_grandchild));
-    /// assert_eq!(subtree.next(), Some(second_grandchild));
-    /// assert_eq!(subtree.next(), Some(third_child));
-    /// assert_eq!(subtree.next(), Some(fourth_child));
-    /// assert!(subtree.next().is_none());
+    /// west.detach(&mut arena);
+    /// assert!(english.graft(&mut arena, west).is_err());  // cycle
+    /// romance.graft(&mut arena, west).unwrap();
+    /// assert_eq!(arena[west].parent(), Some(romance));
     ///
-    /// let mut subtree = second_grandchild.subtree_tokens(&arena, TraversalOrder::Pre);
-    /// assert_eq!(subtree.next(), Some(second_grandchild));
-    /// assert!(subtree.next().is_none());
+    /// // cannot graft a node that still has a parent
+    /// assert!(germanic.graft(&mut arena, romance).is_err());
     /// ```
-    pub fn subtree_tokens<'a, T>(self, arena: &'a Arena<T>, order: TraversalOrder)
-        -> SubtreeTokens<'a, T> {
-        let preord_tokens_next = |iter: &mut SubtreeTokens<T>| 
-            depth_first_tokens_next(iter, preorder_next);
-        let postord_tokens_next = |iter: &mut SubtreeTokens<T>| 
-            depth_first_tokens_next(iter, postorder_next);
-        match order {
-            TraversalOrder::Pre => SubtreeTokens {
-                arena,
-                subtree_root: self,
-                node_token: Some(self),
-                branch: Branch::Child,
-                curr_level: VecDeque::new(),  // unused field
-                next_level: VecDeque::new(),  // unused field
-                next: preord_tokens_next
-            },
-            TraversalOrder::Post => {
-                let (node_token, branch) =
-                    postorder_next(self, self, Branch::Child, arena);
-                SubtreeTokens {
-                    arena,
-                    subtree_root: self,
-                    node_token,
-                    branch,
-                    curr_level: VecDeque::new(),  // unused field
-                    next_level: VecDeque::new(),  // unused field
-                    next: postord_tokens_next
-                }
-            },
-            TraversalOrder::Level => {
-                SubtreeTokens {
-                    arena,
-                    subtree_root: self,  // unused field
-                    node_token: None,  // unused field
-                    branch: Branch::None,  // unused field
-                    curr_level: std::iter::once(self).collect(),
-                    next_level: VecDeque::new(),
-                    next: breadth_first_tokens_next
-                }
-            }
+    ///
+    /// [`detach`]: struct.Token.html#method.detach
+    /// [`append_node`]: struct.Token.html#method.append_node
+    pub fn graft<T>(self, arena: &mut Arena<T>, child_root: Token)
+        -> Result<(), Error> {
+        if arena.get(child_root).is_none() { panic!("Invalid token") }
+        if is_frozen(arena, Some(self)) { return Err(Error::FrozenNode) }
+        if child_root == self
+            || self.ancestors_tokens(arena).any(|t| t == child_root) {
+            return Err(Error::CyclicGraph)
         }
+        node_operation(self, arena, child_root, link_as_last_child)
     }
 
-    /// Returns an iterator of references of subtree nodes of the given node.
+    /// Reorders the children of the given node with the given comparator
+    /// function. The sort is stable.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
     /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, "Romance");
+    /// root_token.append(&mut arena, "Slavic");
     /// root_token.append(&mut arena, "Germanic");
-    /// let third_child = root_token.append(&mut arena, "Slavic");
-    /// root_token.append(&mut arena, "Celtic");
-    /// third_child.append(&mut arena, "Polish");
-    /// third_child.append(&mut arena, "Slovakian");
+    /// root_token.append(&mut arena, "Romance");
     ///
-    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre);
-    /// assert_eq!(subtree.next().unwrap().data, "Indo-European");
-    /// assert_eq!(subtree.next().unwrap().data, "Romance");
-    /// assert_eq!(subtree.next().unwrap().data, "Germanic");
-    /// assert_eq!(subtree.next().unwrap().data, "Slavic");
-    /// assert_eq!(subtree.next().unwrap().data, "Polish");
-    /// assert_eq!(subtree.next().unwrap().data, "Slovakian");
-    /// assert_eq!(subtree.next().unwrap().data, "Celtic");
-    /// assert!(subtree.next().is_none());
+    /// // sort by length, then alphabetically
+    /// root_token.sort_children_by(&mut arena, |a, b| {
+    ///     a.len().cmp(&b.len()).then(a.cmp(b))
+    /// });
+    ///
+    /// let mut children = root_token.children(&arena).map(|x| x.data);
+    /// assert_eq!(children.next(), Some("Slavic"));
+    /// assert_eq!(children.next(), Some("Romance"));
+    /// assert_eq!(children.next(), Some("Germanic"));
+    /// assert!(children.next().is_none());
     /// ```
-    pub fn subtree<'a, T>(self, arena: &'a Arena<T>, order: TraversalOrder)
-        -> Subtree<'a, T> {
-        Subtree {
-            arena,
-            iter: self.subtree_tokens(arena, order)
-        }
+    pub fn sort_children_by<T, F>(self, arena: &mut Arena<T>, mut cmp: F)
+        where F: FnMut(&T, &T) -> Ordering {
+        if is_frozen(arena, Some(self)) { panic!("Frozen node") }
+        let mut children: Vec<Token> = self.children_tokens(arena).collect();
+        children.sort_by(|&a, &b| cmp(&arena[a].data, &arena[b].data));
+        relink_children(self, arena, &children);
     }
 
-    /// Returns an iterator of mutable references of subtree nodes of the given
-    /// node.
+    /// Reorders the children of the given node by their data. The sort is
+    /// stable.
     ///
     /// # Panics:
     ///
-    /// Panics if the token does not correspond to a node in the arena.
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
     ///
     /// # Examples:
     ///
     /// ```
     /// use atree::Arena;
-    /// use atree::iter::TraversalOrder;
     ///
-    /// let root_data = 1usize;
+    /// let root_data = "Indo-European";
     /// let (mut arena, root_token) = Arena::with_data(root_data);
     ///
-    /// root_token.append(&mut arena, 2usize);
-    /// root_token.append(&mut arena, 3usize);
-    /// let third_child = root_token.append(&mut arena, 4usize);
-    /// root_token.append(&mut arena, 5usize);
-    /// third_child.append(&mut arena, 10usize);
-    /// third_child.append(&mut arena, 20usize);
-    ///
-    /// for x in root_token.subtree_mut(&mut arena, TraversalOrder::Pre) {
-    ///     x.data += 100;
-    /// }
+    /// root_token.append(&mut arena, "Slavic");
+    /// root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Romance");
+    /// root_token.sort_children(&mut arena);
     ///
-    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre);
-    /// assert_eq!(subtree.next().unwrap().data, 101);
-    /// assert_eq!(subtree.next().unwrap().data, 102);
-    /// assert_eq!(subtree.next().unwrap().data, 103);
-    /// assert_eq!(subtree.next().unwrap().data, 104);
-    /// assert_eq!(subtree.next().unwrap().data, 110);
-    /// assert_eq!(subtree.next().unwrap().data, 120);
-    /// assert_eq!(subtree.next().unwrap().data, 105);
-    /// assert!(subtree.next().is_none());
+    /// let mut children = root_token.children(&arena).map(|x| x.data);
+    /// assert_eq!(children.next(), Some("Germanic"));
+    /// assert_eq!(children.next(), Some("Romance"));
+    /// assert_eq!(children.next(), Some("Slavic"));
+    /// assert!(children.next().is_none());
     /// ```
-    pub fn subtree_mut<'a, T>(self, arena: &'a mut Arena<T>, order: TraversalOrder)
-        -> SubtreeMut<'a, T> {
-        SubtreeMut {
-            arena: arena as *mut Arena<T>,
-            iter: self.subtree_tokens(arena, order),
-            marker: PhantomData::default()
-        }
+    pub fn sort_children<T>(self, arena: &mut Arena<T>) where T: Ord {
+        self.sort_children_by(arena, |a, b| a.cmp(b))
     }
 
-    /// Removes all descendants of the current node.
-    pub (crate) fn remove_descendants<T>(self, arena: &mut Arena<T>) {
-        // This will not silently fail since postorder_next will panic if self
-        // isn't valid.  This won't do anything if self has no descendants, but
-        // that's the intended behavior.
-        if let (Some(mut token), mut branch) =
-            postorder_next(self, self, Branch::Child, arena) {
-            while branch != Branch::None {
-                let (t, b) = postorder_next(token, self, branch, arena);
-                arena.allocator.remove(token);  // should not fail (not here anyway)
-                token = t.unwrap();
-                branch = b;
-            }
-            arena[self].first_child = None;
-        }
+    /// Cyclically shifts the children of the given node by `k` positions, to
+    /// the left for positive `k` and to the right for negative `k`. The shift
+    /// wraps around modulo the number of children.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(0usize);
+    /// root_token.append_children(&mut arena, 1..6usize);
+    ///
+    /// root_token.rotate_children(&mut arena, 2);
+    /// let children: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+    /// assert_eq!(children, vec![3, 4, 5, 1, 2]);
+    ///
+    /// root_token.rotate_children(&mut arena, -1);
+    /// let children: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+    /// assert_eq!(children, vec![2, 3, 4, 5, 1]);
+    /// ```
+    pub fn rotate_children<T>(self, arena: &mut Arena<T>, k: isize) {
+        if is_frozen(arena, Some(self)) { panic!("Frozen node") }
+        let mut children: Vec<Token> = self.children_tokens(arena).collect();
+        if children.len() < 2 { return }
+        let shift = k.rem_euclid(children.len() as isize) as usize;
+        children.rotate_left(shift);
+        relink_children(self, arena, &children);
     }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
 
-    #[test]
-    #[allow(clippy::cognitive_complexity)]
-    fn replace_node() {
-        // root node that we will attach subtrees to
-        let root_data = "Indo-European";
-        let (mut arena, root) = Arena::with_data(root_data);
-       
-        // the Germanic branch
-        let germanic = root.append(&mut arena, "Germanic");
-        let west = germanic.append(&mut arena, "West");
-        west.append(&mut arena, "Scots");
+    /// Detaches the given node and its descendants into its own tree while
+    /// keeping it in the same arena. To detach and allocate the subtree into its
+    /// own arena, use [`split_at`] instead. The detached tree can be attached
+    /// elsewhere later on with [`graft`], which makes for cut and paste.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// parent of the node is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// // root node that we will attach subtrees to
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root) = Arena::with_data(root_data);
+    ///
+    /// // the Germanic branch
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West");
+    /// let scots = west.append(&mut arena, "Scots");
+    /// let english = west.append(&mut arena, "English");
+    ///
+    /// // detach the west branch from the main tree
+    /// west.detach(&mut arena);
+    ///
+    /// // the west branch is gone from the original tree
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert!(iter.next().is_none());
+    ///
+    /// // it exists on its own
+    /// let mut iter = west.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("West"));
+    /// assert_eq!(iter.next(), Some("Scots"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    ///
+    /// [`split_at`]: struct.Arena.html#method.split_at
+    /// [`graft`]: struct.Token.html#method.graft
+    #[doc(alias = "cut")]
+    pub fn detach<T>(self, arena: &mut Arena<T>) {
+        let (parent, previous_sibling, next_sibling) = match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => (node.parent, node.previous_sibling, node.next_sibling)
+        };
+        if is_frozen(arena, parent) { panic!("Frozen node") }
+        let node = arena.node_mut(self);
+        node.parent = None;
+        node.previous_sibling = None;
+        node.next_sibling = None;
+
+        match previous_sibling {
+            Some(token) => match arena.allocator.get_mut(token) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.next_sibling = next_sibling
+            },
+            None => if let Some(token) = parent {
+                match arena.allocator.get_mut(token) {
+                    None => panic!("Corrupt arena"),
+                    Some(n) => n.first_child = next_sibling
+                }
+            }
+        }
+
+        match next_sibling {
+            Some(token) => match arena.allocator.get_mut(token) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.previous_sibling = previous_sibling
+            },
+            None => if let Some(token) = parent {
+                match arena.allocator.get_mut(token) {
+                    None => panic!("Corrupt arena"),
+                    Some(n) => n.last_child = previous_sibling
+                }
+            }
+        }
+    }
+
+    /// Moves the given node and its descendants into a new arena of their own,
+    /// freeing their cells in the original arena. This is the same as
+    /// [`Arena::split_off`] and the inverse of [`Arena::transplant`]. Returns
+    /// the new arena along with the token of the root in the new arena.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if
+    /// any of the nodes in the subtree or the parent of the node is frozen.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    ///
+    /// let (germanic_arena, germanic_root) = germanic.split_off(&mut arena);
+    /// assert_eq!(arena.node_count(), 1);
+    /// assert_eq!(germanic_arena.node_count(), 2);
+    /// assert_eq!(germanic_arena[germanic_root].data, "Germanic");
+    /// ```
+    ///
+    /// [`Arena::split_off`]: struct.Arena.html#method.split_off
+    /// [`Arena::transplant`]: struct.Arena.html#method.transplant
+    pub fn split_off<T>(self, arena: &mut Arena<T>) -> (Arena<T>, Token) {
+        arena.split_off(self)
+    }
+
+    /// Moves the given node along with its descendants to the end of the
+    /// children of a new parent. Returns error if the new parent is within the
+    /// subtree of the given node since a cyclic graph would result, or if
+    /// either the old or the new parent is frozen.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let romance = root.append(&mut arena, "Romance");
+    /// let french = germanic.append(&mut arena, "French");
+    /// french.append(&mut arena, "Picard");
+    ///
+    /// // French is not a Germanic language
+    /// french.move_to(&mut arena, romance).unwrap();
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert_eq!(iter.next(), Some("French"));
+    /// assert_eq!(iter.next(), Some("Picard"));
+    /// assert!(iter.next().is_none());
+    ///
+    /// // cannot move a node under its own descendant
+    /// assert!(romance.move_to(&mut arena, french).is_err());
+    /// ```
+    #[doc(alias = "reparent")]
+    #[doc(alias = "move_subtree")]
+    pub fn move_to<T>(self, arena: &mut Arena<T>, new_parent: Token)
+        -> Result<(), Error> {
+        let parent = match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.parent
+        };
+        if is_frozen(arena, parent) || is_frozen(arena, Some(new_parent)) {
+            return Err(Error::FrozenNode)
+        }
+        if new_parent == self
+            || new_parent.ancestors_tokens(arena).any(|t| t == self) {
+            return Err(Error::CyclicGraph)
+        }
+        self.detach(arena);
+        link_as_last_child(new_parent, arena, self);
+        Ok(())
+    }
+
+    /// Replaces the data of the given node and returns the old data. The node
+    /// keeps its position and its descendants.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// node is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let child = root_token.append(&mut arena, "Germanic");
+    /// child.append(&mut arena, "English");
+    ///
+    /// assert_eq!(child.replace_data(&mut arena, "West Germanic"), "Germanic");
+    /// assert_eq!(arena[child].data, "West Germanic");
+    /// assert_eq!(arena[child].parent(), Some(root_token));
+    /// assert_eq!(child.children(&arena).count(), 1);
+    /// ```
+    pub fn replace_data<T>(self, arena: &mut Arena<T>, data: T) -> T {
+        core::mem::replace(&mut arena[self].data, data)
+    }
+
+    /// Replace the subtree of self with the subtree of other. Does not remove
+    /// self or its descendants but simply makes it a standalone tree within the
+    /// arena.
+    ///
+    /// **Note**: for performance reasons, this operation does not check whether
+    /// the "other" node is in fact a descendant of the parent tree of self. A
+    /// cyclic graph may result.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// // root node that we will attach subtrees to
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root) = Arena::with_data(root_data);
+    ///
+    /// // the Germanic branch
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West");
+    /// let scots = west.append(&mut arena, "Scots");
+    /// let english = west.append(&mut arena, "English");
+    ///
+    /// // the slavic branch
+    /// let slavic = root.append(&mut arena, "Slavic");
+    /// let polish = slavic.append(&mut arena, "Polish");
+    /// let russian = slavic.append(&mut arena, "Russian");
+    ///
+    /// // the Romance branch
+    /// let romance = arena.new_node("Romance");
+    /// let french = romance.append(&mut arena, "French");
+    /// let italian = romance.append(&mut arena, "Italian");
+    ///
+    /// // replace_node germanic with romance
+    /// germanic.replace_node(&mut arena, romance).unwrap();
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert_eq!(iter.next(), Some("French"));
+    /// assert_eq!(iter.next(), Some("Italian"));
+    /// assert_eq!(iter.next(), Some("Slavic"));
+    /// assert_eq!(iter.next(), Some("Polish"));
+    /// assert_eq!(iter.next(), Some("Russian"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    pub fn replace_node<T>(self, arena: &mut Arena<T>, other: Token)
+        -> Result<(), Error> {
+        let self_node = match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(n) => n
+        };
+        let parent = self_node.parent;
+        let previous_sibling = self_node.previous_sibling;
+        let next_sibling = self_node.next_sibling;
+        if is_frozen(arena, parent) { return Err(Error::FrozenNode) }
+
+        let other_node = match arena.allocator.get_mut(other) {
+            None => panic!("Invalid token"),
+            Some(n) => n
+        };
+
+        // check that the other node is really a root node of its own
+        match (other_node.previous_sibling,
+               other_node.next_sibling,
+               other_node.parent) {
+            (None, None, None) => (),
+            _ => return Err(Error::NotARootNode)
+        }
+
+        // replace_node the self node with the other node
+        other_node.parent = parent;
+        other_node.next_sibling = next_sibling;
+        other_node.previous_sibling = previous_sibling;
+
+        let self_node = arena.node_mut(self);  // indexability has been checked
+        self_node.parent = None;
+        self_node.previous_sibling = None;
+        self_node.next_sibling = None;
+
+        // update previous_sibling, next_sibling and parent of the self node
+        match previous_sibling {
+            Some(sibling) => match arena.allocator.get_mut(sibling) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.next_sibling = Some(other)
+            },
+            None => if let Some(p) = parent {
+                match arena.allocator.get_mut(p) {
+                    None => panic!("Corrupt arena"),
+                    Some(node) => node.first_child = Some(other)
+                }
+            }
+        }
+
+        match next_sibling {
+            Some(sibling) => match arena.allocator.get_mut(sibling) {
+                None => panic!("Corrupt arena"),
+                Some(node) => node.previous_sibling = Some(other)
+            },
+            None => if let Some(p) = parent {
+                match arena.allocator.get_mut(p) {
+                    None => panic!("Corrupt arena"),
+                    Some(node) => node.last_child = Some(other)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the subtree of the given node with the tree rooted at
+    /// `new_root`, which takes its exact place among the siblings. Unlike
+    /// [`replace_node`], the old subtree is removed from the arena and its data
+    /// is returned in pre-order. Returns error if `new_root` is not a root
+    /// node, if the given node is part of the tree of `new_root`, or if the
+    /// parent of the given node or any node in its subtree is frozen.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Proto-Germanic");
+    /// germanic.append(&mut arena, "Old English");
+    /// root.append(&mut arena, "Romance");
+    ///
+    /// let new_germanic = arena.new_node("Germanic");
+    /// new_germanic.append(&mut arena, "English");
+    ///
+    /// let removed = germanic.replace_subtree(&mut arena, new_germanic).unwrap();
+    /// assert_eq!(removed, ["Proto-Germanic", "Old English"]);
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    ///
+    /// [`replace_node`]: struct.Token.html#method.replace_node
+    #[doc(alias = "replace_with_subtree")]
+    pub fn replace_subtree<T>(self, arena: &mut Arena<T>, new_root: Token)
+        -> Result<Vec<T>, Error> {
+        if arena.get(new_root).is_none() { panic!("Invalid token") }
+        if new_root == self
+            || self.ancestors_tokens(arena).any(|t| t == new_root) {
+            return Err(Error::CyclicGraph)
+        }
+        if self.subtree(arena, TraversalOrder::Pre).any(|node| node.frozen) {
+            return Err(Error::FrozenNode)
+        }
+        self.replace_node(arena, new_root)?;
+        Ok(arena.drain_subtree(self).collect())
+    }
+
+    /// Wraps the given node in a new node with the given data. The new node
+    /// takes the place of the given node among its siblings and adopts it as
+    /// its only child. If the given node is a root, the new node becomes the
+    /// new root. Returns the token of the new node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if the
+    /// parent of the node is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let english = root.append(&mut arena, "English");
+    /// root.append(&mut arena, "Romance");
+    ///
+    /// let germanic = english.wrap(&mut arena, "Germanic");
+    /// assert_eq!(arena[english].parent(), Some(germanic));
+    ///
+    /// let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("Indo-European"));
+    /// assert_eq!(iter.next(), Some("Germanic"));
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    pub fn wrap<T>(self, arena: &mut Arena<T>, data: T) -> Token {
+        let parent = match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node.parent
+        };
+        if is_frozen(arena, parent) { panic!("Frozen node") }
+        let wrapper = arena.new_node(data);
+        // the wrapper is a fresh root and the parent is not frozen so this
+        // cannot fail
+        self.replace_node(arena, wrapper).unwrap();
+        link_as_last_child(wrapper, arena, self);
+        wrapper
+    }
+
+    /// Removes the given node but keeps its children, moving them into its
+    /// place among its siblings in their original order. This is the inverse
+    /// of [`wrap`]. If the node is a root, each of its children becomes the
+    /// root of a separate tree. Returns the data of the removed node, or
+    /// `None` if the token does not correspond to a node in the arena.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the node or its parent is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root) = Arena::with_data("Indo-European");
+    /// let germanic = root.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// germanic.append(&mut arena, "Scots");
+    /// root.append(&mut arena, "Romance");
+    ///
+    /// assert_eq!(germanic.unwrap_node(&mut arena), Some("Germanic"));
+    /// assert_eq!(germanic.unwrap_node(&mut arena), None);
+    ///
+    /// let mut iter = root.children(&arena).map(|x| x.data);
+    /// assert_eq!(iter.next(), Some("English"));
+    /// assert_eq!(iter.next(), Some("Scots"));
+    /// assert_eq!(iter.next(), Some("Romance"));
+    /// assert!(iter.next().is_none());
+    /// ```
+    ///
+    /// [`wrap`]: struct.Token.html#method.wrap
+    pub fn unwrap_node<T>(self, arena: &mut Arena<T>) -> Option<T> {
+        match arena.contains(self) {
+            true => Some(arena.splice_out(self)),
+            false => None
+        }
+    }
+
+    /// Returns an iterator of tokens of ancestor nodes.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let child_token = root_token.append(&mut arena, "Germanic");
+    /// let grandchild_token = child_token.append(&mut arena, "English");
+    /// let mut ancestors_tokens = grandchild_token.ancestors_tokens(&arena);
+    ///
+    /// assert_eq!(ancestors_tokens.next(), Some(child_token));
+    /// assert_eq!(ancestors_tokens.next(), Some(root_token));
+    /// assert!(ancestors_tokens.next().is_none());
+    /// ```
+    pub fn ancestors_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> AncestorTokens<'a, T> {
+        let parent = match arena.get(self) {
+            Some(n) => n.parent,
+            None => panic!("Invalid token")
+        };
+        AncestorTokens { arena, node_token: parent }
+    }
+
+    /// Returns an iterator of tokens of siblings preceding the current node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let first_child_token = root_token.append(&mut arena, "Germanic");
+    /// let second_child_token = root_token.append(&mut arena, "Romance");
+    /// let third_child_token = root_token.append(&mut arena, "Slavic");
+    /// root_token.append(&mut arena, "Hellenic");
+    ///
+    /// let mut sibling_tokens = third_child_token.preceding_siblings_tokens(&arena);
+    /// assert_eq!(sibling_tokens.next(), Some(second_child_token));
+    /// assert_eq!(sibling_tokens.next(), Some(first_child_token));
+    /// assert!(sibling_tokens.next().is_none());
+    /// ```
+    pub fn preceding_siblings_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> PrecedingSiblingTokens<'a, T> {
+        let previous_sibling = match arena.get(self) {
+            Some(n) => n.previous_sibling,
+            None => panic!("Invalid token")
+        };
+        PrecedingSiblingTokens { arena, node_token: previous_sibling }
+    }
+
+    /// Returns an iterator of tokens of siblings following the current node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, "Romance");
+    /// let second_child_token = root_token.append(&mut arena, "Germanic");
+    /// let third_child_token = root_token.append(&mut arena, "Slavic");
+    /// let fourth_child_token = root_token.append(&mut arena, "Hellenic");
+    ///
+    /// let mut sibling_tokens = second_child_token.following_siblings_tokens(&arena);
+    /// assert_eq!(sibling_tokens.next(), Some(third_child_token));
+    /// assert_eq!(sibling_tokens.next(), Some(fourth_child_token));
+    /// assert!(sibling_tokens.next().is_none());
+    /// ```
+    pub fn following_siblings_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> FollowingSiblingTokens<'a, T> {
+        let next_sibling = match arena.get(self) {
+            Some(n) => n.next_sibling,
+            None => panic!("Invalid token")
+        };
+        FollowingSiblingTokens { arena, node_token: next_sibling }
+    }
+
+    /// Returns an iterator of tokens of child nodes in the order of insertion.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let first_child_token = root_token.append(&mut arena, "Romance");
+    /// let second_child_token = root_token.append(&mut arena, "Germanic");
+    /// let third_child_token = root_token.append(&mut arena, "Slavic");
+    /// let fourth_child_token = root_token.append(&mut arena, "Hellenic");
+    ///
+    /// let mut children_tokens = root_token.children_tokens(&arena);
+    /// assert_eq!(children_tokens.next(), Some(first_child_token));
+    /// assert_eq!(children_tokens.next(), Some(second_child_token));
+    /// assert_eq!(children_tokens.next(), Some(third_child_token));
+    /// assert_eq!(children_tokens.next(), Some(fourth_child_token));
+    /// assert!(children_tokens.next().is_none());
+    /// ```
+    pub fn children_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> ChildrenTokens<'a, T> {
+        let (first_child, last_child) = match arena.get(self) {
+            Some(n) => (n.first_child, n.last_child),
+            None => panic!("Invalid token")
+        };
+        ChildrenTokens { arena, node_token: first_child, back_token: last_child }
+    }
+
+    /// Returns an iterator of tokens of child nodes in the reverse order of
+    /// insertion.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let first_child_token = root_token.append(&mut arena, "Romance");
+    /// let second_child_token = root_token.append(&mut arena, "Germanic");
+    /// let third_child_token = root_token.append(&mut arena, "Slavic");
+    ///
+    /// let mut children_tokens = root_token.children_rev_tokens(&arena);
+    /// assert_eq!(children_tokens.next(), Some(third_child_token));
+    /// assert_eq!(children_tokens.next(), Some(second_child_token));
+    /// assert_eq!(children_tokens.next(), Some(first_child_token));
+    /// assert!(children_tokens.next().is_none());
+    /// ```
+    pub fn children_rev_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> ChildrenRevTokens<'a, T> {
+        let last_child = match arena.get(self) {
+            Some(n) => n.last_child,
+            None => panic!("Invalid token")
+        };
+        ChildrenRevTokens { arena, node_token: last_child }
+    }
+
+    /// Returns an iterator of references of ancestor nodes.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let child_token = root_token.append(&mut arena, "Germanic");
+    /// let grandchild_token = child_token.append(&mut arena, "Swedish");
+    /// let mut ancestors = grandchild_token.ancestors(&arena);
+    ///
+    /// assert_eq!(ancestors.next().unwrap().data, "Germanic");
+    /// assert_eq!(ancestors.next().unwrap().data, "Indo-European");
+    /// assert!(ancestors.next().is_none());
+    /// ```
+    pub fn ancestors<'a, T>(self, arena: &'a Arena<T>) -> Ancestors<'a, T> {
+        Ancestors { token_iter: self.ancestors_tokens(arena) }
+    }
+
+    /// Returns an iterator of tokens of ancestor nodes, starting from the
+    /// parent and stopping right before the `stop` node, which is not
+    /// yielded. If `stop` is not an ancestor of the given node the iterator
+    /// goes all the way up to the root like [`ancestors_tokens`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West Germanic");
+    /// let english = west.append(&mut arena, "English");
+    ///
+    /// let mut ancestors = english.ancestors_until_tokens(&arena, root_token);
+    /// assert_eq!(ancestors.next(), Some(west));
+    /// assert_eq!(ancestors.next(), Some(germanic));
+    /// assert!(ancestors.next().is_none());
+    /// ```
+    ///
+    /// [`ancestors_tokens`]: struct.Token.html#method.ancestors_tokens
+    pub fn ancestors_until_tokens<'a, T>(self, arena: &'a Arena<T>, stop: Token)
+        -> AncestorsUntilTokens<'a, T> {
+        let parent = match arena.get(self) {
+            Some(n) => n.parent,
+            None => panic!("Invalid token")
+        };
+        AncestorsUntilTokens { arena, node_token: parent, stop }
+    }
+
+    /// Returns an iterator of references of ancestor nodes, starting from the
+    /// parent and stopping right before the `stop` node, which is not
+    /// yielded. If `stop` is not an ancestor of the given node the iterator
+    /// goes all the way up to the root like [`ancestors`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let west = germanic.append(&mut arena, "West Germanic");
+    /// let english = west.append(&mut arena, "English");
+    ///
+    /// let mut ancestors = english.ancestors_until(&arena, germanic)
+    ///     .map(|x| x.data);
+    /// assert_eq!(ancestors.next(), Some("West Germanic"));
+    /// assert!(ancestors.next().is_none());
+    /// ```
+    ///
+    /// [`ancestors`]: struct.Token.html#method.ancestors
+    pub fn ancestors_until<'a, T>(self, arena: &'a Arena<T>, stop: Token)
+        -> AncestorsUntil<'a, T> {
+        AncestorsUntil { token_iter: self.ancestors_until_tokens(arena, stop) }
+    }
+
+    /// Returns the token of the nearest ancestor whose data satisfies the
+    /// predicate, walking up from the parent of the given node. The node
+    /// itself is not considered. Returns `None` if no ancestor matches.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("family");
+    /// let germanic = root_token.append(&mut arena, "branch");
+    /// let west = germanic.append(&mut arena, "group");
+    /// let english = west.append(&mut arena, "language");
+    ///
+    /// assert_eq!(english.nearest_ancestor(&arena, |x| *x == "branch"),
+    ///            Some(germanic));
+    /// assert_eq!(english.nearest_ancestor(&arena, |x| *x == "language"), None);
+    /// ```
+    #[doc(alias = "climb_while")]
+    pub fn nearest_ancestor<T, F>(self, arena: &Arena<T>, pred: F)
+        -> Option<Token> where F: Fn(&T) -> bool {
+        self.ancestors_tokens(arena).find(|&t| pred(&arena[t].data))
+    }
+
+    /// Returns an iterator of tokens of the ancestors that the given nodes
+    /// have in common, starting from the lowest common ancestor and going up
+    /// to the root. A node counts as an ancestor of itself here, so if one
+    /// node is an ancestor of the other the iterator starts at it. The
+    /// iterator is empty if the nodes belong to different trees.
+    ///
+    /// For many queries on a tree that does not change, see
+    /// [`DepthIndex::lca`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let scots = germanic.append(&mut arena, "Scots");
+    ///
+    /// let mut common = english.common_ancestors(&arena, scots);
+    /// assert_eq!(common.next(), Some(germanic));
+    /// assert_eq!(common.next(), Some(root_token));
+    /// assert!(common.next().is_none());
+    /// ```
+    ///
+    /// [`DepthIndex::lca`]: struct.DepthIndex.html#method.lca
+    pub fn common_ancestors<'a, T>(self, arena: &'a Arena<T>, other: Token)
+        -> AncestorTokens<'a, T> {
+        let depth = |token: Token| token.ancestors_tokens(arena).count();
+        let (mut a, mut b) = (self, other);
+        let (depth_a, depth_b) = (depth(a), depth(b));
+        for _ in depth_b..depth_a { a = arena[a].parent.unwrap() }
+        for _ in depth_a..depth_b { b = arena[b].parent.unwrap() }
+        // both are at the same depth now so they reach the roots together
+        let lca = loop {
+            match a == b {
+                true => break Some(a),
+                false => match (arena[a].parent, arena[b].parent) {
+                    (Some(p), Some(q)) => { a = p; b = q; },
+                    _ => break None
+                }
+            }
+        };
+        AncestorTokens { arena, node_token: lca }
+    }
+
+    /// Returns the steps it takes to walk through the tree from the current
+    /// node to the given node: first up to their lowest common ancestor, then
+    /// down to the target. Returns `None` if the nodes belong to different
+    /// trees. The route from a node to itself is empty.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::{Arena, Move};
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// let route = english.route_to(&arena, french).unwrap();
+    /// assert_eq!(route, vec![Move::Up, Move::Up, Move::Down(romance),
+    ///                        Move::Down(french)]);
+    /// ```
+    pub fn route_to<T>(self, arena: &Arena<T>, other: Token)
+        -> Option<Vec<Move>> {
+        let lca = self.common_ancestors(arena, other).next()?;
+        let mut route = Vec::new();
+        let mut token = self;
+        while token != lca {
+            route.push(Move::Up);
+            token = arena[token].parent.unwrap();
+        }
+        let ups = route.len();
+        let mut token = other;
+        while token != lca {
+            route.push(Move::Down(token));
+            token = arena[token].parent.unwrap();
+        }
+        route[ups..].reverse();
+        Some(route)
+    }
+
+    /// Returns an iterator of references of sibling nodes preceding the current
+    /// node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, "Romance");
+    /// root_token.append(&mut arena, "Germanic");
+    /// let third_child_token = root_token.append(&mut arena, "Slavic");
+    /// root_token.append(&mut arena, "Celtic");
+    ///
+    /// let mut siblings = third_child_token.preceding_siblings(&arena);
+    /// assert_eq!(siblings.next().unwrap().data, "Germanic");
+    /// assert_eq!(siblings.next().unwrap().data, "Romance");
+    /// assert!(siblings.next().is_none());
+    /// ```
+    pub fn preceding_siblings<'a, T>(self, arena: &'a Arena<T>)
+        -> PrecedingSiblings<'a, T> {
+        PrecedingSiblings { token_iter: self.preceding_siblings_tokens(arena) }
+    }
+
+    /// Returns an iterator of references of sibling nodes following the current
+    /// node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, "Romance");
+    /// let second_child_token = root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Slavic");
+    /// root_token.append(&mut arena, "Hellenic");
+    ///
+    /// let mut siblings = second_child_token.following_siblings(&arena);
+    /// assert_eq!(siblings.next().unwrap().data, "Slavic");
+    /// assert_eq!(siblings.next().unwrap().data, "Hellenic");
+    /// assert!(siblings.next().is_none());
+    /// ```
+    pub fn following_siblings<'a, T>(self, arena: &'a Arena<T>)
+        -> FollowingSiblings<'a, T> {
+        FollowingSiblings { token_iter: self.following_siblings_tokens(arena) }
+    }
+
+    /// Returns an iterator of child node references in the order of insertion.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let first_child_token = root_token.append(&mut arena, "Germanic");
+    /// let second_child_token = root_token.append(&mut arena, "Romance");
+    /// let third_child_token = root_token.append(&mut arena, "Slavic");
+    /// let fourth_child_token = root_token.append(&mut arena, "Celtic");
+    ///
+    /// let mut children = root_token.children(&arena);
+    /// assert_eq!(children.next().unwrap().data, "Germanic");
+    /// assert_eq!(children.next().unwrap().data, "Romance");
+    /// assert_eq!(children.next().unwrap().data, "Slavic");
+    /// assert_eq!(children.next().unwrap().data, "Celtic");
+    /// assert!(children.next().is_none());
+    /// ```
+    pub fn children<'a, T>(self, arena: &'a Arena<T>) -> Children<'a, T> {
+        Children { token_iter: self.children_tokens(arena) }
+    }
+
+    /// Returns an iterator of the tokens of child nodes paired with references
+    /// to the nodes, in the order of insertion.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    ///
+    /// let mut entries = root_token.children_entries(&arena)
+    ///     .map(|(token, node)| (token, node.data));
+    /// assert_eq!(entries.next(), Some((germanic, "Germanic")));
+    /// assert_eq!(entries.next(), Some((romance, "Romance")));
+    /// assert!(entries.next().is_none());
+    /// ```
+    pub fn children_entries<'a, T>(self, arena: &'a Arena<T>)
+        -> ChildrenEntries<'a, T> {
+        ChildrenEntries { token_iter: self.children_tokens(arena) }
+    }
+
+    /// Returns an iterator of references to the data of the child nodes in the
+    /// order of insertion. This is shorthand for mapping [`children`] to the
+    /// node data.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let data: Vec<_> = root_token.children_data(&arena).collect();
+    /// assert_eq!(data, [&"Germanic", &"Romance"]);
+    /// ```
+    ///
+    /// [`children`]: struct.Token.html#method.children
+    pub fn children_data<'a, T>(self, arena: &'a Arena<T>)
+        -> ChildrenData<'a, T> {
+        ChildrenData { node_iter: self.children(arena) }
+    }
+
+    /// Returns an iterator of child node references in the reverse order of
+    /// insertion.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Romance");
+    /// root_token.append(&mut arena, "Slavic");
+    ///
+    /// let mut children = root_token.children_rev(&arena);
+    /// assert_eq!(children.next().unwrap().data, "Slavic");
+    /// assert_eq!(children.next().unwrap().data, "Romance");
+    /// assert_eq!(children.next().unwrap().data, "Germanic");
+    /// assert!(children.next().is_none());
+    /// ```
+    pub fn children_rev<'a, T>(self, arena: &'a Arena<T>)
+        -> ChildrenRev<'a, T> {
+        ChildrenRev { token_iter: self.children_rev_tokens(arena) }
+    }
+
+    /// Returns an iterator of mutable ancestor node references.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let child_token = root_token.append(&mut arena, 2usize);
+    /// let grandchild_token = child_token.append(&mut arena, 3usize);
+    /// let great_grandchild_token = grandchild_token.append(&mut arena, 4usize);
+    /// let ggreat_grandchild_token = great_grandchild_token.append(&mut arena, 5usize);
+    ///
+    /// for x in ggreat_grandchild_token.ancestors_mut(&mut arena) {
+    ///     x.data += 2;
+    /// }
+    ///
+    /// let mut ancestors = ggreat_grandchild_token.ancestors(&arena);
+    /// assert_eq!(ancestors.next().unwrap().data, 6usize);
+    /// assert_eq!(ancestors.next().unwrap().data, 5usize);
+    /// assert_eq!(ancestors.next().unwrap().data, 4usize);
+    /// assert_eq!(ancestors.next().unwrap().data, 3usize);
+    /// assert!(ancestors.next().is_none());
+    /// ```
+    pub fn ancestors_mut<'a, T>(self, arena: &'a mut Arena<T>)
+        -> AncestorsMut<'a, T> {
+        AncestorsMut {
+            arena: arena as *mut Arena<T>,
+            node_token: Some(self),
+            marker: PhantomData
+        }
+    }
+
+    /// Returns an iterator of mutable references of sibling nodes that follow
+    /// the current node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, 2usize);
+    /// let second_child_token = root_token.append(&mut arena, 3usize);
+    /// root_token.append(&mut arena, 4usize);
+    /// root_token.append(&mut arena, 5usize);
+    ///
+    /// for x in second_child_token.following_siblings_mut(&mut arena) {
+    ///     x.data += 2;
+    /// }
+    ///
+    /// let mut children = root_token.children(&arena);
+    /// assert_eq!(children.next().unwrap().data, 2usize);
+    /// assert_eq!(children.next().unwrap().data, 3usize);
+    /// assert_eq!(children.next().unwrap().data, 6usize);
+    /// assert_eq!(children.next().unwrap().data, 7usize);
+    /// assert!(children.next().is_none());
+    /// ```
+    pub fn following_siblings_mut<'a, T>(self, arena: &'a mut Arena<T>)
+        -> FollowingSiblingsMut<'a, T> {
+        let next_sibling = match arena.get(self) {
+            Some(n) => n.next_sibling,
+            None => panic!("Invalid token")
+        };
+        FollowingSiblingsMut {
+            arena: arena as *mut Arena<T>,
+            node_token: next_sibling,
+            marker: PhantomData
+        }
+    }
+
+    /// Returns an iterator of mutable references of sibling nodes that precede
+    /// the current node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, 2usize);
+    /// root_token.append(&mut arena, 3usize);
+    /// root_token.append(&mut arena, 4usize);
+    /// let fourth_child_token = root_token.append(&mut arena, 5usize);
+    ///
+    /// for x in fourth_child_token.preceding_siblings_mut(&mut arena) {
+    ///     x.data += 5;
+    /// }
+    ///
+    /// let mut children = root_token.children(&arena);
+    /// assert_eq!(children.next().unwrap().data, 7usize);
+    /// assert_eq!(children.next().unwrap().data, 8usize);
+    /// assert_eq!(children.next().unwrap().data, 9usize);
+    /// assert_eq!(children.next().unwrap().data, 5usize);
+    /// assert!(children.next().is_none());
+    /// ```
+    pub fn preceding_siblings_mut<'a, T>(self, arena: &'a mut Arena<T>)
+        -> PrecedingSiblingsMut<'a, T> {
+        let previous_sibling = match arena.get(self) {
+            Some(n) => n.previous_sibling,
+            None => panic!("Invalid token")
+        };
+        PrecedingSiblingsMut {
+            arena: arena as *mut Arena<T>,
+            node_token: previous_sibling,
+            marker: PhantomData
+        }
+    }
+
+    /// Returns an iterator of mutable references of child nodes in the order of
+    /// insertion.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, 2usize);
+    /// root_token.append(&mut arena, 3usize);
+    /// let third_child_token = root_token.append(&mut arena, 4usize);
+    /// root_token.append(&mut arena, 5usize);
+    /// let grandchild = third_child_token.append(&mut arena, 10usize);
+    ///
+    /// for x in root_token.children_mut(&mut arena) {
+    ///     x.data += 10;
+    /// }
+    ///
+    /// let mut children = root_token.children(&arena);
+    /// assert_eq!(children.next().unwrap().data, 12);
+    /// assert_eq!(children.next().unwrap().data, 13);
+    /// assert_eq!(children.next().unwrap().data, 14);
+    /// assert_eq!(children.next().unwrap().data, 15);
+    /// assert_eq!(arena.get(grandchild).unwrap().data, 10);
+    /// assert!(children.next().is_none());
+    /// ```
+    pub fn children_mut<'a, T>(self, arena: &'a mut Arena<T>)
+        -> ChildrenMut<'a, T> {
+        let first_child = match arena.get(self) {
+            Some(n) => n.first_child,
+            None => panic!("Invalid token")
+        };
+        ChildrenMut {
+            arena: arena as *mut Arena<T>,
+            node_token: first_child,
+            marker: PhantomData
+        }
+    }
+
+    /// Returns an iterator of mutable references to the data of the child
+    /// nodes in the order of insertion. Frozen nodes are skipped over.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(0usize);
+    /// root_token.append(&mut arena, 1usize);
+    /// root_token.append(&mut arena, 2usize);
+    ///
+    /// for x in root_token.children_data_mut(&mut arena) { *x *= 10 }
+    /// let data: Vec<_> = root_token.children_data(&arena).collect();
+    /// assert_eq!(data, [&10, &20]);
+    /// ```
+    pub fn children_data_mut<'a, T>(self, arena: &'a mut Arena<T>)
+        -> ChildrenDataMut<'a, T> {
+        ChildrenDataMut { node_iter: self.children_mut(arena) }
+    }
+
+    /// Returns an iterator of tokens of subtree nodes of the given node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let first_child = root_token.append(&mut arena, "Romance");
+    /// let second_child = root_token.append(&mut arena, "Germanic");
+    /// let third_child = root_token.append(&mut arena, "Slavic");
+    /// let first_grandchild = second_child.append(&mut arena, "English");
+    /// let second_grandchild = second_child.append(&mut arena, "Icelandic");
+    /// let fourth_child = root_token.append(&mut arena, "Celtic");
+    ///
+    /// let mut subtree = root_token.subtree_tokens(&arena, TraversalOrder::Pre);
+    /// assert_eq!(subtree.next(), Some(root_token));
+    /// assert_eq!(subtree.next(), Some(first_child));
+    /// assert_eq!(subtree.next(), Some(second_child));
+    /// assert_eq!(subtree.next(), Some(first_grandchild));
+    /// assert_eq!(subtree.next(), Some(second_grandchild));
+    /// assert_eq!(subtree.next(), Some(third_child));
+    /// assert_eq!(subtree.next(), Some(fourth_child));
+    /// assert!(subtree.next().is_none());
+    ///
+    /// let mut subtree = second_grandchild.subtree_tokens(&arena, TraversalOrder::Pre);
+    /// assert_eq!(subtree.next(), Some(second_grandchild));
+    /// assert!(subtree.next().is_none());
+    /// ```
+    pub fn subtree_tokens<'a, T>(self, arena: &'a Arena<T>, order: TraversalOrder)
+        -> SubtreeTokens<'a, T> {
+        let preord_tokens_next = |iter: &mut SubtreeTokens<T>| 
+            depth_first_tokens_next(iter, preorder_next);
+        let postord_tokens_next = |iter: &mut SubtreeTokens<T>| 
+            depth_first_tokens_next(iter, postorder_next);
+        match order {
+            TraversalOrder::Pre => SubtreeTokens {
+                arena,
+                subtree_root: self,
+                node_token: Some(self),
+                branch: Branch::Child,
+                curr_level: VecDeque::new(),  // unused field
+                next_level: VecDeque::new(),  // unused field
+                next: preord_tokens_next
+            },
+            TraversalOrder::Post => {
+                let (node_token, branch) =
+                    postorder_next(self, self, Branch::Child, arena);
+                SubtreeTokens {
+                    arena,
+                    subtree_root: self,
+                    node_token,
+                    branch,
+                    curr_level: VecDeque::new(),  // unused field
+                    next_level: VecDeque::new(),  // unused field
+                    next: postord_tokens_next
+                }
+            },
+            TraversalOrder::Level => {
+                SubtreeTokens {
+                    arena,
+                    subtree_root: self,  // unused field
+                    node_token: None,  // unused field
+                    branch: Branch::None,  // unused field
+                    curr_level: core::iter::once(self).collect(),
+                    next_level: VecDeque::new(),
+                    next: breadth_first_tokens_next
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator of references of subtree nodes of the given node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, "Romance");
+    /// root_token.append(&mut arena, "Germanic");
+    /// let third_child = root_token.append(&mut arena, "Slavic");
+    /// root_token.append(&mut arena, "Celtic");
+    /// third_child.append(&mut arena, "Polish");
+    /// third_child.append(&mut arena, "Slovakian");
+    ///
+    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre);
+    /// assert_eq!(subtree.next().unwrap().data, "Indo-European");
+    /// assert_eq!(subtree.next().unwrap().data, "Romance");
+    /// assert_eq!(subtree.next().unwrap().data, "Germanic");
+    /// assert_eq!(subtree.next().unwrap().data, "Slavic");
+    /// assert_eq!(subtree.next().unwrap().data, "Polish");
+    /// assert_eq!(subtree.next().unwrap().data, "Slovakian");
+    /// assert_eq!(subtree.next().unwrap().data, "Celtic");
+    /// assert!(subtree.next().is_none());
+    /// ```
+    pub fn subtree<'a, T>(self, arena: &'a Arena<T>, order: TraversalOrder)
+        -> Subtree<'a, T> {
+        Subtree {
+            arena,
+            iter: self.subtree_tokens(arena, order)
+        }
+    }
+
+    /// Returns an iterator of references to the data of the subtree nodes of
+    /// the given node. This is shorthand for mapping [`subtree`] to the node
+    /// data.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let data: Vec<_> = root_token.subtree_data(&arena, TraversalOrder::Post)
+    ///     .collect();
+    /// assert_eq!(data, [&"English", &"Germanic", &"Romance", &"Indo-European"]);
+    /// ```
+    ///
+    /// [`subtree`]: struct.Token.html#method.subtree
+    pub fn subtree_data<'a, T>(self, arena: &'a Arena<T>,
+                               order: TraversalOrder) -> SubtreeData<'a, T> {
+        SubtreeData { node_iter: self.subtree(arena, order) }
+    }
+
+    /// Clears the buffer and fills it with the tokens of the subtree nodes of
+    /// the given node in the given order. Reusing one buffer across calls
+    /// saves allocating a new `Vec` every time.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    ///
+    /// let mut buf = Vec::new();
+    /// root_token.collect_subtree_tokens(&arena, TraversalOrder::Post, &mut buf);
+    /// assert_eq!(buf, vec![english, germanic, root_token]);
+    /// germanic.collect_subtree_tokens(&arena, TraversalOrder::Pre, &mut buf);
+    /// assert_eq!(buf, vec![germanic, english]);
+    /// ```
+    pub fn collect_subtree_tokens<T>(self, arena: &Arena<T>,
+                                     order: TraversalOrder,
+                                     buf: &mut Vec<Token>) {
+        buf.clear();
+        buf.extend(self.subtree_tokens(arena, order));
+    }
+
+    /// Returns an iterator that walks the subtrees of the given nodes in
+    /// lockstep in the given order and yields pairs of corresponding nodes.
+    /// The two subtrees may live in different arenas and hold different types
+    /// of data.
+    ///
+    /// Whenever the traversal reaches a pair of nodes, their numbers of
+    /// children are compared before anything below them is visited. The
+    /// iterator stops at the first pair where they differ, so every pair that
+    /// is yielded is a pair of nodes at the same position in both subtrees.
+    /// The mismatched pair itself is not yielded.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in its arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut values, root) = Arena::with_data(1usize);
+    /// root.append(&mut values, 2usize);
+    /// root.append(&mut values, 3usize);
+    ///
+    /// let (mut names, other_root) = Arena::with_data("one");
+    /// other_root.append(&mut names, "two");
+    /// other_root.append(&mut names, "three");
+    ///
+    /// let mut pairs = root.zip_subtree(&values, other_root, &names,
+    ///                                  TraversalOrder::Pre)
+    ///     .map(|(a, b)| (a.data, b.data));
+    /// assert_eq!(pairs.next(), Some((1, "one")));
+    /// assert_eq!(pairs.next(), Some((2, "two")));
+    /// assert_eq!(pairs.next(), Some((3, "three")));
+    /// assert!(pairs.next().is_none());
+    /// ```
+    pub fn zip_subtree<'a, T, U>(self, arena: &'a Arena<T>, other: Token,
+                                 other_arena: &'a Arena<U>,
+                                 order: TraversalOrder)
+        -> ZipSubtree<'a, T, U> {
+        if arena.get(self).is_none() || other_arena.get(other).is_none() {
+            panic!("Invalid token")
+        }
+        let mut pending = VecDeque::new();
+        pending.push_back((self, other, false));
+        ZipSubtree { arena, other_arena, order, pending }
+    }
+
+    /// Returns an iterator of the tokens of subtree nodes of the given node
+    /// paired with references to the nodes.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    ///
+    /// let mut entries = root_token.subtree_entries(&arena, TraversalOrder::Pre)
+    ///     .map(|(token, node)| (token, node.data));
+    /// assert_eq!(entries.next(), Some((root_token, "Indo-European")));
+    /// assert_eq!(entries.next(), Some((germanic, "Germanic")));
+    /// assert_eq!(entries.next(), Some((english, "English")));
+    /// assert!(entries.next().is_none());
+    /// ```
+    pub fn subtree_entries<'a, T>(self, arena: &'a Arena<T>,
+                                  order: TraversalOrder)
+        -> SubtreeEntries<'a, T> {
+        SubtreeEntries { token_iter: self.subtree_tokens(arena, order) }
+    }
+
+    /// Returns an iterator of tokens of the leaves in the subtree of the given
+    /// node in pre-order.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let swedish = germanic.append(&mut arena, "Swedish");
+    /// let albanian = root_token.append(&mut arena, "Albanian");
+    ///
+    /// let mut leaves = root_token.leaves_tokens(&arena);
+    /// assert_eq!(leaves.next(), Some(english));
+    /// assert_eq!(leaves.next(), Some(swedish));
+    /// assert_eq!(leaves.next(), Some(albanian));
+    /// assert!(leaves.next().is_none());
+    /// ```
+    pub fn leaves_tokens<'a, T>(self, arena: &'a Arena<T>) -> LeavesTokens<'a, T> {
+        LeavesTokens {
+            arena,
+            iter: self.subtree_tokens(arena, TraversalOrder::Pre)
+        }
+    }
+
+    /// Returns an iterator of references of the leaves in the subtree of the
+    /// given node in pre-order.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// germanic.append(&mut arena, "Swedish");
+    /// root_token.append(&mut arena, "Albanian");
+    ///
+    /// let mut leaves = root_token.leaves(&arena);
+    /// assert_eq!(leaves.next().unwrap().data, "English");
+    /// assert_eq!(leaves.next().unwrap().data, "Swedish");
+    /// assert_eq!(leaves.next().unwrap().data, "Albanian");
+    /// assert!(leaves.next().is_none());
+    /// ```
+    pub fn leaves<'a, T>(self, arena: &'a Arena<T>) -> Leaves<'a, T> {
+        Leaves { token_iter: self.leaves_tokens(arena) }
+    }
+
+    /// Returns an iterator of tokens of the descendants that are exactly
+    /// `depth` levels below the given node, from left to right. A depth of 0
+    /// yields the node itself, a depth of 1 its children and so on. Nodes
+    /// deeper than `depth` are never visited. See [`nodes_at_depth`] to
+    /// collect the tokens into a `Vec` directly.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// let mut tokens = root_token.descendants_at_depth_tokens(&arena, 2);
+    /// assert_eq!(tokens.next(), Some(english));
+    /// assert_eq!(tokens.next(), Some(french));
+    /// assert!(tokens.next().is_none());
+    /// ```
+    ///
+    /// [`nodes_at_depth`]: struct.Token.html#method.nodes_at_depth
+    pub fn descendants_at_depth_tokens<'a, T>(self, arena: &'a Arena<T>,
+                                              depth: usize)
+        -> DescendantsAtDepthTokens<'a, T> {
+        if arena.get(self).is_none() { panic!("Invalid token") }
+        if depth == 0 {
+            return DescendantsAtDepthTokens {
+                arena,
+                parents: VecDeque::new(),
+                node_token: Some(self)
+            }
+        }
+        // expand level by level until the level right above the target
+        let mut parents = VecDeque::new();
+        parents.push_back(self);
+        for _ in 1..depth {
+            if parents.is_empty() { break }
+            parents = parents.iter()
+                .flat_map(|&t| t.children_tokens(arena))
+                .collect();
+        }
+        DescendantsAtDepthTokens { arena, parents, node_token: None }
+    }
+
+    /// Returns an iterator of references of the descendants that are exactly
+    /// `depth` levels below the given node, from left to right. A depth of 0
+    /// yields the node itself, a depth of 1 its children and so on.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// root_token.append(&mut arena, "Romance");
+    /// germanic.append(&mut arena, "English");
+    ///
+    /// let mut nodes = root_token.descendants_at_depth(&arena, 1)
+    ///     .map(|x| x.data);
+    /// assert_eq!(nodes.next(), Some("Germanic"));
+    /// assert_eq!(nodes.next(), Some("Romance"));
+    /// assert!(nodes.next().is_none());
+    /// ```
+    pub fn descendants_at_depth<'a, T>(self, arena: &'a Arena<T>, depth: usize)
+        -> DescendantsAtDepth<'a, T> {
+        DescendantsAtDepth {
+            token_iter: self.descendants_at_depth_tokens(arena, depth)
+        }
+    }
+
+    /// Copies the subtree of the given node into an owned, nested tree. The
+    /// children come in the same order as in the arena, so the result can be
+    /// turned back into an arena with [`Arena::from_nested`].
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::{Arena, RecursiveTree};
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let leaf = |data| RecursiveTree { data, children: vec![] };
+    /// assert_eq!(germanic.to_nested(&arena), RecursiveTree {
+    ///     data: "Germanic",
+    ///     children: vec![leaf("English")]
+    /// });
+    /// ```
+    ///
+    /// [`Arena::from_nested`]: struct.Arena.html#method.from_nested
+    #[doc(alias = "to_spec")]
+    pub fn to_nested<T>(self, arena: &Arena<T>) -> RecursiveTree<T>
+        where T: Clone {
+        let new_tree = |token: Token| RecursiveTree {
+            data: arena[token].data.clone(),
+            children: Vec::new()
+        };
+        // each frame holds a partially built tree and the next child to visit
+        let mut stack = vec![(new_tree(self), arena[self].first_child)];
+        loop {
+            let (_, next_child) = stack.last_mut().unwrap();  // never empty
+            match *next_child {
+                Some(child) => {
+                    *next_child = arena[child].next_sibling;
+                    stack.push((new_tree(child), arena[child].first_child));
+                },
+                None => {
+                    let (tree, _) = stack.pop().unwrap();
+                    match stack.last_mut() {
+                        None => break tree,
+                        Some((parent, _)) => parent.children.push(tree)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders the subtree rooted at the given node in the GraphViz DOT
+    /// language for debugging. See [`Arena::to_dot`] for details.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    ///
+    /// let dot = germanic.to_dot(&arena, |data| data.to_string());
+    /// assert_eq!(dot, arena.to_dot(germanic, |data| data.to_string()));
+    /// ```
+    ///
+    /// [`Arena::to_dot`]: struct.Arena.html#method.to_dot
+    #[doc(alias = "graphviz")]
+    pub fn to_dot<T, F>(self, arena: &Arena<T>, label: F) -> String
+        where F: Fn(&T) -> String {
+        arena.to_dot(self, label)
+    }
+
+    /// Builds a new arena that holds a copy of the subtree of the given node
+    /// with every datum transformed by the closure. The shape of the subtree
+    /// is preserved exactly. Returns the new arena along with the token of
+    /// the root of the copy.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1);
+    /// let child = root_token.append(&mut arena, 2);
+    /// child.append(&mut arena, 3);
+    /// root_token.append(&mut arena, 4);
+    ///
+    /// let (new_arena, new_root) = child.map_subtree(&arena, |x| x * 10);
+    /// let mut subtree = new_root.subtree(&new_arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data);
+    /// assert_eq!(subtree.next(), Some(20));
+    /// assert_eq!(subtree.next(), Some(30));
+    /// assert!(subtree.next().is_none());
+    /// ```
+    pub fn map_subtree<T, U, F>(self, arena: &Arena<T>, mut f: F)
+        -> (Arena<U>, Token) where F: FnMut(&T) -> U {
+        let root = match arena.get(self) {
+            None => panic!("Invalid token"),
+            Some(node) => node
+        };
+        let (mut new_arena, new_root) = Arena::with_data_and_capacity(
+            f(&root.data), self.subtree_size_hint(arena));
+        // the path from the root of the subtree to the current node, as pairs
+        // of the tokens in the original arena and in the new one
+        let mut path = vec![(self, new_root)];
+        for node in self.subtree(arena, TraversalOrder::Pre).skip(1) {
+            while let Some(&(token, _)) = path.last() {
+                match Some(token) == node.parent {
+                    true => break,
+                    false => { path.pop(); }
+                }
+            }
+            let (_, new_parent) = *path.last().unwrap();  // root is never popped
+            let new_token = new_parent.append(&mut new_arena, f(&node.data));
+            path.push((node.token, new_token));
+        }
+        (new_arena, new_root)
+    }
+
+    /// Returns an iterator of tokens of the nodes that come after the given
+    /// node in a pre-order traversal of the tree it belongs to, excluding its
+    /// descendants.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// let mut following = germanic.following_tokens(&arena);
+    /// assert_eq!(following.next(), Some(romance));
+    /// assert_eq!(following.next(), Some(french));
+    /// assert!(following.next().is_none());
+    /// ```
+    pub fn following_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> FollowingTokens<'a, T> {
+        let root = self.ancestors_tokens(arena).last().unwrap_or(self);
+        let node_token = match root == self {
+            true => None,
+            false => preorder_next(self, root, Branch::Sibling, arena).0
+        };
+        FollowingTokens { arena, root, node_token }
+    }
+
+    /// Returns an iterator of references of the nodes that come after the
+    /// given node in a pre-order traversal of the tree it belongs to, excluding
+    /// its descendants.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// romance.append(&mut arena, "French");
+    ///
+    /// let mut following = germanic.following(&arena);
+    /// assert_eq!(following.next().unwrap().data, "Romance");
+    /// assert_eq!(following.next().unwrap().data, "French");
+    /// assert!(following.next().is_none());
+    /// ```
+    pub fn following<'a, T>(self, arena: &'a Arena<T>) -> Following<'a, T> {
+        Following { token_iter: self.following_tokens(arena) }
+    }
+
+    /// Returns an iterator of tokens of the nodes that come before the given
+    /// node in a pre-order traversal of the tree it belongs to, excluding its
+    /// ancestors. The nodes are returned in pre-order.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// let mut preceding = french.preceding_tokens(&arena);
+    /// assert_eq!(preceding.next(), Some(germanic));
+    /// assert_eq!(preceding.next(), Some(english));
+    /// assert!(preceding.next().is_none());
+    /// ```
+    pub fn preceding_tokens<'a, T>(self, arena: &'a Arena<T>)
+        -> PrecedingTokens<'a, T> {
+        let mut path = vec![self];
+        path.extend(self.ancestors_tokens(arena));
+        let root = *path.last().unwrap();  // never empty
+        PrecedingTokens { arena, root, node_token: Some(root), path }
+    }
+
+    /// Returns an iterator of references of the nodes that come before the
+    /// given node in a pre-order traversal of the tree it belongs to, excluding
+    /// its ancestors. The nodes are returned in pre-order.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// let mut preceding = french.preceding(&arena);
+    /// assert_eq!(preceding.next().unwrap().data, "Germanic");
+    /// assert_eq!(preceding.next().unwrap().data, "English");
+    /// assert!(preceding.next().is_none());
+    /// ```
+    pub fn preceding<'a, T>(self, arena: &'a Arena<T>) -> Preceding<'a, T> {
+        Preceding { token_iter: self.preceding_tokens(arena) }
+    }
+
+    /// Folds the subtree of the given node bottom-up. `f` is called on each
+    /// node with its data and the folded values of its children in order, and
+    /// the folded value of the given node is returned.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// // (1 + 2) * 3
+    /// let (mut arena, mul) = Arena::with_data("*");
+    /// let add = mul.append(&mut arena, "+");
+    /// add.append(&mut arena, "1");
+    /// add.append(&mut arena, "2");
+    /// mul.append(&mut arena, "3");
+    ///
+    /// let value = mul.fold(&arena, |data, args: Vec<i32>| match *data {
+    ///     "+" => args.iter().sum(),
+    ///     "*" => args.iter().product(),
+    ///     n => n.parse().unwrap()
+    /// });
+    /// assert_eq!(value, 9);
+    /// ```
+    pub fn fold<T, B, F>(self, arena: &Arena<T>, mut f: F) -> B
+        where F: FnMut(&T, Vec<B>) -> B {
+        // each frame holds a node, the next child to visit and the folded
+        // values of the children visited so far
+        let mut stack = vec![(self, arena[self].first_child, Vec::new())];
+        loop {
+            let (_, next_child, _) = stack.last_mut().unwrap();  // never empty
+            match *next_child {
+                Some(child) => {
+                    *next_child = arena[child].next_sibling;
+                    stack.push((child, arena[child].first_child, Vec::new()));
+                },
+                None => {
+                    let (token, _, values) = stack.pop().unwrap();
+                    let value = f(&arena[token].data, values);
+                    match stack.last_mut() {
+                        None => break value,
+                        Some((_, _, values)) => values.push(value)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Folds the data along the path from the root of the tree down to the
+    /// given node, root first and the node itself last.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("usr");
+    /// let share = root_token.append(&mut arena, "share");
+    /// let doc = share.append(&mut arena, "doc");
+    ///
+    /// let path = doc.fold_path(&arena, String::new(), |path, dir| {
+    ///     path + "/" + dir
+    /// });
+    /// assert_eq!(path, "/usr/share/doc");
+    /// ```
+    pub fn fold_path<T, B, F>(self, arena: &Arena<T>, init: B, mut f: F) -> B
+        where F: FnMut(B, &T) -> B {
+        // the ancestors come bottom-up, so collect them to walk down instead
+        let mut path: Vec<Token> = self.ancestors_tokens(arena).collect();
+        path.reverse();
+        path.push(self);
+        path.into_iter().fold(init, |acc, token| f(acc, &arena[token].data))
+    }
+
+    /// Returns true if the subtree of the given node and the subtree of the
+    /// other node in the other arena have the same shape and equal data at
+    /// each corresponding position, with children compared in order.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in its arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena1, root1) = Arena::with_data("+");
+    /// root1.append(&mut arena1, "1");
+    /// root1.append(&mut arena1, "2");
+    ///
+    /// let (mut arena2, root2) = Arena::with_data("*");
+    /// let sum = root2.append(&mut arena2, "+");
+    /// sum.append(&mut arena2, "1");
+    /// sum.append(&mut arena2, "2");
+    ///
+    /// assert!(root1.eq_subtree(&arena1, sum, &arena2));
+    /// assert!(!root1.eq_subtree(&arena1, root2, &arena2));
+    /// ```
+    pub fn eq_subtree<T>(self, arena: &Arena<T>, other: Token,
+                         other_arena: &Arena<T>) -> bool where T: PartialEq {
+        let mut stack = vec![(self, other)];
+        while let Some((token, other_token)) = stack.pop() {
+            if arena[token].data != other_arena[other_token].data {
+                return false
+            }
+            let mut children = token.children_tokens(arena);
+            let mut other_children = other_token.children_tokens(other_arena);
+            loop {
+                match (children.next(), other_children.next()) {
+                    (None, None) => break,
+                    (Some(a), Some(b)) => stack.push((a, b)),
+                    _ => return false  // different number of children
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns true if the subtrees of the given nodes in the same arena have
+    /// the same shape and equal data at each corresponding position. See
+    /// [`eq_subtree`] for comparing across arenas.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root) = Arena::with_data("+");
+    /// let left = root.append(&mut arena, "*");
+    /// left.append(&mut arena, "x");
+    /// let right = root.append(&mut arena, "*");
+    /// right.append(&mut arena, "x");
+    ///
+    /// assert!(left.eq_subtree_in(&arena, right));
+    /// assert!(!left.eq_subtree_in(&arena, root));
+    /// ```
+    ///
+    /// [`eq_subtree`]: struct.Token.html#method.eq_subtree
+    pub fn eq_subtree_in<T>(self, arena: &Arena<T>, other: Token) -> bool
+        where T: PartialEq {
+        self.eq_subtree(arena, other, arena)
+    }
+
+    /// Feeds the shape and the data of the subtree of the given node into the
+    /// given hasher. The nodes are hashed in pre-order along with the number
+    /// of their children, which determines the shape of the subtree.
+    ///
+    /// This is consistent with [`eq_subtree`]: subtrees that are equal under
+    /// `eq_subtree` produce the same hash, provided that the `Hash`
+    /// implementation of `T` is consistent with its `PartialEq`
+    /// implementation. As usual, unequal subtrees may still collide.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::Hasher;
+    ///
+    /// let (mut arena, root) = Arena::with_data("+");
+    /// let left = root.append(&mut arena, "*");
+    /// left.append(&mut arena, "x");
+    /// let right = root.append(&mut arena, "*");
+    /// right.append(&mut arena, "x");
+    ///
+    /// let hash = |token: atree::Token| {
+    ///     let mut hasher = DefaultHasher::new();
+    ///     token.hash_subtree(&arena, &mut hasher);
+    ///     hasher.finish()
+    /// };
+    /// assert_eq!(hash(left), hash(right));
+    /// ```
+    ///
+    /// [`eq_subtree`]: struct.Token.html#method.eq_subtree
+    pub fn hash_subtree<T, H>(self, arena: &Arena<T>, state: &mut H)
+        where T: Hash, H: Hasher {
+        for token in self.subtree_tokens(arena, TraversalOrder::Pre) {
+            arena[token].data.hash(state);
+            token.children_tokens(arena).count().hash(state);
+        }
+    }
+
+    /// Returns the data of the leaves in the subtree of the given node from
+    /// left to right, i.e. the yield of the subtree.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "S";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let np = root_token.append(&mut arena, "NP");
+    /// np.append(&mut arena, "dogs");
+    /// let vp = root_token.append(&mut arena, "VP");
+    /// vp.append(&mut arena, "bark");
+    ///
+    /// assert_eq!(root_token.leaf_sequence(&arena), vec![&"dogs", &"bark"]);
+    /// ```
+    pub fn leaf_sequence<T>(self, arena: &Arena<T>) -> Vec<&T> {
+        self.leaves(arena).map(|node| &node.data).collect()
+    }
+
+    /// Returns true if the subtrees of the given nodes have the same leaf
+    /// sequence regardless of their internal structure.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if either token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let mut arena = Arena::new();
+    ///
+    /// // (S (NP old men) (VP walk))
+    /// let first = arena.new_node("S");
+    /// let np = first.append(&mut arena, "NP");
+    /// np.append(&mut arena, "old");
+    /// np.append(&mut arena, "men");
+    /// let vp = first.append(&mut arena, "VP");
+    /// vp.append(&mut arena, "walk");
+    ///
+    /// // (S old (NP men walk))
+    /// let second = arena.new_node("S");
+    /// second.append(&mut arena, "old");
+    /// let np = second.append(&mut arena, "NP");
+    /// np.append(&mut arena, "men");
+    /// np.append(&mut arena, "walk");
+    ///
+    /// assert!(first.leaf_sequence_eq(&arena, second));
+    /// assert!(!first.leaf_sequence_eq(&arena, np));
+    /// ```
+    pub fn leaf_sequence_eq<T>(self, arena: &Arena<T>, other: Token) -> bool
+        where T: PartialEq {
+        let leaves = self.leaves(arena).map(|node| &node.data);
+        let other_leaves = other.leaves(arena).map(|node| &node.data);
+        leaves.eq(other_leaves)
+    }
+
+    /// Returns an iterator of mutable references of subtree nodes of the given
+    /// node.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let root_data = 1usize;
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// root_token.append(&mut arena, 2usize);
+    /// root_token.append(&mut arena, 3usize);
+    /// let third_child = root_token.append(&mut arena, 4usize);
+    /// root_token.append(&mut arena, 5usize);
+    /// third_child.append(&mut arena, 10usize);
+    /// third_child.append(&mut arena, 20usize);
+    ///
+    /// for x in root_token.subtree_mut(&mut arena, TraversalOrder::Pre) {
+    ///     x.data += 100;
+    /// }
+    ///
+    /// let mut subtree = root_token.subtree(&arena, TraversalOrder::Pre);
+    /// assert_eq!(subtree.next().unwrap().data, 101);
+    /// assert_eq!(subtree.next().unwrap().data, 102);
+    /// assert_eq!(subtree.next().unwrap().data, 103);
+    /// assert_eq!(subtree.next().unwrap().data, 104);
+    /// assert_eq!(subtree.next().unwrap().data, 110);
+    /// assert_eq!(subtree.next().unwrap().data, 120);
+    /// assert_eq!(subtree.next().unwrap().data, 105);
+    /// assert!(subtree.next().is_none());
+    /// ```
+    pub fn subtree_mut<'a, T>(self, arena: &'a mut Arena<T>, order: TraversalOrder)
+        -> SubtreeMut<'a, T> {
+        let tokens = self.subtree_tokens(arena, order).collect();
+        match SubtreeMut::split(arena, &[tokens]) {
+            Some(mut subtrees) => subtrees.pop().unwrap(),
+            None => panic!("Corrupt arena")
+        }
+    }
+
+    /// Returns an iterator of mutable references to the data of the subtree
+    /// nodes of the given node. Frozen nodes are skipped over.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// let child = root_token.append(&mut arena, 2usize);
+    /// child.append(&mut arena, 3usize);
+    ///
+    /// for x in root_token.subtree_data_mut(&mut arena, TraversalOrder::Pre) {
+    ///     *x += 100;
+    /// }
+    /// let data: Vec<_> = root_token.subtree_data(&arena, TraversalOrder::Pre)
+    ///     .collect();
+    /// assert_eq!(data, [&101, &102, &103]);
+    /// ```
+    pub fn subtree_data_mut<'a, T>(self, arena: &'a mut Arena<T>,
+                                   order: TraversalOrder)
+        -> SubtreeDataMut<'a, T> {
+        SubtreeDataMut { node_iter: self.subtree_mut(arena, order) }
+    }
+
+    /// Returns the tokens of all nodes exactly `depth` levels below the given
+    /// node in level order. A `depth` of zero returns the node itself.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let root_data = "Indo-European";
+    /// let (mut arena, root_token) = Arena::with_data(root_data);
+    ///
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// let romance = root_token.append(&mut arena, "Romance");
+    /// let english = germanic.append(&mut arena, "English");
+    /// let french = romance.append(&mut arena, "French");
+    ///
+    /// assert_eq!(root_token.nodes_at_depth(&arena, 0), vec![root_token]);
+    /// assert_eq!(root_token.nodes_at_depth(&arena, 1), vec![germanic, romance]);
+    /// assert_eq!(root_token.nodes_at_depth(&arena, 2), vec![english, french]);
+    /// assert!(root_token.nodes_at_depth(&arena, 3).is_empty());
+    /// ```
+    pub fn nodes_at_depth<T>(self, arena: &Arena<T>, depth: usize) -> Vec<Token> {
+        self.descendants_at_depth_tokens(arena, depth).collect()
+    }
+
+    /// Returns the node that follows the given node in in-order traversal.
+    /// In-order traversal is generalized to trees of any degree by visiting the
+    /// subtree of the first child, then the node itself, and then the subtrees
+    /// of the rest of the children. For binary trees this is just the usual
+    /// in-order traversal.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, two) = Arena::with_data(2);
+    /// let one = two.append(&mut arena, 1);
+    /// let four = two.append(&mut arena, 4);
+    /// let three = four.append(&mut arena, 3);
+    ///
+    /// assert_eq!(one.inorder_successor(&arena), Some(two));
+    /// assert_eq!(two.inorder_successor(&arena), Some(three));
+    /// assert_eq!(three.inorder_successor(&arena), Some(four));
+    /// assert_eq!(four.inorder_successor(&arena), None);
+    /// ```
+    pub fn inorder_successor<T>(self, arena: &Arena<T>) -> Option<Token> {
+        let leftmost = |mut token: Token| {
+            while let Some(child) = arena[token].first_child { token = child }
+            token
+        };
+        let node = match arena.get(self) {
+            Some(n) => n,
+            None => panic!("Invalid token")
+        };
+        // the subtree of the second child comes right after the node
+        if let Some(second_child) = node.first_child
+            .and_then(|child| arena[child].next_sibling) {
+            return Some(leftmost(second_child))
+        }
+        // otherwise we are done with the subtree so we look for the next one
+        let mut token = self;
+        loop {
+            let node = &arena[token];
+            match node.parent {
+                None => break None,
+                Some(parent) => match arena[parent].first_child == Some(token) {
+                    true => break Some(parent),
+                    false => match node.next_sibling {
+                        Some(sibling) => break Some(leftmost(sibling)),
+                        None => token = parent
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the node that precedes the given node in in-order traversal.
+    /// See [`inorder_successor`] for how in-order traversal works for trees of
+    /// any degree.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, two) = Arena::with_data(2);
+    /// let one = two.append(&mut arena, 1);
+    /// let four = two.append(&mut arena, 4);
+    /// let three = four.append(&mut arena, 3);
+    ///
+    /// assert_eq!(four.inorder_predecessor(&arena), Some(three));
+    /// assert_eq!(three.inorder_predecessor(&arena), Some(two));
+    /// assert_eq!(two.inorder_predecessor(&arena), Some(one));
+    /// assert_eq!(one.inorder_predecessor(&arena), None);
+    /// ```
+    ///
+    /// [`inorder_successor`]: struct.Token.html#method.inorder_successor
+    pub fn inorder_predecessor<T>(self, arena: &Arena<T>) -> Option<Token> {
+        // the last node of a subtree in in-order traversal
+        let rightmost = |mut token: Token| loop {
+            let node = &arena[token];
+            match node.first_child.and_then(|child| arena[child].next_sibling) {
+                None => break token,  // nothing comes after the node itself
+                Some(_) => token = node.last_child.unwrap()
+            }
+        };
+        let node = match arena.get(self) {
+            Some(n) => n,
+            None => panic!("Invalid token")
+        };
+        // the subtree of the first child comes right before the node
+        if let Some(first_child) = node.first_child {
+            return Some(rightmost(first_child))
+        }
+        // otherwise the node starts its subtree so we look for the one before
+        let mut token = self;
+        loop {
+            let node = &arena[token];
+            match (node.parent, node.previous_sibling) {
+                (None, _) => break None,
+                (Some(parent), None) => token = parent,
+                (Some(parent), Some(sibling)) =>
+                    match arena[parent].first_child == Some(sibling) {
+                        true => break Some(parent),
+                        false => break Some(rightmost(sibling))
+                    }
+            }
+        }
+    }
+
+    /// Removes all descendants of the given node, leaving it a leaf, and
+    /// returns their data in pre-order. The cells of the removed nodes are
+    /// freed.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if
+    /// the node or any of its descendants is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data("Indo-European");
+    /// let germanic = root_token.append(&mut arena, "Germanic");
+    /// germanic.append(&mut arena, "English");
+    /// root_token.append(&mut arena, "Romance");
+    ///
+    /// let removed = root_token.remove_children(&mut arena);
+    /// assert_eq!(removed, ["Germanic", "English", "Romance"]);
+    /// assert!(root_token.is_leaf(&arena));
+    /// assert_eq!(arena.node_count(), 1);
+    /// ```
+    pub fn remove_children<T>(self, arena: &mut Arena<T>) -> Vec<T> {
+        if self.subtree(arena, TraversalOrder::Pre).any(|node| node.frozen) {
+            panic!("Frozen node")
+        }
+        let tokens: Vec<_> = self.subtree_tokens(arena, TraversalOrder::Pre)
+            .skip(1)
+            .collect();
+        let removed = tokens.into_iter()
+            .map(|token| match arena.allocator.remove(token) {
+                Some(node) => node.data,
+                None => panic!("Corrupt arena")
+            })
+            .collect();
+        let node = arena.node_mut(self);
+        node.first_child = None;
+        node.last_child = None;
+        removed
+    }
+
+    /// Removes every descendant of the given node whose data fails the
+    /// predicate, along with the descendants of the removed node. Nodes within
+    /// a removed subtree are not passed to the predicate. The given node itself
+    /// is always kept.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena or if a
+    /// node to be removed, any of its descendants or its parent is frozen.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1);
+    /// let two = root_token.append(&mut arena, 2);
+    /// two.append(&mut arena, 3);
+    /// let four = root_token.append(&mut arena, 4);
+    /// four.append(&mut arena, 5);
+    /// four.append(&mut arena, 6);
+    ///
+    /// root_token.prune(&mut arena, |&x| x % 2 == 0);
+    /// let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data)
+    ///     .collect();
+    /// assert_eq!(data, vec![1, 2, 4, 6]);
+    /// ```
+    pub fn prune<T, F>(self, arena: &mut Arena<T>, mut pred: F)
+        where F: FnMut(&T) -> bool {
+        let mut stack: Vec<_> = self.children_tokens(arena).collect();
+        while let Some(token) = stack.pop() {
+            match pred(&arena[token].data) {
+                true => stack.extend(token.children_tokens(arena)),
+                false => arena.uproot(token)
+            }
+        }
+    }
+
+    /// Removes all descendants of the current node.
+    pub (crate) fn remove_descendants<T>(self, arena: &mut Arena<T>) {
+        // This will not silently fail since postorder_next will panic if self
+        // isn't valid.  This won't do anything if self has no descendants, but
+        // that's the intended behavior.
+        if let (Some(mut token), mut branch) =
+            postorder_next(self, self, Branch::Child, arena) {
+            while branch != Branch::None {
+                let (t, b) = postorder_next(token, self, branch, arena);
+                arena.allocator.remove(token);  // should not fail (not here anyway)
+                token = t.unwrap();
+                branch = b;
+            }
+            let node = arena.node_mut(self);
+            node.first_child = None;
+            node.last_child = None;
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Token {
+    /// Returns a parallel iterator over the nodes in the subtree of the
+    /// current node, the current node included. The order of iteration is
+    /// unspecified. Requires the `rayon` feature.
+    ///
+    /// The tokens of the subtree are gathered up front, so this only pays off
+    /// when the work done on each node outweighs a sequential traversal.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use rayon::prelude::*;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// let child_token = root_token.append(&mut arena, 2);
+    /// child_token.append(&mut arena, 3);
+    /// arena.new_node(100);  // not part of the tree
+    ///
+    /// let sum: usize = root_token.par_subtree(&arena).map(|x| x.data).sum();
+    /// assert_eq!(sum, 6);
+    /// ```
+    pub fn par_subtree<T>(self, arena: &Arena<T>)
+        -> impl ParallelIterator<Item=&Node<T>>
+        where T: Sync {
+        let tokens: Vec<Token> = self.subtree_tokens(arena, TraversalOrder::Pre)
+            .collect();
+        tokens.into_par_iter().map(move |token| &arena[token])
+    }
+
+    /// Returns a parallel iterator over the descendants of the current node,
+    /// that is its subtree without the current node itself. The order of
+    /// iteration is unspecified. Requires the `rayon` feature.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use rayon::prelude::*;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// let child_token = root_token.append(&mut arena, 2);
+    /// child_token.append(&mut arena, 3);
+    ///
+    /// let sum: usize = root_token.par_descendants(&arena).map(|x| x.data).sum();
+    /// assert_eq!(sum, 5);
+    /// ```
+    pub fn par_descendants<T>(self, arena: &Arena<T>)
+        -> impl ParallelIterator<Item=&Node<T>>
+        where T: Sync {
+        let tokens: Vec<Token> = self.subtree_tokens(arena, TraversalOrder::Pre)
+            .skip(1)
+            .collect();
+        tokens.into_par_iter().map(move |token| &arena[token])
+    }
+
+    /// Returns a parallel iterator of mutable references to the nodes in the
+    /// subtree of the current node, the current node included. Frozen nodes
+    /// are skipped over. The order of iteration is unspecified. Requires the
+    /// `rayon` feature.
+    ///
+    /// # Panics:
+    ///
+    /// Panics if the token does not correspond to a node in the arena.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use atree::Arena;
+    /// use atree::iter::TraversalOrder;
+    /// use rayon::prelude::*;
+    ///
+    /// let (mut arena, root_token) = Arena::with_data(1usize);
+    /// let child_token = root_token.append(&mut arena, 2);
+    /// child_token.append(&mut arena, 3);
+    ///
+    /// child_token.par_subtree_mut(&mut arena).for_each(|x| x.data *= 10);
+    /// let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+    ///     .map(|x| x.data)
+    ///     .collect();
+    /// assert_eq!(data, vec![1, 20, 30]);
+    /// ```
+    pub fn par_subtree_mut<T>(self, arena: &mut Arena<T>)
+        -> impl ParallelIterator<Item=&mut Node<T>>
+        where T: Send {
+        // the cells are handed out by the allocator so that the references are
+        // disjoint by construction
+        let mut in_subtree = vec![false; arena.capacity()];
+        for token in self.subtree_tokens(arena, TraversalOrder::Pre) {
+            in_subtree[token.slot()] = true;
+        }
+        arena.allocator.par_iter_mut()
+            .filter_map(move |(token, node)| {
+                match in_subtree[token.slot()] && !node.frozen {
+                    true => Some(node),
+                    false => None
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::cognitive_complexity)]
+    fn replace_node() {
+        // root node that we will attach subtrees to
+        let root_data = "Indo-European";
+        let (mut arena, root) = Arena::with_data(root_data);
+       
+        // the Germanic branch
+        let germanic = root.append(&mut arena, "Germanic");
+        let west = germanic.append(&mut arena, "West");
+        west.append(&mut arena, "Scots");
         west.append(&mut arena, "English");
        
         // the slavic branch
@@ -1197,197 +3861,1732 @@ mod test {
         assert_eq!(iter.next(), Some("Russian"));
         assert!(iter.next().is_none());
 
-        // the Romance branch
-        let romance = arena.new_node("Romance");
-        romance.append(&mut arena, "French");
-        romance.append(&mut arena, "Italian");
-       
-        // replace_node germanic with romance
-        germanic.replace_node(&mut arena, romance).unwrap();
-       
-        let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-            .map(|x| x.data);
-        assert_eq!(iter.next(), Some("Indo-European"));
-        assert_eq!(iter.next(), Some("Romance"));
-        assert_eq!(iter.next(), Some("French"));
-        assert_eq!(iter.next(), Some("Italian"));
-        assert_eq!(iter.next(), Some("Slavic"));
-        assert_eq!(iter.next(), Some("Polish"));
-        assert_eq!(iter.next(), Some("Russian"));
-        assert!(iter.next().is_none());
+        // the Romance branch
+        let romance = arena.new_node("Romance");
+        romance.append(&mut arena, "French");
+        romance.append(&mut arena, "Italian");
+       
+        // replace_node germanic with romance
+        germanic.replace_node(&mut arena, romance).unwrap();
+       
+        let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data);
+        assert_eq!(iter.next(), Some("Indo-European"));
+        assert_eq!(iter.next(), Some("Romance"));
+        assert_eq!(iter.next(), Some("French"));
+        assert_eq!(iter.next(), Some("Italian"));
+        assert_eq!(iter.next(), Some("Slavic"));
+        assert_eq!(iter.next(), Some("Polish"));
+        assert_eq!(iter.next(), Some("Russian"));
+        assert!(iter.next().is_none());
+
+        // How about the other way around (replacing the slavic branch instead
+        slavic.replace_node(&mut arena, germanic).unwrap();
+
+        let mut iter = root.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data);
+        assert_eq!(iter.next(), Some("Indo-European"));
+        assert_eq!(iter.next(), Some("Romance"));
+        assert_eq!(iter.next(), Some("French"));
+        assert_eq!(iter.next(), Some("Italian"));
+        assert_eq!(iter.next(), Some("Germanic"));
+        assert_eq!(iter.next(), Some("West"));
+        assert_eq!(iter.next(), Some("Scots"));
+        assert_eq!(iter.next(), Some("English"));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn subtree_tokens_postord() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+       
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        let first_grandchild = first_child.append(&mut arena, 0usize);
+        let fourth_child = root_token.append(&mut arena, 5usize);
+        let second_grandchild = second_child.append(&mut arena, 10usize);
+        let third_grandchild = second_child.append(&mut arena, 20usize);
+        let great_grandchild = third_grandchild.append(&mut arena, 20usize);
+       
+        let mut subtree = root_token.subtree_tokens(&arena, TraversalOrder::Post);
+        assert_eq!(subtree.next(), Some(first_grandchild));
+        assert_eq!(subtree.next(), Some(first_child));
+        assert_eq!(subtree.next(), Some(second_grandchild));
+        assert_eq!(subtree.next(), Some(great_grandchild));
+        assert_eq!(subtree.next(), Some(third_grandchild));
+        assert_eq!(subtree.next(), Some(second_child));
+        assert_eq!(subtree.next(), Some(third_child));
+        assert_eq!(subtree.next(), Some(fourth_child));
+        assert_eq!(subtree.next(), Some(root_token));
+        assert!(subtree.next().is_none());
+       
+        let mut subtree = great_grandchild.subtree_tokens(&arena, TraversalOrder::Post);
+        assert_eq!(subtree.next(), Some(great_grandchild));
+        assert!(subtree.next().is_none());
+    }
+
+    #[test]
+    fn subtree_tokens_levelord() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+       
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        let first_grandchild = second_child.append(&mut arena, 10usize);
+        let second_grandchild = second_child.append(&mut arena, 20usize);
+        let fourth_child = root_token.append(&mut arena, 5usize);
+       
+        let mut subtree = root_token.subtree_tokens(&arena, TraversalOrder::Level);
+        assert_eq!(subtree.next(), Some(root_token));
+        assert_eq!(subtree.next(), Some(first_child));
+        assert_eq!(subtree.next(), Some(second_child));
+        assert_eq!(subtree.next(), Some(third_child));
+        assert_eq!(subtree.next(), Some(fourth_child));
+        assert_eq!(subtree.next(), Some(first_grandchild));
+        assert_eq!(subtree.next(), Some(second_grandchild));
+        assert!(subtree.next().is_none());
+       
+        let mut subtree = second_grandchild.subtree_tokens(&arena, TraversalOrder::Level);
+        assert_eq!(subtree.next(), Some(second_grandchild));
+        assert!(subtree.next().is_none());
+    }
+
+    #[test]
+    fn subtree_postord() {
+        let root_data = "Indo-European";
+        let (mut arena, root_token) = Arena::with_data(root_data);
+       
+        root_token.append(&mut arena, "Romance");
+        root_token.append(&mut arena, "Germanic");
+        let third_child = root_token.append(&mut arena, "Celtic");
+        root_token.append(&mut arena, "Slavic");
+        third_child.append(&mut arena, "Ulster");
+        third_child.append(&mut arena, "Gaulish");
+       
+        let mut subtree = root_token.subtree(&arena, TraversalOrder::Post);
+        assert_eq!(subtree.next().unwrap().data, "Romance");
+        assert_eq!(subtree.next().unwrap().data, "Germanic");
+        assert_eq!(subtree.next().unwrap().data, "Ulster");
+        assert_eq!(subtree.next().unwrap().data, "Gaulish");
+        assert_eq!(subtree.next().unwrap().data, "Celtic");
+        assert_eq!(subtree.next().unwrap().data, "Slavic");
+        assert_eq!(subtree.next().unwrap().data, "Indo-European");
+        assert!(subtree.next().is_none());
+    }
+
+    #[test]
+    fn subtree_levelord() {
+        let root_data = "Indo-European";
+        let (mut arena, root_token) = Arena::with_data(root_data);
+       
+        root_token.append(&mut arena, "Romance");
+        root_token.append(&mut arena, "Germanic");
+        let third_child = root_token.append(&mut arena, "Slavic");
+        root_token.append(&mut arena, "Hellenic");
+        third_child.append(&mut arena, "Russian");
+        third_child.append(&mut arena, "Ukrainian");
+       
+        let mut subtree = root_token.subtree(&arena, TraversalOrder::Level);
+        assert_eq!(subtree.next().unwrap().data, "Indo-European");
+        assert_eq!(subtree.next().unwrap().data, "Romance");
+        assert_eq!(subtree.next().unwrap().data, "Germanic");
+        assert_eq!(subtree.next().unwrap().data, "Slavic");
+        assert_eq!(subtree.next().unwrap().data, "Hellenic");
+        assert_eq!(subtree.next().unwrap().data, "Russian");
+        assert_eq!(subtree.next().unwrap().data, "Ukrainian");
+        assert!(subtree.next().is_none());
+    }
+
+    #[test]
+    fn subtree_postord_mut() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+       
+        root_token.append(&mut arena, 2usize);
+        root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        root_token.append(&mut arena, 5usize);
+        third_child.append(&mut arena, 10usize);
+        third_child.append(&mut arena, 20usize);
+       
+        for x in root_token.subtree_mut(&mut arena, TraversalOrder::Post) {
+            x.data += 100;
+        }
+       
+        let mut subtree = root_token.subtree(&arena, TraversalOrder::Post);
+        assert_eq!(subtree.next().unwrap().data, 102);
+        assert_eq!(subtree.next().unwrap().data, 103);
+        assert_eq!(subtree.next().unwrap().data, 110);
+        assert_eq!(subtree.next().unwrap().data, 120);
+        assert_eq!(subtree.next().unwrap().data, 104);
+        assert_eq!(subtree.next().unwrap().data, 105);
+        assert_eq!(subtree.next().unwrap().data, 101);
+        assert!(subtree.next().is_none());
+    }
+
+    #[test]
+    fn subtree_levelord_mut() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+       
+        root_token.append(&mut arena, 2usize);
+        root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        root_token.append(&mut arena, 5usize);
+        third_child.append(&mut arena, 10usize);
+        third_child.append(&mut arena, 20usize);
+       
+        for x in root_token.subtree_mut(&mut arena, TraversalOrder::Level) {
+            x.data += 100;
+        }
+       
+        let mut subtree = root_token.subtree(&arena, TraversalOrder::Level);
+        assert_eq!(subtree.next().unwrap().data, 101);
+        assert_eq!(subtree.next().unwrap().data, 102);
+        assert_eq!(subtree.next().unwrap().data, 103);
+        assert_eq!(subtree.next().unwrap().data, 104);
+        assert_eq!(subtree.next().unwrap().data, 105);
+        assert_eq!(subtree.next().unwrap().data, 110);
+        assert_eq!(subtree.next().unwrap().data, 120);
+        assert!(subtree.next().is_none());
+    }
+
+    #[test]
+    fn nodes_at_depth() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        let first_grandchild = first_child.append(&mut arena, 10usize);
+        let second_grandchild = third_child.append(&mut arena, 20usize);
+        let third_grandchild = third_child.append(&mut arena, 30usize);
+        second_grandchild.append(&mut arena, 100usize);
+        second_child.append(&mut arena, 40usize);
+
+        let depth2 = root_token.nodes_at_depth(&arena, 2);
+        let data: Vec<_> = depth2.iter().map(|&t| arena[t].data).collect();
+        assert_eq!(&[10, 40, 20, 30], &data[..]);
+        assert_eq!(depth2[0], first_grandchild);
+        assert_eq!(depth2[3], third_grandchild);
+
+        let depth1 = third_child.nodes_at_depth(&arena, 1);
+        assert_eq!(depth1, vec![second_grandchild, third_grandchild]);
+        assert!(first_grandchild.nodes_at_depth(&arena, 5).is_empty());
+    }
+
+    #[test]
+    fn append_children() {
+        let (mut arena, root) = Arena::with_data(0usize);
+        root.append(&mut arena, 1);
+        let tokens = root.append_children(&mut arena, 2..7);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(arena.node_count(), 7);
+        assert_eq!(arena.capacity(), 7);
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(arena[*token].data, i + 2);
+        }
+        let data: Vec<_> = root.children(&arena).map(|x| x.data).collect();
+        assert_eq!(data, vec![1, 2, 3, 4, 5, 6]);
+        assert!(arena.validate().is_ok());
+
+        let leaf = tokens[0];
+        let empty = leaf.append_children(&mut arena, Vec::new());
+        assert!(empty.is_empty());
+        assert!(leaf.is_leaf(&arena));
+        assert_eq!(arena.node_count(), 7);
+    }
+
+    #[test]
+    fn try_append() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let child = root_token.try_append(&mut arena, 2usize).unwrap();
+        let grandchild = child.try_append(&mut arena, 3usize).unwrap();
+        assert_eq!(arena[grandchild].parent(), Some(child));
+        assert_eq!(arena.node_count(), 3);
+
+        // removed node
+        arena.uproot(grandchild);
+        match grandchild.try_append(&mut arena, 4usize) {
+            Err(Error::InvalidToken) => (),
+            _ => panic!("appended to a removed node")
+        }
+
+        // out-of-range token
+        let token = Token::new(NonZeroUsize::new(100).unwrap(), 0);
+        match token.try_append(&mut arena, 5usize) {
+            Err(Error::InvalidToken) => (),
+            _ => panic!("appended to an out-of-range token")
+        }
+        assert_eq!(arena.node_count(), 2);
+    }
+
+    #[test]
+    fn is_perfect_and_complete() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let first_grandchild = first_child.append(&mut arena, 4usize);
+        first_child.append(&mut arena, 5usize);
+        second_child.append(&mut arena, 6usize);
+        let fourth_grandchild = second_child.append(&mut arena, 7usize);
+        assert!(root_token.is_perfect(&arena));
+        assert!(root_token.is_complete(&arena));
+
+        // missing the rightmost leaf
+        arena.uproot(fourth_grandchild);
+        assert!(!root_token.is_perfect(&arena));
+        assert!(root_token.is_complete(&arena));
+
+        // missing the leftmost leaf
+        second_child.append(&mut arena, 7usize);
+        arena.uproot(first_grandchild);
+        assert!(!root_token.is_perfect(&arena));
+        assert!(!root_token.is_complete(&arena));
+
+        // a lone node is both
+        assert!(second_child.is_perfect(&arena));
+        let leaf = arena.new_node(8usize);
+        assert!(leaf.is_perfect(&arena));
+        assert!(leaf.is_complete(&arena));
+    }
+
+    #[test]
+    fn move_to() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        let first_grandchild = first_child.append(&mut arena, 10usize);
+        let second_grandchild = first_child.append(&mut arena, 20usize);
+        let great_grandchild = second_grandchild.append(&mut arena, 100usize);
+        let data = |arena: &Arena<usize>, token: Token| -> Vec<_> {
+            token.subtree(arena, TraversalOrder::Pre).map(|x| x.data).collect()
+        };
+
+        // a leaf
+        first_grandchild.move_to(&mut arena, third_child).unwrap();
+        assert_eq!(&[1, 2, 20, 100, 3, 4, 10], &data(&arena, root_token)[..]);
+
+        // an internal node
+        first_child.move_to(&mut arena, second_child).unwrap();
+        assert_eq!(&[1, 3, 2, 20, 100, 4, 10], &data(&arena, root_token)[..]);
+        assert_eq!(arena[root_token].first_child(), Some(second_child));
+        assert_eq!(arena[second_child].previous_sibling(), None);
+
+        // under a new root
+        let new_root = arena.new_node(5usize);
+        second_grandchild.move_to(&mut arena, new_root).unwrap();
+        assert_eq!(&[1, 3, 2, 4, 10], &data(&arena, root_token)[..]);
+        assert_eq!(&[5, 20, 100], &data(&arena, new_root)[..]);
+        assert!(arena.validate().is_ok());
+
+        // cycles
+        match new_root.move_to(&mut arena, great_grandchild) {
+            Err(Error::CyclicGraph) => (),
+            _ => panic!("moved a node under its own descendant")
+        }
+        assert!(new_root.move_to(&mut arena, new_root).is_err());
+        assert_eq!(&[5, 20, 100], &data(&arena, new_root)[..]);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn inorder_successor_and_predecessor() {
+        let (mut arena, eight) = Arena::with_data(8usize);
+        let four = eight.append(&mut arena, 4usize);
+        let twelve = eight.append(&mut arena, 12usize);
+        let two = four.append(&mut arena, 2usize);
+        let six = four.append(&mut arena, 6usize);
+        let ten = twelve.append(&mut arena, 10usize);
+        let fourteen = twelve.append(&mut arena, 14usize);
+        let one = two.append(&mut arena, 1usize);
+        two.append(&mut arena, 3usize);
+        six.append(&mut arena, 5usize);
+        six.append(&mut arena, 7usize);
+        ten.append(&mut arena, 9usize);
+        ten.append(&mut arena, 11usize);
+        fourteen.append(&mut arena, 13usize);
+
+        let mut forward = vec![arena[one].data];
+        let mut token = one;
+        while let Some(next) = token.inorder_successor(&arena) {
+            forward.push(arena[next].data);
+            token = next;
+        }
+        assert_eq!((1..=14).collect::<Vec<_>>(), forward);
+
+        let mut backward = vec![arena[fourteen].data];
+        let mut token = fourteen;
+        while let Some(previous) = token.inorder_predecessor(&arena) {
+            backward.push(arena[previous].data);
+            token = previous;
+        }
+        assert_eq!((1..=14).rev().collect::<Vec<_>>(), backward);
+    }
+
+    #[test]
+    fn subtree_mut_doubles_subtree() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        root_token.append(&mut arena, 4usize);
+        let grandchild = second_child.append(&mut arena, 10usize);
+        second_child.append(&mut arena, 20usize);
+        grandchild.append(&mut arena, 100usize);
+
+        for order in &[TraversalOrder::Pre, TraversalOrder::Post, TraversalOrder::Level] {
+            let mut tokens: Vec<_> = second_child.subtree_mut(&mut arena, *order)
+                .map(|x| { x.data *= 2; x.token() })
+                .collect();
+            assert_eq!(tokens.len(), 4);
+            tokens.sort_by_key(|t| t.index);
+            tokens.dedup();
+            assert_eq!(tokens.len(), 4);  // no node is visited twice
+        }
+
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(&[1, 2, 24, 80, 800, 160, 4], &data[..]);
+    }
+
+    #[test]
+    fn leaves() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        first_child.append(&mut arena, 10usize);
+        let second_grandchild = second_child.append(&mut arena, 20usize);
+        second_child.append(&mut arena, 30usize);
+        second_child.append(&mut arena, 40usize);
+        second_grandchild.append(&mut arena, 200usize);
+        second_grandchild.append(&mut arena, 300usize);
+
+        let data: Vec<_> = root_token.leaves(&arena).map(|x| x.data).collect();
+        assert_eq!(&[10, 200, 300, 30, 40, 4], &data[..]);
+        let tokens: Vec<_> = root_token.leaves_tokens(&arena).collect();
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[5], third_child);
+        assert!(tokens.iter().all(|&t| t.is_leaf(&arena)));
+
+        let data: Vec<_> = second_child.leaves(&arena).map(|x| x.data).collect();
+        assert_eq!(&[200, 300, 30, 40], &data[..]);
+        let mut leaves = third_child.leaves_tokens(&arena);
+        assert_eq!(leaves.next(), Some(third_child));
+        assert!(leaves.next().is_none());
+    }
+
+    #[test]
+    fn descendant_count() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let leaf = first_child.append(&mut arena, 10usize);
+        let grandchild = second_child.append(&mut arena, 20usize);
+        second_child.append(&mut arena, 30usize);
+        grandchild.append(&mut arena, 200usize);
+
+        assert_eq!(root_token.descendant_count(&arena), 6);
+        assert_eq!(root_token.subtree_size(&arena), 7);
+        assert_eq!(second_child.descendant_count(&arena), 3);
+        assert_eq!(second_child.subtree_size(&arena), 4);
+        assert_eq!(leaf.descendant_count(&arena), 0);
+        assert_eq!(leaf.subtree_size(&arena), 1);
+        assert_eq!(root_token.subtree_size(&arena),
+                   root_token.subtree_tokens(&arena, TraversalOrder::Pre).count());
+
+        // deep trees do not overflow the stack
+        let mut token = leaf;
+        for i in 0..100_000 { token = token.append(&mut arena, i); }
+        assert_eq!(first_child.descendant_count(&arena), 100_001);
+    }
+
+    #[test]
+    fn duplicate() {
+        let root_data = "a";
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, "b");
+        let last_child = root_token.append(&mut arena, "c");
+        first_child.append(&mut arena, "d");
+
+        let copy = first_child.duplicate(&mut arena);
+        assert_eq!(arena[copy].data, arena[first_child].data);
+        assert_eq!(arena[copy].parent(), Some(root_token));
+        assert_eq!(arena[first_child].next_sibling(), Some(copy));
+        assert_eq!(arena[copy].next_sibling(), Some(last_child));
+        assert!(copy.is_leaf(&arena));
+        let data: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+        assert_eq!(data, vec!["b", "b", "c"]);
+
+        let last_copy = last_child.duplicate(&mut arena);
+        assert_eq!(arena[last_child].next_sibling(), Some(last_copy));
+        assert!(arena[last_copy].next_sibling().is_none());
+
+        let root_copy = root_token.duplicate(&mut arena);
+        assert_eq!(arena[root_copy].data, "a");
+        assert!(arena[root_copy].parent().is_none());
+        assert!(arena[root_token].next_sibling().is_none());
+        assert!(root_copy.is_leaf(&arena));
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn to_nested() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        first_child.append(&mut arena, 10usize);
+        let grandchild = second_child.append(&mut arena, 20usize);
+        second_child.append(&mut arena, 30usize);
+        grandchild.append(&mut arena, 200usize);
+        grandchild.append(&mut arena, 300usize);
+
+        let leaf = |data| RecursiveTree { data, children: vec![] };
+        let expected = RecursiveTree {
+            data: 3,
+            children: vec![
+                RecursiveTree { data: 20, children: vec![leaf(200), leaf(300)] },
+                leaf(30)
+            ]
+        };
+        assert_eq!(second_child.to_nested(&arena), expected);
+        assert_eq!(first_child.to_nested(&arena).children, vec![leaf(10)]);
+
+        let tree = root_token.to_nested(&arena);
+        assert_eq!(tree.data, 1);
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.children[1], expected);
+
+        // round trip through a fresh arena
+        let (new_arena, new_root) = Arena::from_nested(tree.clone());
+        assert_eq!(new_root.to_nested(&new_arena), tree);
+        assert!(root_token.eq_subtree(&arena, new_root, &new_arena));
+    }
+
+    #[test]
+    fn following_and_preceding() {
+        //         1
+        //      /  |  \
+        //     2   3   4
+        //    / \  |   |
+        //   5   6 7   8
+        //       |
+        //       9
+        let root_data = 1usize;
+        let (mut arena, n1) = Arena::with_data(root_data);
+        let n2 = n1.append(&mut arena, 2usize);
+        let n3 = n1.append(&mut arena, 3usize);
+        let n4 = n1.append(&mut arena, 4usize);
+        let n5 = n2.append(&mut arena, 5usize);
+        let n6 = n2.append(&mut arena, 6usize);
+        let n7 = n3.append(&mut arena, 7usize);
+        let n8 = n4.append(&mut arena, 8usize);
+        let n9 = n6.append(&mut arena, 9usize);
+        // other trees in the arena are not part of the document
+        let other = arena.new_node(10usize);
+        other.append(&mut arena, 11usize);
+
+        let following = |t: Token| -> Vec<usize> {
+            t.following(&arena).map(|x| x.data).collect()
+        };
+        let preceding = |t: Token| -> Vec<usize> {
+            t.preceding(&arena).map(|x| x.data).collect()
+        };
+
+        assert!(following(n1).is_empty());
+        assert_eq!(following(n2), vec![3, 7, 4, 8]);
+        assert_eq!(following(n5), vec![6, 9, 3, 7, 4, 8]);
+        assert_eq!(following(n6), vec![3, 7, 4, 8]);
+        assert_eq!(following(n9), vec![3, 7, 4, 8]);
+        assert_eq!(following(n7), vec![4, 8]);
+        assert!(following(n8).is_empty());
+
+        assert!(preceding(n1).is_empty());
+        assert!(preceding(n2).is_empty());
+        assert!(preceding(n5).is_empty());
+        assert_eq!(preceding(n6), vec![5]);
+        assert_eq!(preceding(n9), vec![5]);
+        assert_eq!(preceding(n3), vec![2, 5, 6, 9]);
+        assert_eq!(preceding(n7), vec![2, 5, 6, 9]);
+        assert_eq!(preceding(n8), vec![2, 5, 6, 9, 3, 7]);
+        assert_eq!(n8.preceding_tokens(&arena).last(), Some(n7));
+
+        assert!(following(other).is_empty());
+        assert!(following(n4).is_empty());
+        assert!(preceding(other).is_empty());
+    }
+
+    #[test]
+    fn sort_children() {
+        let root_data = 0usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+        let tokens = root_token.append_children(&mut arena, vec![5, 4, 3, 2, 1]);
+        tokens[2].append(&mut arena, 30);
+
+        root_token.sort_children(&mut arena);
+        let data: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+        assert_eq!(data, vec![1, 2, 3, 4, 5]);
+        let data: Vec<_> = tokens[0].preceding_siblings(&arena)
+            .map(|x| x.data).collect();
+        assert_eq!(data, vec![4, 3, 2, 1]);
+        assert_eq!(arena[root_token].first_child(), Some(tokens[4]));
+        assert!(arena[tokens[4]].previous_sibling().is_none());
+        assert!(arena[tokens[0]].next_sibling().is_none());
+        assert_eq!(tokens[2].children(&arena).next().unwrap().data, 30);
+        assert!(arena.validate().is_ok());
+
+        // the sort is stable
+        let (mut arena, root_token) = Arena::with_data((0usize, 'x'));
+        root_token.append_children(&mut arena, vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')]);
+        root_token.sort_children_by(&mut arena, |a, b| a.0.cmp(&b.0));
+        let data: Vec<_> = root_token.children(&arena).map(|x| x.data.1).collect();
+        assert_eq!(data, vec!['b', 'd', 'a', 'c']);
+
+        // sorting leaves is a no-op
+        let leaf = arena[root_token].first_child().unwrap();
+        leaf.sort_children(&mut arena);
+        assert!(leaf.is_leaf(&arena));
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn clone_subtree() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let grandchild = first_child.append(&mut arena, 4usize);
+        first_child.append(&mut arena, 5usize);
+        grandchild.append(&mut arena, 6usize);
+        grandchild.append(&mut arena, 7usize);
+
+        let preorder = |arena: &Arena<usize>, token: Token| -> Vec<usize> {
+            token.subtree(arena, TraversalOrder::Pre).map(|x| x.data).collect()
+        };
+        let original = preorder(&arena, first_child);
+
+        let copy = first_child.clone_subtree(&mut arena, Some(second_child));
+        assert_eq!(preorder(&arena, copy), original);
+        assert_eq!(preorder(&arena, first_child), original);
+        assert_eq!(arena[copy].parent(), Some(second_child));
+        assert_eq!(arena.node_count(), 12);
+        assert!(arena.validate().is_ok());
+
+        // the copy is independent of the original
+        for node in copy.subtree_mut(&mut arena, TraversalOrder::Pre) {
+            node.data *= 10;
+        }
+        let grandchild_copy = arena[copy].first_child().unwrap();
+        grandchild_copy.append(&mut arena, 80);
+        assert_eq!(preorder(&arena, first_child), original);
+        assert_eq!(preorder(&arena, copy), vec![20, 40, 60, 70, 80, 50]);
+
+        // copy as a tree of its own and into its own subtree
+        let standalone = first_child.clone_subtree(&mut arena, None);
+        assert!(arena[standalone].parent().is_none());
+        assert!(arena[standalone].next_sibling().is_none());
+        assert_eq!(preorder(&arena, standalone), original);
+        let nested = first_child.clone_subtree(&mut arena, Some(grandchild));
+        assert_eq!(preorder(&arena, nested), original);
+        assert_eq!(preorder(&arena, first_child),
+                   vec![2, 4, 6, 7, 2, 4, 6, 7, 5, 5]);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn fold() {
+        let root_data = 1usize;
+        let (mut arena, root_token) = Arena::with_data(root_data);
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        let grandchild = first_child.append(&mut arena, 4usize);
+        first_child.append(&mut arena, 5usize);
+        grandchild.append(&mut arena, 6usize);
+        second_child.append(&mut arena, 7usize);
+
+        let sum = root_token.fold(&arena, |&data, sums: Vec<usize>| {
+            data + sums.iter().sum::<usize>()
+        });
+        assert_eq!(sum, 28);
+        let leaf_sum = grandchild.fold(&arena, |&data, sums: Vec<usize>| {
+            data + sums.iter().sum::<usize>()
+        });
+        assert_eq!(leaf_sum, 10);
+
+        let repr = root_token.fold(&arena, |data, children: Vec<String>| {
+            match children.is_empty() {
+                true => data.to_string(),
+                false => format!("({} {})", data, children.join(" "))
+            }
+        });
+        assert_eq!(repr, "(1 (2 (4 6) 5) (3 7))");
+
+        // deep trees do not overflow the stack
+        let mut token = second_child;
+        for _ in 0..100_000 { token = token.append(&mut arena, 1usize); }
+        let depth = root_token.fold(&arena, |_, depths: Vec<usize>| {
+            depths.into_iter().max().unwrap_or(0) + 1
+        });
+        assert_eq!(depth, 100_002);
+    }
+
+    #[test]
+    fn eq_subtree() {
+        let build = |leaf: usize, extra: bool| {
+            let (mut arena, root) = Arena::with_data(1usize);
+            let first_child = root.append(&mut arena, 2usize);
+            let second_child = root.append(&mut arena, 3usize);
+            first_child.append(&mut arena, 4usize);
+            first_child.append(&mut arena, leaf);
+            second_child.append(&mut arena, 6usize);
+            if extra { second_child.append(&mut arena, 7usize); }
+            (arena, root)
+        };
+
+        let (arena1, root1) = build(5, false);
+        let (arena2, root2) = build(5, false);
+        let (arena3, root3) = build(50, false);
+        let (arena4, root4) = build(5, true);
+
+        assert!(root1.eq_subtree(&arena1, root2, &arena2));
+        assert!(root1.eq_subtree(&arena1, root1, &arena1));
+        assert!(!root1.eq_subtree(&arena1, root3, &arena3));
+        assert!(!root1.eq_subtree(&arena1, root4, &arena4));
+        assert!(!root4.eq_subtree(&arena4, root1, &arena1));
+
+        // subtrees of the same arena
+        let (mut arena, root) = build(5, false);
+        let copy = root.clone_subtree(&mut arena, Some(root));
+        assert!(root.eq_subtree(&arena1, copy, &arena));
+        let first_child = arena[root].first_child().unwrap();
+        assert!(!root.eq_subtree_in(&arena, copy));
+        assert!(first_child.eq_subtree_in(&arena, arena[copy].first_child().unwrap()));
+        assert!(!first_child.eq_subtree_in(&arena, copy));
+    }
+
+    #[test]
+    fn hash_subtree() {
+        use std::collections::HashSet;
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |arena: &Arena<usize>, token: Token| {
+            let mut hasher = DefaultHasher::new();
+            token.hash_subtree(arena, &mut hasher);
+            hasher.finish()
+        };
+
+        // 0 -> [1 -> [2, 3], 1 -> [2, 3], 1 -> [2 -> [3]], 1 -> [2, 3, 4]]
+        let (mut arena, root) = Arena::with_data(0usize);
+        let a = root.append(&mut arena, 1usize);
+        a.append_children(&mut arena, vec![2, 3]);
+        let b = root.append(&mut arena, 1usize);
+        b.append_children(&mut arena, vec![2, 3]);
+        let c = root.append(&mut arena, 1usize);
+        c.append(&mut arena, 2usize).append(&mut arena, 3usize);
+        let d = root.append(&mut arena, 1usize);
+        d.append_children(&mut arena, vec![2, 3, 4]);
+
+        assert!(a.eq_subtree_in(&arena, b));
+        let mut hashes = HashSet::new();
+        assert!(hashes.insert(hash(&arena, a)));
+        assert!(!hashes.insert(hash(&arena, b)));
+        // same data in pre-order but a different shape
+        assert!(hashes.insert(hash(&arena, c)));
+        assert!(hashes.insert(hash(&arena, d)));
+        assert_eq!(hashes.len(), 3);
+
+        // consistent across arenas
+        let (arena2, copy) = arena.clone().split_off(a);
+        assert_eq!(hash(&arena2, copy), hash(&arena, a));
+    }
+
+    #[test]
+    fn leaf_sequence() {
+        let mut arena = Arena::new();
+
+        // 1 -> [2 -> [10, 20], 30, 3 -> [4 -> [40]]]
+        let first = arena.new_node(1usize);
+        let two = first.append(&mut arena, 2);
+        two.append(&mut arena, 10);
+        two.append(&mut arena, 20);
+        first.append(&mut arena, 30);
+        let three = first.append(&mut arena, 3);
+        let four = three.append(&mut arena, 4);
+        four.append(&mut arena, 40);
+
+        // 5 -> [10, 6 -> [20, 30, 40]]
+        let second = arena.new_node(5usize);
+        second.append(&mut arena, 10);
+        let six = second.append(&mut arena, 6);
+        six.append_children(&mut arena, vec![20, 30, 40]);
+
+        assert_eq!(first.leaf_sequence(&arena), vec![&10, &20, &30, &40]);
+        assert_eq!(six.leaf_sequence(&arena), vec![&20, &30, &40]);
+        assert!(first.leaf_sequence_eq(&arena, second));
+        assert!(second.leaf_sequence_eq(&arena, first));
+        assert!(!first.leaf_sequence_eq(&arena, six));
+        assert!(!six.leaf_sequence_eq(&arena, first));
+        assert!(four.leaf_sequence_eq(&arena, three));
+    }
+
+    #[test]
+    fn children_rev() {
+        fn check(arena: &Arena<usize>, token: Token) {
+            let mut forward: Vec<_> = token.children_tokens(arena).collect();
+            let backward: Vec<_> = token.children_rev_tokens(arena).collect();
+            forward.reverse();
+            assert_eq!(forward, backward);
+            let data: Vec<_> = token.children_rev(arena).map(|n| n.data).collect();
+            let expected: Vec<_> = forward.iter().map(|&t| arena[t].data).collect();
+            assert_eq!(data, expected);
+            assert!(arena.validate().is_ok());
+        }
+
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        assert!(root_token.children_rev_tokens(&arena).next().is_none());
+
+        let first_child = root_token.append(&mut arena, 2usize);
+        let second_child = root_token.append(&mut arena, 3usize);
+        check(&arena, root_token);
+
+        let third_child = second_child.insert_after(&mut arena, 4usize);
+        first_child.insert_before(&mut arena, 5usize);
+        first_child.append_children(&mut arena, vec![6, 7, 8]);
+        check(&arena, root_token);
+        check(&arena, first_child);
+        assert_eq!(arena[root_token].last_child(), Some(third_child));
+
+        third_child.detach(&mut arena);
+        assert_eq!(arena[root_token].last_child(), Some(second_child));
+        check(&arena, root_token);
+
+        second_child.replace_node(&mut arena, third_child).unwrap();
+        assert_eq!(arena[root_token].last_child(), Some(third_child));
+        check(&arena, root_token);
+
+        second_child.move_to(&mut arena, first_child).unwrap();
+        check(&arena, first_child);
+        first_child.sort_children_by(&mut arena, |a, b| b.cmp(a));
+        check(&arena, first_child);
+
+        first_child.remove_descendants(&mut arena);
+        check(&arena, first_child);
+        check(&arena, root_token);
+    }
+
+    #[test]
+    fn children_double_ended() {
+        for count in 0..6usize {
+            let (mut arena, root_token) = Arena::with_data(0usize);
+            let children = root_token.append_children(&mut arena, 1..=count);
+
+            let mut reversed = children.clone();
+            reversed.reverse();
+            let rev: Vec<_> = root_token.children_tokens(&arena).rev().collect();
+            assert_eq!(rev, reversed);
+            let rev: Vec<_> = root_token.children(&arena).rev()
+                .map(|n| n.data)
+                .collect();
+            assert_eq!(rev, (1..=count).rev().collect::<Vec<_>>());
+
+            // alternate between both ends
+            let mut iter = root_token.children_tokens(&arena);
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            loop {
+                match iter.next() {
+                    None => break,
+                    Some(t) => front.push(t)
+                }
+                match iter.next_back() {
+                    None => break,
+                    Some(t) => back.push(t)
+                }
+            }
+            assert!(iter.next().is_none());
+            assert!(iter.next_back().is_none());
+            back.reverse();
+            front.extend(back);
+            assert_eq!(front, children);
+        }
+
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        root_token.append_children(&mut arena, 1..=5usize);
+        let mut iter = root_token.children(&arena).map(|n| n.data);
+        assert_eq!(iter.next_back(), Some(5));
+        assert_eq!(iter.next_back(), Some(4));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next_back(), Some(3));
+        assert_eq!(iter.next(), Some(2));
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn predicates() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        assert!(root_token.is_root(&arena));
+        assert!(root_token.is_leaf(&arena));
+        assert!(!root_token.is_only_child(&arena));
+
+        let only_child = root_token.append(&mut arena, 2usize);
+        assert!(root_token.is_root(&arena));
+        assert!(!root_token.is_leaf(&arena));
+        assert!(only_child.is_only_child(&arena));
+        assert!(only_child.is_leaf(&arena));
+        assert!(!only_child.is_root(&arena));
+
+        let second_child = root_token.append(&mut arena, 3usize);
+        let third_child = root_token.append(&mut arena, 4usize);
+        for &child in [only_child, second_child, third_child].iter() {
+            assert!(!child.is_only_child(&arena));
+            assert!(!child.is_root(&arena));
+        }
+
+        second_child.detach(&mut arena);
+        third_child.detach(&mut arena);
+        assert!(only_child.is_only_child(&arena));
+        assert!(second_child.is_root(&arena));
+        assert!(!second_child.is_only_child(&arena));
+    }
+
+    #[test]
+    fn entries() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let children = root_token.append_children(&mut arena, 2..5usize);
+        children[1].append_children(&mut arena, 10..13usize);
+
+        for &order in [TraversalOrder::Pre, TraversalOrder::Post,
+                       TraversalOrder::Level].iter() {
+            let tokens: Vec<_> = root_token.subtree_tokens(&arena, order)
+                .collect();
+            let entries: Vec<_> = root_token.subtree_entries(&arena, order)
+                .collect();
+            assert_eq!(tokens.len(), entries.len());
+            for (&token, &(t, node)) in tokens.iter().zip(entries.iter()) {
+                assert_eq!(token, t);
+                assert_eq!(node.token(), t);
+                assert!(core::ptr::eq(&arena[t], node));
+            }
+        }
+
+        let tokens: Vec<_> = root_token.children_entries(&arena)
+            .map(|(t, node)| {
+                assert!(core::ptr::eq(&arena[t], node));
+                t
+            })
+            .collect();
+        assert_eq!(tokens, children);
+        let last = root_token.children_entries(&arena).next_back();
+        assert_eq!(last.map(|(t, node)| (t, node.data)), Some((children[2], 4)));
+    }
+
+    #[test]
+    fn zip_subtree() {
+        let build = |extra: bool| {
+            let (mut arena, root) = Arena::with_data(1usize);
+            let children = root.append_children(&mut arena, 2..5usize);
+            children[0].append_children(&mut arena, 5..7usize);
+            children[2].append(&mut arena, 7usize);
+            if extra { children[1].append(&mut arena, 8usize); }
+            (arena, root)
+        };
+        let (arena, root) = build(false);
+        let (other_arena, other_root) = build(false);
+        let other_arena = other_arena.map_with_token(|_, x| x.to_string());
+
+        for &order in [TraversalOrder::Pre, TraversalOrder::Post,
+                       TraversalOrder::Level].iter() {
+            let pairs: Vec<_> = root.zip_subtree(&arena, other_root,
+                                                 &other_arena, order)
+                .map(|(a, b)| (a.data, b.data.clone()))
+                .collect();
+            let expected: Vec<_> = root.subtree(&arena, order)
+                .map(|n| (n.data, n.data.to_string()))
+                .collect();
+            assert_eq!(pairs, expected);
+        }
+
+        // the two trees diverge at the node with data 3
+        let (other_arena, other_root) = build(true);
+        let zip = |order| root.zip_subtree(&arena, other_root, &other_arena,
+                                           order)
+            .map(|(a, b)| { assert_eq!(a.data, b.data); a.data })
+            .collect::<Vec<_>>();
+        assert_eq!(zip(TraversalOrder::Pre), vec![1, 2, 5, 6]);
+        assert_eq!(zip(TraversalOrder::Level), vec![1, 2]);
+        assert_eq!(zip(TraversalOrder::Post), vec![5, 6, 2]);
+    }
+
+    #[test]
+    fn descendants_at_depth() {
+        // 1 ── 2 ── 5 ── 8
+        //  ├── 3
+        //  └── 4 ── 6
+        //       └── 7 ── 9
+        //                └── 10
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let children = root_token.append_children(&mut arena, 2..5usize);
+        let n5 = children[0].append(&mut arena, 5usize);
+        children[2].append_children(&mut arena, 6..8usize);
+        n5.append(&mut arena, 8usize);
+        let n7 = arena[children[2]].last_child().unwrap();
+        n7.append_children(&mut arena, 9..11usize);
+
+        let at = |token: Token, depth| token.descendants_at_depth(&arena, depth)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+        assert_eq!(at(root_token, 0), vec![1]);
+        assert_eq!(at(root_token, 1), vec![2, 3, 4]);
+        assert_eq!(at(root_token, 2), vec![5, 6, 7]);
+        assert_eq!(at(root_token, 3), vec![8, 9, 10]);
+        assert!(at(root_token, 4).is_empty());
+        assert!(at(root_token, 100).is_empty());
+        assert_eq!(at(children[2], 2), vec![9, 10]);
+        assert!(at(children[1], 1).is_empty());
+
+        let tokens: Vec<_> = root_token.descendants_at_depth_tokens(&arena, 1)
+            .collect();
+        assert_eq!(tokens, children);
+    }
+
+    #[test]
+    fn map_subtree() {
+        let (mut arena, root_token) = Arena::with_data(1i32);
+        let children = root_token.append_children(&mut arena, 2..5i32);
+        children[0].append_children(&mut arena, 5..7i32);
+        children[0].append(&mut arena, 7).append(&mut arena, 8);
+        children[2].append(&mut arena, 9);
+        children[1].insert_after(&mut arena, 10);
+
+        let (new_arena, new_root) = root_token.map_subtree(&arena, |x| x.to_string());
+        assert_eq!(new_arena.node_count(), arena.node_count());
+        assert!(new_arena.validate().is_ok());
+
+        fn shape<T>(token: Token, arena: &Arena<T>) -> Vec<(usize, bool)> {
+            token.subtree_tokens(arena, TraversalOrder::Pre)
+                .map(|t| (t.ancestors_tokens(arena).count(), arena[t].is_leaf()))
+                .collect()
+        }
+        assert_eq!(shape(root_token, &arena), shape(new_root, &new_arena));
+        let original: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data.to_string())
+            .collect();
+        let mapped: Vec<_> = new_root.subtree(&new_arena, TraversalOrder::Pre)
+            .map(|x| x.data.clone())
+            .collect();
+        assert_eq!(original, mapped);
+
+        let (leaf_arena, leaf) = children[1].map_subtree(&arena, |x| x * 2);
+        assert_eq!(leaf_arena.node_count(), 1);
+        assert_eq!(leaf_arena[leaf].data, 6);
+    }
+
+    #[test]
+    fn common_ancestors() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let children = root_token.append_children(&mut arena, 2..4usize);
+        let grandchild = children[0].append(&mut arena, 4usize);
+        let great_grandchild = grandchild.append(&mut arena, 5usize);
+        let other_root = arena.new_node(6usize);
+
+        let common = |a: Token, b: Token| a.common_ancestors(&arena, b)
+            .collect::<Vec<_>>();
+        assert_eq!(common(great_grandchild, children[1]), vec![root_token]);
+        assert_eq!(common(children[1], great_grandchild), vec![root_token]);
+        assert_eq!(common(great_grandchild, children[0]),
+                   vec![children[0], root_token]);
+        assert_eq!(common(grandchild, grandchild),
+                   vec![grandchild, children[0], root_token]);
+        assert!(common(great_grandchild, other_root).is_empty());
+        assert_eq!(common(other_root, other_root), vec![other_root]);
+    }
+
+    #[test]
+    fn route_to() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchildren = children[0].append_children(&mut arena, 4..6usize);
+        let leaf = grandchildren[1].append(&mut arena, 6usize);
+        let cousin = children[2].append(&mut arena, 7usize);
+        let other_root = arena.new_node(8usize);
+
+        let depth = |t: Token| t.ancestors_tokens(&arena).count();
+        let tokens: Vec<_> = root_token.subtree_tokens(&arena,
+                                                       TraversalOrder::Pre)
+            .collect();
+        for &a in tokens.iter() {
+            for &b in tokens.iter() {
+                let route = a.route_to(&arena, b).unwrap();
+                let lca = a.common_ancestors(&arena, b).next().unwrap();
+                assert_eq!(route.len(), depth(a) + depth(b) - 2 * depth(lca));
+                let end = route.iter().fold(a, |t, step| match *step {
+                    Move::Up => arena[t].parent.unwrap(),
+                    Move::Down(child) => {
+                        assert_eq!(arena[child].parent, Some(t));
+                        child
+                    }
+                });
+                assert_eq!(end, b);
+            }
+        }
+
+        assert_eq!(leaf.route_to(&arena, cousin).unwrap(),
+                   vec![Move::Up, Move::Up, Move::Up, Move::Down(children[2]),
+                        Move::Down(cousin)]);
+        assert_eq!(leaf.route_to(&arena, children[0]).unwrap(),
+                   vec![Move::Up, Move::Up]);
+        assert!(leaf.route_to(&arena, leaf).unwrap().is_empty());
+        assert!(leaf.route_to(&arena, other_root).is_none());
+    }
+
+    #[test]
+    fn rotate_children() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..6usize);
+        let data = |arena: &Arena<usize>| root_token.children(arena)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+
+        root_token.rotate_children(&mut arena, 2);
+        assert_eq!(data(&arena), vec![3, 4, 5, 1, 2]);
+        assert_eq!(arena[root_token].first_child(), Some(children[2]));
+        assert_eq!(arena[root_token].last_child(), Some(children[1]));
+        assert!(arena.validate().is_ok());
+
+        root_token.rotate_children(&mut arena, -1);
+        assert_eq!(data(&arena), vec![2, 3, 4, 5, 1]);
+        root_token.rotate_children(&mut arena, 12);
+        assert_eq!(data(&arena), vec![4, 5, 1, 2, 3]);
+        root_token.rotate_children(&mut arena, -7);
+        assert_eq!(data(&arena), vec![2, 3, 4, 5, 1]);
+        root_token.rotate_children(&mut arena, 5);
+        assert_eq!(data(&arena), vec![2, 3, 4, 5, 1]);
+        assert!(arena.validate().is_ok());
+
+        children[0].rotate_children(&mut arena, 3);
+        assert!(children[0].is_leaf(&arena));
+        let only_child = children[0].append(&mut arena, 10usize);
+        children[0].rotate_children(&mut arena, -3);
+        assert_eq!(arena[children[0]].first_child(), Some(only_child));
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn ancestors_until() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let child = root_token.append(&mut arena, 2usize);
+        let grandchild = child.append(&mut arena, 3usize);
+        let leaf = grandchild.append(&mut arena, 4usize);
+        let sibling = root_token.append(&mut arena, 5usize);
+
+        let until = |token: Token, stop| token.ancestors_until(&arena, stop)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+        assert!(until(leaf, grandchild).is_empty());
+        assert_eq!(until(leaf, child), vec![3]);
+        assert_eq!(until(leaf, root_token), vec![3, 2]);
+        assert_eq!(until(leaf, sibling), vec![3, 2, 1]);
+        assert_eq!(until(leaf, leaf), vec![3, 2, 1]);
+        assert!(until(root_token, child).is_empty());
+
+        let tokens: Vec<_> = leaf.ancestors_until_tokens(&arena, sibling)
+            .collect();
+        let all: Vec<_> = leaf.ancestors_tokens(&arena).collect();
+        assert_eq!(tokens, all);
+    }
+
+    #[test]
+    fn collect_subtree_tokens() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let children = root_token.append_children(&mut arena, 2..10usize);
+        children[0].append_children(&mut arena, 10..12usize);
+
+        let mut buf = Vec::new();
+        root_token.collect_subtree_tokens(&arena, TraversalOrder::Level, &mut buf);
+        let expected: Vec<_> = root_token
+            .subtree_tokens(&arena, TraversalOrder::Level)
+            .collect();
+        assert_eq!(buf, expected);
+        let capacity = buf.capacity();
+
+        children[0].collect_subtree_tokens(&arena, TraversalOrder::Post, &mut buf);
+        let expected: Vec<_> = children[0]
+            .subtree_tokens(&arena, TraversalOrder::Post)
+            .collect();
+        assert_eq!(buf, expected);
+        assert_eq!(buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn link_accessors() {
+        let (mut arena, root_token) = Arena::with_data(1usize);
+        let children = root_token.append_children(&mut arena, 2..5usize);
+
+        assert!(root_token.parent(&arena).is_none());
+        assert!(root_token.previous_sibling(&arena).is_none());
+        assert!(root_token.next_sibling(&arena).is_none());
+
+        assert!(children[0].previous_sibling(&arena).is_none());
+        assert_eq!(children[0].next_sibling(&arena), Some(children[1]));
+        assert_eq!(children[1].previous_sibling(&arena), Some(children[0]));
+        assert_eq!(children[1].next_sibling(&arena), Some(children[2]));
+        assert_eq!(children[2].previous_sibling(&arena), Some(children[1]));
+        assert!(children[2].next_sibling(&arena).is_none());
+        for &child in children.iter() {
+            assert_eq!(child.parent(&arena), Some(root_token));
+        }
+    }
 
-        // How about the other way around (replacing the slavic branch instead
-        slavic.replace_node(&mut arena, germanic).unwrap();
+    #[test]
+    fn fold_path() {
+        let (mut arena, root_token) = Arena::with_data("a");
+        let b = root_token.append(&mut arena, "b");
+        let c = b.append(&mut arena, "c");
+        b.append(&mut arena, "d");
+        let e = c.append(&mut arena, "e");
 
-        let mut iter = root.subtree(&arena, TraversalOrder::Pre)
-            .map(|x| x.data);
-        assert_eq!(iter.next(), Some("Indo-European"));
-        assert_eq!(iter.next(), Some("Romance"));
-        assert_eq!(iter.next(), Some("French"));
-        assert_eq!(iter.next(), Some("Italian"));
-        assert_eq!(iter.next(), Some("Germanic"));
-        assert_eq!(iter.next(), Some("West"));
-        assert_eq!(iter.next(), Some("Scots"));
-        assert_eq!(iter.next(), Some("English"));
-        assert!(iter.next().is_none());
+        let path = |token: Token| token.fold_path(&arena, String::new(),
+                                           |path, x| path + "/" + x);
+        assert_eq!(path(root_token), "/a");
+        assert_eq!(path(c), "/a/b/c");
+        assert_eq!(path(e), "/a/b/c/e");
+
+        let depth = e.fold_path(&arena, 0, |depth, _| depth + 1);
+        assert_eq!(depth, 4);
     }
 
     #[test]
-    fn subtree_tokens_postord() {
-        let root_data = 1usize;
-        let (mut arena, root_token) = Arena::with_data(root_data);
-       
-        let first_child = root_token.append(&mut arena, 2usize);
-        let second_child = root_token.append(&mut arena, 3usize);
-        let third_child = root_token.append(&mut arena, 4usize);
-        let first_grandchild = first_child.append(&mut arena, 0usize);
-        let fourth_child = root_token.append(&mut arena, 5usize);
-        let second_grandchild = second_child.append(&mut arena, 10usize);
-        let third_grandchild = second_child.append(&mut arena, 20usize);
-        let great_grandchild = third_grandchild.append(&mut arena, 20usize);
-       
-        let mut subtree = root_token.subtree_tokens(&arena, TraversalOrder::Post);
-        assert_eq!(subtree.next(), Some(first_grandchild));
-        assert_eq!(subtree.next(), Some(first_child));
-        assert_eq!(subtree.next(), Some(second_grandchild));
-        assert_eq!(subtree.next(), Some(great_grandchild));
-        assert_eq!(subtree.next(), Some(third_grandchild));
-        assert_eq!(subtree.next(), Some(second_child));
-        assert_eq!(subtree.next(), Some(third_child));
-        assert_eq!(subtree.next(), Some(fourth_child));
-        assert_eq!(subtree.next(), Some(root_token));
-        assert!(subtree.next().is_none());
-       
-        let mut subtree = great_grandchild.subtree_tokens(&arena, TraversalOrder::Post);
-        assert_eq!(subtree.next(), Some(great_grandchild));
-        assert!(subtree.next().is_none());
+    fn data_projections() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        children[1].append_children(&mut arena, 4..6usize);
+        children[2].append(&mut arena, 6usize);
+
+        let children_data: Vec<_> = root_token.children_data(&arena).collect();
+        let expected: Vec<_> = root_token.children(&arena)
+            .map(|x| &x.data)
+            .collect();
+        assert_eq!(children_data, expected);
+        let reversed: Vec<_> = root_token.children_data(&arena).rev().collect();
+        assert_eq!(reversed, vec![&3, &2, &1]);
+        for &order in [TraversalOrder::Pre, TraversalOrder::Post,
+                       TraversalOrder::Level].iter() {
+            let data: Vec<_> = root_token.subtree_data(&arena, order).collect();
+            let expected: Vec<_> = root_token.subtree(&arena, order)
+                .map(|x| &x.data)
+                .collect();
+            assert_eq!(data, expected);
+            assert_eq!(arena[root_token].subtree_data(&arena, order)
+                           .collect::<Vec<_>>(),
+                       expected);
+        }
+
+        arena.freeze_subtree(children[1]);
+        for x in root_token.children_data_mut(&mut arena) { *x += 10 }
+        for x in children[1].subtree_data_mut(&mut arena, TraversalOrder::Pre) {
+            *x += 100;
+        }
+        let data: Vec<_> = root_token.subtree_data(&arena, TraversalOrder::Pre)
+            .copied()
+            .collect();
+        assert_eq!(data, vec![0, 11, 2, 4, 5, 13, 6]);
     }
 
     #[test]
-    fn subtree_tokens_levelord() {
-        let root_data = 1usize;
-        let (mut arena, root_token) = Arena::with_data(root_data);
-       
-        let first_child = root_token.append(&mut arena, 2usize);
-        let second_child = root_token.append(&mut arena, 3usize);
-        let third_child = root_token.append(&mut arena, 4usize);
-        let first_grandchild = second_child.append(&mut arena, 10usize);
-        let second_grandchild = second_child.append(&mut arena, 20usize);
-        let fourth_child = root_token.append(&mut arena, 5usize);
-       
-        let mut subtree = root_token.subtree_tokens(&arena, TraversalOrder::Level);
-        assert_eq!(subtree.next(), Some(root_token));
-        assert_eq!(subtree.next(), Some(first_child));
-        assert_eq!(subtree.next(), Some(second_child));
-        assert_eq!(subtree.next(), Some(third_child));
-        assert_eq!(subtree.next(), Some(fourth_child));
-        assert_eq!(subtree.next(), Some(first_grandchild));
-        assert_eq!(subtree.next(), Some(second_grandchild));
-        assert!(subtree.next().is_none());
-       
-        let mut subtree = second_grandchild.subtree_tokens(&arena, TraversalOrder::Level);
-        assert_eq!(subtree.next(), Some(second_grandchild));
-        assert!(subtree.next().is_none());
+    fn subtree_size_hint() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchild = children[1].append(&mut arena, 4usize);
+        grandchild.append(&mut arena, 5usize);
+        for token in root_token.subtree_tokens(&arena, TraversalOrder::Pre)
+            .collect::<Vec<_>>() {
+            assert!(token.subtree_size_hint(&arena) >= token.subtree_size(&arena));
+        }
+        assert_eq!(root_token.subtree_size_hint(&arena), 6);
+        // another tree in the arena makes the bound loose
+        let other_root = arena.new_node(6usize);
+        assert_eq!(other_root.subtree_size_hint(&arena), 7);
+        assert_eq!(other_root.subtree_size(&arena), 1);
+
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let mut token = root_token;
+        for i in 1..5 { token = token.append(&mut arena, i) }
+        for token in root_token.subtree_tokens(&arena, TraversalOrder::Pre)
+            .collect::<Vec<_>>() {
+            assert_eq!(token.subtree_size_hint(&arena),
+                       token.subtree_size(&arena));
+        }
     }
 
     #[test]
-    fn subtree_postord() {
-        let root_data = "Indo-European";
-        let (mut arena, root_token) = Arena::with_data(root_data);
-       
-        root_token.append(&mut arena, "Romance");
-        root_token.append(&mut arena, "Germanic");
-        let third_child = root_token.append(&mut arena, "Celtic");
-        root_token.append(&mut arena, "Slavic");
-        third_child.append(&mut arena, "Ulster");
-        third_child.append(&mut arena, "Gaulish");
-       
-        let mut subtree = root_token.subtree(&arena, TraversalOrder::Post);
-        assert_eq!(subtree.next().unwrap().data, "Romance");
-        assert_eq!(subtree.next().unwrap().data, "Germanic");
-        assert_eq!(subtree.next().unwrap().data, "Ulster");
-        assert_eq!(subtree.next().unwrap().data, "Gaulish");
-        assert_eq!(subtree.next().unwrap().data, "Celtic");
-        assert_eq!(subtree.next().unwrap().data, "Slavic");
-        assert_eq!(subtree.next().unwrap().data, "Indo-European");
-        assert!(subtree.next().is_none());
+    fn wrap() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        children[1].append(&mut arena, 4usize);
+        let data = |arena: &Arena<usize>, token: Token| token
+            .subtree(arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+
+        // a middle child
+        let wrapper = children[1].wrap(&mut arena, 10);
+        assert_eq!(data(&arena, root_token), vec![0, 1, 10, 2, 4, 3]);
+        assert_eq!(arena[wrapper].parent(), Some(root_token));
+        assert_eq!(arena[wrapper].previous_sibling(), Some(children[0]));
+        assert_eq!(arena[wrapper].next_sibling(), Some(children[2]));
+        assert!(children[1].is_only_child(&arena));
+        assert!(arena.validate().is_ok());
+
+        // the first and the last children
+        let first = children[0].wrap(&mut arena, 11);
+        assert_eq!(arena[root_token].first_child(), Some(first));
+        let last = children[2].wrap(&mut arena, 12);
+        assert_eq!(arena[root_token].last_child(), Some(last));
+        assert_eq!(data(&arena, root_token), vec![0, 11, 1, 10, 2, 4, 12, 3]);
+        assert!(arena.validate().is_ok());
+
+        // a root
+        let new_root = root_token.wrap(&mut arena, 13);
+        assert!(new_root.is_root(&arena));
+        assert_eq!(arena[root_token].parent(), Some(new_root));
+        assert_eq!(data(&arena, new_root), vec![13, 0, 11, 1, 10, 2, 4, 12, 3]);
+        assert!(arena.validate().is_ok());
     }
 
     #[test]
-    fn subtree_levelord() {
-        let root_data = "Indo-European";
-        let (mut arena, root_token) = Arena::with_data(root_data);
-       
-        root_token.append(&mut arena, "Romance");
-        root_token.append(&mut arena, "Germanic");
-        let third_child = root_token.append(&mut arena, "Slavic");
-        root_token.append(&mut arena, "Hellenic");
-        third_child.append(&mut arena, "Russian");
-        third_child.append(&mut arena, "Ukrainian");
-       
-        let mut subtree = root_token.subtree(&arena, TraversalOrder::Level);
-        assert_eq!(subtree.next().unwrap().data, "Indo-European");
-        assert_eq!(subtree.next().unwrap().data, "Romance");
-        assert_eq!(subtree.next().unwrap().data, "Germanic");
-        assert_eq!(subtree.next().unwrap().data, "Slavic");
-        assert_eq!(subtree.next().unwrap().data, "Hellenic");
-        assert_eq!(subtree.next().unwrap().data, "Russian");
-        assert_eq!(subtree.next().unwrap().data, "Ukrainian");
-        assert!(subtree.next().is_none());
+    fn unwrap_node() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchildren = children[1].append_children(&mut arena, 4..7usize);
+        grandchildren[1].append(&mut arena, 7usize);
+        let data = |arena: &Arena<usize>, token: Token| token
+            .subtree(arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect::<Vec<_>>();
+
+        assert_eq!(children[1].unwrap_node(&mut arena), Some(2));
+        assert!(!arena.contains(children[1]));
+        let order: Vec<_> = root_token.children_tokens(&arena).collect();
+        assert_eq!(order, vec![children[0], grandchildren[0], grandchildren[1],
+                               grandchildren[2], children[2]]);
+        assert_eq!(data(&arena, root_token), vec![0, 1, 4, 5, 7, 6, 3]);
+        assert!(arena.validate().is_ok());
+
+        // first and last children, and a leaf
+        children[0].unwrap_node(&mut arena);
+        children[2].unwrap_node(&mut arena);
+        assert_eq!(arena[root_token].first_child(), Some(grandchildren[0]));
+        assert_eq!(arena[root_token].last_child(), Some(grandchildren[2]));
+        assert!(arena.validate().is_ok());
+
+        // wrap and unwrap_node undo each other
+        let wrapper = grandchildren[1].wrap(&mut arena, 8);
+        assert_eq!(data(&arena, root_token), vec![0, 4, 8, 5, 7, 6]);
+        assert_eq!(wrapper.unwrap_node(&mut arena), Some(8));
+        assert_eq!(data(&arena, root_token), vec![0, 4, 5, 7, 6]);
+
+        // the children of a root become roots of their own
+        assert_eq!(root_token.unwrap_node(&mut arena), Some(0));
+        for &token in grandchildren.iter() {
+            assert!(token.is_root(&arena));
+            assert!(arena[token].next_sibling().is_none());
+            assert!(arena[token].previous_sibling().is_none());
+        }
+        assert_eq!(data(&arena, grandchildren[1]), vec![5, 7]);
+        assert!(arena.validate().is_ok());
+        assert_eq!(root_token.unwrap_node(&mut arena), None);
     }
 
     #[test]
-    fn subtree_postord_mut() {
-        let root_data = 1usize;
-        let (mut arena, root_token) = Arena::with_data(root_data);
-       
-        root_token.append(&mut arena, 2usize);
-        root_token.append(&mut arena, 3usize);
-        let third_child = root_token.append(&mut arena, 4usize);
-        root_token.append(&mut arena, 5usize);
-        third_child.append(&mut arena, 10usize);
-        third_child.append(&mut arena, 20usize);
-       
-        for x in root_token.subtree_mut(&mut arena, TraversalOrder::Post) {
-            x.data += 100;
+    fn remove_children() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        children[0].append_children(&mut arena, 4..6usize);
+        let grandchild = children[2].append(&mut arena, 6usize);
+        grandchild.append(&mut arena, 7usize);
+        let other_root = arena.new_node(8usize);
+
+        let count = arena.node_count();
+        let size = children[2].subtree_size(&arena);
+        assert_eq!(children[2].remove_children(&mut arena), vec![6, 7]);
+        assert!(children[2].is_leaf(&arena));
+        assert_eq!(arena.node_count(), count - (size - 1));
+        assert!(!arena.contains(grandchild));
+        assert!(arena.validate().is_ok());
+
+        assert!(children[1].remove_children(&mut arena).is_empty());
+        let count = arena.node_count();
+        let size = root_token.subtree_size(&arena);
+        assert_eq!(root_token.remove_children(&mut arena), vec![1, 4, 5, 2, 3]);
+        assert!(root_token.is_leaf(&arena));
+        assert_eq!(arena.node_count(), count - (size - 1));
+        assert!(arena.contains(other_root));
+        assert!(arena.validate().is_ok());
+
+        // the freed cells are reused
+        let capacity = arena.capacity();
+        root_token.append_children(&mut arena, 10..15usize);
+        assert_eq!(arena.capacity(), capacity);
+    }
+
+    #[test]
+    fn graft() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchild = children[0].append(&mut arena, 4usize);
+        let great_grandchild = grandchild.append(&mut arena, 5usize);
+        let count = arena.node_count();
+
+        children[0].detach(&mut arena);
+        children[2].graft(&mut arena, children[0]).unwrap();
+        assert_eq!(arena[children[0]].parent(), Some(children[2]));
+        assert_eq!(arena[children[2]].last_child(), Some(children[0]));
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![0, 2, 3, 1, 4, 5]);
+        assert_eq!(arena.node_count(), count);
+        assert!(arena.validate().is_ok());
+
+        // not a root
+        match children[1].graft(&mut arena, grandchild) {
+            Err(Error::NotARootNode) => (),
+            _ => panic!("grafted a node that has a parent")
+        }
+        // cycles
+        grandchild.detach(&mut arena);
+        match great_grandchild.graft(&mut arena, grandchild) {
+            Err(Error::CyclicGraph) => (),
+            _ => panic!("grafted a tree under its own descendant")
+        }
+        match grandchild.graft(&mut arena, grandchild) {
+            Err(Error::CyclicGraph) => (),
+            _ => panic!("grafted a tree under itself")
+        }
+        assert!(grandchild.is_root(&arena));
+        assert!(arena.validate().is_ok());
+
+        // frozen
+        arena.freeze_subtree(children[1]);
+        match children[1].graft(&mut arena, grandchild) {
+            Err(Error::FrozenNode) => (),
+            _ => panic!("grafted under a frozen node")
         }
-       
-        let mut subtree = root_token.subtree(&arena, TraversalOrder::Post);
-        assert_eq!(subtree.next().unwrap().data, 102);
-        assert_eq!(subtree.next().unwrap().data, 103);
-        assert_eq!(subtree.next().unwrap().data, 110);
-        assert_eq!(subtree.next().unwrap().data, 120);
-        assert_eq!(subtree.next().unwrap().data, 104);
-        assert_eq!(subtree.next().unwrap().data, 105);
-        assert_eq!(subtree.next().unwrap().data, 101);
-        assert!(subtree.next().is_none());
     }
 
     #[test]
-    fn subtree_levelord_mut() {
-        let root_data = 1usize;
-        let (mut arena, root_token) = Arena::with_data(root_data);
-       
-        root_token.append(&mut arena, 2usize);
-        root_token.append(&mut arena, 3usize);
-        let third_child = root_token.append(&mut arena, 4usize);
-        root_token.append(&mut arena, 5usize);
-        third_child.append(&mut arena, 10usize);
-        third_child.append(&mut arena, 20usize);
-       
-        for x in root_token.subtree_mut(&mut arena, TraversalOrder::Level) {
-            x.data += 100;
+    fn replace_subtree() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..4usize);
+        let grandchildren = children[1].append_children(&mut arena, 4..6usize);
+        grandchildren[0].append(&mut arena, 6usize);
+        let new_root = arena.new_node(10usize);
+        new_root.append_children(&mut arena, 11..13usize);
+        let count = arena.node_count();
+
+        let removed = children[1].replace_subtree(&mut arena, new_root).unwrap();
+        assert_eq!(removed, vec![2, 4, 6, 5]);
+        assert_eq!(arena.node_count(), count - 4);
+        assert!(!arena.contains(children[1]));
+        assert!(!arena.contains(grandchildren[0]));
+        assert_eq!(arena[new_root].parent(), Some(root_token));
+        assert_eq!(arena[new_root].previous_sibling(), Some(children[0]));
+        assert_eq!(arena[new_root].next_sibling(), Some(children[2]));
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![0, 1, 10, 11, 12, 3]);
+        assert!(arena.validate().is_ok());
+
+        // the new tree has to be a free-standing one that does not contain the
+        // node being replaced
+        match children[0].replace_subtree(&mut arena, children[2]) {
+            Err(Error::NotARootNode) => (),
+            _ => panic!("replaced with a node that has a parent")
         }
-       
-        let mut subtree = root_token.subtree(&arena, TraversalOrder::Level);
-        assert_eq!(subtree.next().unwrap().data, 101);
-        assert_eq!(subtree.next().unwrap().data, 102);
-        assert_eq!(subtree.next().unwrap().data, 103);
-        assert_eq!(subtree.next().unwrap().data, 104);
-        assert_eq!(subtree.next().unwrap().data, 105);
-        assert_eq!(subtree.next().unwrap().data, 110);
-        assert_eq!(subtree.next().unwrap().data, 120);
-        assert!(subtree.next().is_none());
+        match children[0].replace_subtree(&mut arena, root_token) {
+            Err(Error::CyclicGraph) => (),
+            _ => panic!("replaced a node with its own ancestor")
+        }
+        match root_token.replace_subtree(&mut arena, root_token) {
+            Err(Error::CyclicGraph) => (),
+            _ => panic!("replaced a node with itself")
+        }
+        let data: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert_eq!(data, vec![0, 1, 10, 11, 12, 3]);
+
+        // a root can be replaced too
+        let other_root = arena.new_node(20usize);
+        let removed = root_token.replace_subtree(&mut arena, other_root).unwrap();
+        assert_eq!(removed, vec![0, 1, 10, 11, 12, 3]);
+        assert_eq!(arena.node_count(), 1);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn size_hints() {
+        fn check<I: Iterator>(mut iter: I) {
+            loop {
+                let (lower, upper) = iter.size_hint();
+                let rest = iter.by_ref().take(lower).count();
+                assert_eq!(rest, lower);
+                match iter.next() {
+                    None => break,
+                    Some(_) => assert!(upper.unwrap() > lower)
+                }
+            }
+        }
+
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let children = root_token.append_children(&mut arena, 1..6usize);
+        let grandchild = children[2].append(&mut arena, 6usize);
+        let leaf = grandchild.append(&mut arena, 7usize);
+        for &token in children.iter() {
+            check(token.following_siblings_tokens(&arena));
+            check(token.preceding_siblings(&arena));
+        }
+        check(root_token.children_tokens(&arena));
+        check(root_token.children_entries(&arena));
+        check(root_token.children_data(&arena));
+        check(root_token.children_rev(&arena));
+        check(leaf.ancestors_tokens(&arena));
+        check(leaf.ancestors(&arena));
+
+        let mut iter = root_token.children_tokens(&arena);
+        assert_eq!(iter.size_hint(), (2, Some(arena.node_count())));
+        iter.next();
+        iter.next_back();
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (1, Some(1)));
+        iter.next();
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(leaf.children_tokens(&arena).size_hint(), (0, Some(0)));
+
+        // whole arena iterators know exactly how many nodes there are
+        arena.uproot(children[4]);
+        assert_eq!(arena.iter().len(), arena.node_count());
+        let nodes: Vec<_> = arena.iter().collect();
+        assert_eq!(nodes.capacity(), arena.node_count());
+        let mut iter = arena.iter();
+        iter.next();
+        assert_eq!(iter.len(), arena.node_count() - 1);
+        assert_eq!(arena.iter_mut().size_hint(), (0, Some(arena.node_count())));
+        let count = arena.node_count();
+        let data: Vec<_> = arena.into_iter().collect();
+        assert_eq!(data.capacity(), count);
+    }
+
+    #[test]
+    fn nearest_ancestor() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let child = root_token.append(&mut arena, 1usize);
+        let grandchild = child.append(&mut arena, 2usize);
+        let leaf = grandchild.append(&mut arena, 3usize);
+
+        let even = |x: &usize| x.is_multiple_of(2);
+        assert_eq!(leaf.nearest_ancestor(&arena, even), Some(grandchild));
+        assert_eq!(grandchild.nearest_ancestor(&arena, even), Some(root_token));
+        assert_eq!(leaf.nearest_ancestor(&arena, |&x| x == 1), Some(child));
+        assert_eq!(leaf.nearest_ancestor(&arena, |&x| x > 3), None);
+        // the node itself does not count
+        assert_eq!(leaf.nearest_ancestor(&arena, |&x| x == 3), None);
+        assert_eq!(root_token.nearest_ancestor(&arena, |_| true), None);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn token_niche() {
+        use core::mem::size_of;
+        assert_eq!(size_of::<Option<Token>>(), size_of::<Token>());
+        assert_eq!(size_of::<Option<Token>>(), size_of::<usize>());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_subtree() {
+        use rayon::prelude::*;
+
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let mut tokens = vec![root_token];
+        for i in 1..500 {
+            let parent = tokens[(i - 1) / 4];
+            tokens.push(parent.append(&mut arena, i));
+        }
+        let other_root = arena.new_node(1000);
+        other_root.append(&mut arena, 1001);
+        let subtree = tokens[3];
+        let mut expected: Vec<_> = subtree.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        expected.sort();
+
+        let mut data: Vec<_> = subtree.par_subtree(&arena).map(|x| x.data).collect();
+        data.sort();
+        assert_eq!(data, expected);
+        let mut data: Vec<_> = subtree.par_descendants(&arena)
+            .map(|x| x.data)
+            .collect();
+        data.sort();
+        assert_eq!(data, expected[1..]);
+
+        let frozen = subtree.children_tokens(&arena).next().unwrap();
+        arena.freeze_subtree(frozen);
+        let frozen_data: Vec<_> = frozen.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        subtree.par_subtree_mut(&mut arena).for_each(|x| x.data += 10000);
+        for node in subtree.subtree(&arena, TraversalOrder::Pre) {
+            match frozen_data.contains(&(node.data)) {
+                true => assert!(node.data < 10000),
+                false => assert!(node.data >= 10000)
+            }
+        }
+        assert_eq!(arena[root_token].data, 0);
+        assert_eq!(arena[other_root].data, 1000);
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn insert_siblings() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let three = root_token.append(&mut arena, 3);
+        let one = three.insert_before(&mut arena, 1);
+        let five = three.insert_after(&mut arena, 5);
+        one.insert_after(&mut arena, 2);
+        five.insert_before(&mut arena, 4);
+        let six = five.insert_after(&mut arena, 6);
+        let zero = one.insert_before(&mut arena, 0);
+        let children: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+        assert_eq!(children, (0..7).collect::<Vec<_>>());
+        let rev: Vec<_> = root_token.children_rev(&arena).map(|x| x.data).collect();
+        assert_eq!(rev, (0..7).rev().collect::<Vec<_>>());
+        assert_eq!(arena[root_token].first_child(), Some(zero));
+        assert_eq!(arena[root_token].last_child(), Some(six));
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn prepend() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let last = root_token.prepend(&mut arena, 3);
+        assert_eq!(arena[root_token].last_child(), Some(last));
+        root_token.prepend(&mut arena, 2);
+        let first = root_token.prepend(&mut arena, 1);
+        root_token.append(&mut arena, 4);
+        let children: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+        assert_eq!(children, vec![1, 2, 3, 4]);
+        let rev: Vec<_> = root_token.children_rev(&arena).map(|x| x.data).collect();
+        assert_eq!(rev, vec![4, 3, 2, 1]);
+        assert_eq!(arena[root_token].first_child(), Some(first));
+        assert!(arena.validate().is_ok());
+    }
+
+    #[test]
+    fn insert_child_at() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let last = root_token.insert_child_at(&mut arena, 0, 4).unwrap();
+        root_token.insert_child_at(&mut arena, 0, 1).unwrap();
+        root_token.insert_child_at(&mut arena, 1, 3).unwrap();
+        root_token.insert_child_at(&mut arena, 1, 2).unwrap();
+        assert!(matches!(root_token.insert_child_at(&mut arena, 5, 6),
+                         Err(Error::IndexOutOfBounds)));
+        let end = root_token.insert_child_at(&mut arena, 4, 5).unwrap();
+        let children: Vec<_> = root_token.children(&arena).map(|x| x.data).collect();
+        assert_eq!(children, vec![1, 2, 3, 4, 5]);
+        assert_eq!(arena[last].next_sibling(), Some(end));
+        assert_eq!(arena[root_token].last_child(), Some(end));
+        assert_eq!(arena.node_count(), 6);
+        assert!(arena.validate().is_ok());
+
+        arena.freeze_subtree(root_token);
+        assert!(matches!(root_token.insert_child_at(&mut arena, 0, 0),
+                         Err(Error::FrozenNode)));
+    }
+
+    #[test]
+    fn replace_data() {
+        let (mut arena, root_token) = Arena::with_data(String::from("a"));
+        let child = root_token.append(&mut arena, String::from("b"));
+        let old = child.replace_data(&mut arena, String::from("c"));
+        assert_eq!(old, "b");
+        assert_eq!(arena[child].data, "c");
+        assert_eq!(arena[root_token].first_child(), Some(child));
+        assert_eq!(arena.node_count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Frozen node")]
+    fn replace_data_frozen() {
+        let (mut arena, root_token) = Arena::with_data(1);
+        arena.freeze_subtree(root_token);
+        root_token.replace_data(&mut arena, 2);
+    }
+
+    #[test]
+    fn prune() {
+        let (mut arena, root_token) = Arena::with_data(0usize);
+        let mut tokens = vec![root_token];
+        for i in 1..40 {
+            let parent = tokens[(i - 1) / 3];
+            tokens.push(parent.append(&mut arena, i));
+        }
+        let mut seen = Vec::new();
+        root_token.prune(&mut arena, |&x| { seen.push(x); x % 4 != 1 });
+        // 1 goes and takes 4..=6 along, whose children are never looked at
+        assert!(!seen.contains(&4) && !seen.contains(&13));
+        let kept: Vec<_> = root_token.subtree(&arena, TraversalOrder::Pre)
+            .map(|x| x.data)
+            .collect();
+        assert!(kept.iter().all(|&x| x % 4 != 1));
+        let kept_ancestors = |x: usize| tokens[x].ancestors(&arena)
+            .all(|node| node.data % 4 != 1);
+        assert!(kept.iter().all(|&x| kept_ancestors(x)));
+        assert_eq!(arena.node_count(), kept.len());
+        assert!(arena.validate().is_ok());
+
+        root_token.prune(&mut arena, |_| false);
+        assert_eq!(arena.node_count(), 1);
+    }
+
+    #[test]
+    fn to_dot() {
+        let (mut arena, root_token) = Arena::with_data("root");
+        let a = root_token.append(&mut arena, "a");
+        a.append(&mut arena, "b");
+        root_token.append(&mut arena, "c");
+
+        assert_eq!(a.to_dot(&arena, |data| data.to_string()),
+                   "digraph {\n    2 [label=\"a\"];\n    \
+                    3 [label=\"b\"];\n    2 -> 3;\n}\n");
+        assert_eq!(root_token.to_dot(&arena, |data| data.to_uppercase()),
+                   arena.to_dot(root_token, |data| data.to_uppercase()));
     }
 
     #[test]